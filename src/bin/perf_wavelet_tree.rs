@@ -366,7 +366,7 @@ fn main() {
     let select_queries = gen_select_queries(args.n_queries, &text);
 
     let output_filename = input_filename.clone() + ".256.qwt";
-    let ds = load_or_build_and_save_qwt::<QWT256<_>>(&output_filename, &text);
+    let ds = load_or_build_and_save_qwt::<QWT256<_>>(&output_filename, &text).unwrap();
 
     if args.test_correctness {
         test_correctness(&ds, &text);
@@ -394,7 +394,7 @@ fn main() {
     // TODO: make this a macro!
 
     let output_filename = input_filename.clone() + ".256Pfs.qwt";
-    let ds = load_or_build_and_save_qwt::<QWT256Pfs<_>>(&output_filename, &text);
+    let ds = load_or_build_and_save_qwt::<QWT256Pfs<_>>(&output_filename, &text).unwrap();
 
     if args.test_correctness {
         test_correctness(&ds, &text);
@@ -421,7 +421,7 @@ fn main() {
     }
 
     let output_filename = input_filename.clone() + ".512.qwt";
-    let ds = load_or_build_and_save_qwt::<QWT512<_>>(&output_filename, &text);
+    let ds = load_or_build_and_save_qwt::<QWT512<_>>(&output_filename, &text).unwrap();
 
     if args.test_correctness {
         test_correctness(&ds, &text);
@@ -448,7 +448,7 @@ fn main() {
     }
 
     let output_filename = input_filename.clone() + ".512Pfs.qwt";
-    let ds = load_or_build_and_save_qwt::<QWT512Pfs<_>>(&output_filename, &text);
+    let ds = load_or_build_and_save_qwt::<QWT512Pfs<_>>(&output_filename, &text).unwrap();
 
     if args.test_correctness {
         test_correctness(&ds, &text);
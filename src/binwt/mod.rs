@@ -0,0 +1,504 @@
+//! This module implements a binary Wavelet Matrix, providing implementations of [`AccessUnsigned`], [`RankUnsigned`], and [`SelectUnsigned`] for a vector of unsigned integers.
+//!
+//! The binary Wavelet Matrix supports the same operations as the Quad Wavelet Tree:
+//!
+//! - `get(i)`: Accesses the `i`-th symbol of the indexed sequence.
+//! - `rank(s, i)`: Counts the number of occurrences of symbol `s` up to position `i`, excluding `i`.
+//! - `select(s, i)`: Returns the position of the `i+1`-th occurrence of symbol `s`.
+//!
+//! Each level is a binary vector indexed by the generic `BV`, which is either [`RSNarrow`](crate::RSNarrow) or [`RSWide`](crate::RSWide).
+//! We have two aliases types: `BWMNarrow<T>` and `BWMWide<T>`. The generic type `T` is the type of the indexed unsigned integer values.
+//!
+//! The binary Wavelet Matrix is mainly a baseline for the Quad Wavelet Tree, which is usually faster because it has half the levels.
+//! Since both implement the same traits, switching between the two is just a matter of changing a type alias.
+//!
+//! ## Performance
+//!
+//! All operations run in $$\Theta(\log \sigma)$$ time, where $$\sigma$$ is the alphabet size, i.e., one plus the largest symbol in the sequence. The space usage is $$n \lceil\log \sigma\rceil + o(n \log \sigma )$$ bits.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use qwt::{BWMNarrow, AccessUnsigned, RankUnsigned, SelectUnsigned};
+//!
+//! let bwm = BWMNarrow::try_from(vec![1_u32, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+//!
+//! assert_eq!(bwm.get(3), Some(4));
+//! assert_eq!(bwm.rank(3, 7), Some(1));
+//! assert_eq!(bwm.select(3, 0), Some(2));
+//! ```
+
+use crate::utils::{msb, stable_partition_of_2};
+use crate::WTIndexable;
+use crate::{AccessBin, AccessUnsigned, RankBin, RankUnsigned, SelectBin, SelectUnsigned};
+use crate::{BitVector, Error, SpaceUsage};
+
+use serde::{Deserialize, Serialize};
+
+use num_traits::AsPrimitive;
+
+/// Alias for the trait bounds to be satisfied by a data structure
+/// to support `rank` and `select` queries at each level of the binary wavelet matrix.
+pub trait BinRSforWT:
+    TryFrom<BitVector, Error = Error> + AccessBin + RankBin + SelectBin + SpaceUsage + Default
+{
+}
+
+// Generic implementation for any T
+impl<T> BinRSforWT for T where
+    T: TryFrom<BitVector, Error = Error> + AccessBin + RankBin + SelectBin + SpaceUsage + Default
+{
+}
+
+/// The generic BV is the data structure we use to index the binary vector
+/// of each level to support `access`, `rank`, and `select` queries.
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct BinaryWaveletMatrix<T, BV> {
+    n: usize,            // The length of the represented sequence
+    n_levels: usize,     // The number of levels of the wavelet matrix
+    sigma: T, // The largest symbol in the sequence. *NOTE*: It's not +1 because it may overflow
+    bvs: Vec<BV>, // A binary vector for each level
+    n_zeros: Vec<usize>, // The number of zeros in each level
+}
+
+impl<T, BV> BinaryWaveletMatrix<T, BV>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+    BV: BinRSforWT,
+{
+    /// Builds the binary wavelet matrix of the `sequence` of unsigned integers.
+    /// The input `sequence`` will be **destroyed**.
+    ///
+    /// Both space usage and query time depend on the length of the binary
+    /// representation of the largest value in the sequence.
+    ///
+    /// ## Panics
+    /// Panics if the sequence is longer than the largest length supported by `BV`.
+    /// Use [`BinaryWaveletMatrix::try_new`] to get an error instead.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::BWMNarrow;
+    ///
+    /// let mut data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let bwm = BWMNarrow::new(&mut data);
+    ///
+    /// assert_eq!(bwm.len(), 8);
+    /// ```
+    #[must_use]
+    pub fn new(sequence: &mut [T]) -> Self {
+        match Self::try_new(sequence) {
+            Ok(bwm) => bwm,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Builds the binary wavelet matrix of the `sequence` of unsigned integers as
+    /// [`BinaryWaveletMatrix::new`] does, but returns an error instead of panicking.
+    /// The input `sequence`` will be **destroyed**.
+    ///
+    /// # Errors
+    /// Returns [`Error::TooLong`] if the sequence is longer than the largest length
+    /// supported by `BV`.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::BWMWide;
+    ///
+    /// let mut data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let bwm = BWMWide::try_new(&mut data).unwrap();
+    ///
+    /// assert_eq!(bwm.len(), 8);
+    /// ```
+    pub fn try_new(sequence: &mut [T]) -> Result<Self, Error> {
+        if sequence.is_empty() {
+            return Ok(Self {
+                n: 0,
+                n_levels: 0,
+                sigma: T::zero(),
+                bvs: Vec::new(),
+                n_zeros: Vec::new(),
+            });
+        }
+        let sigma = *sequence.iter().max().unwrap();
+        let n_levels = (msb(sigma) + 1) as usize;
+
+        let mut bvs = Vec::<BV>::with_capacity(n_levels);
+        let mut n_zeros = Vec::<usize>::with_capacity(n_levels);
+
+        for level in 0..n_levels {
+            let shift = n_levels - 1 - level;
+
+            let bv: BitVector = sequence
+                .iter()
+                .map(|&symbol| (symbol >> shift).as_() & 1 == 1)
+                .collect();
+
+            n_zeros.push(bv.count_zeros());
+            bvs.push(BV::try_from(bv)?);
+
+            stable_partition_of_2(sequence, shift);
+        }
+
+        Ok(Self {
+            n: sequence.len(),
+            n_levels,
+            sigma,
+            bvs,
+            n_zeros,
+        })
+    }
+
+    /// Returns the length of the indexed sequence.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::BWMNarrow;
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let bwm = BWMNarrow::try_from(data).unwrap();
+    ///
+    /// assert_eq!(bwm.len(), 8);
+    /// ```
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns the largest value in the sequence, or `None` if the sequence is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::BWMWide;
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let bwm = BWMWide::try_from(data).unwrap();
+    ///
+    /// assert_eq!(bwm.sigma(), Some(5));
+    /// ```
+    #[must_use]
+    pub fn sigma(&self) -> Option<T> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.sigma)
+        }
+    }
+
+    /// Checks if the indexed sequence is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::BWMNarrow;
+    ///
+    /// let bwm = BWMNarrow::<u8>::default();
+    ///
+    /// assert_eq!(bwm.is_empty(), true);
+    /// ```
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Returns the number of levels in the wavelet matrix, i.e., the number of
+    /// bits of the largest symbol.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::BWMNarrow;
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 255, 4, 5, 3];
+    ///
+    /// let bwm = BWMNarrow::try_from(data).unwrap();
+    ///
+    /// assert_eq!(bwm.n_levels(), 8);
+    /// ```
+    #[must_use]
+    pub fn n_levels(&self) -> usize {
+        self.n_levels
+    }
+
+    /// Maps the position `i` at `level` to the corresponding position at the next level,
+    /// following the `bit`.
+    #[inline(always)]
+    unsafe fn next_position(&self, level: usize, bit: bool, i: usize) -> usize {
+        let bv = self.bvs.get_unchecked(level);
+        if bit {
+            self.n_zeros.get_unchecked(level) + bv.rank1_unchecked(i)
+        } else {
+            bv.rank0_unchecked(i)
+        }
+    }
+}
+
+impl<T, BV> RankUnsigned for BinaryWaveletMatrix<T, BV>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+    BV: BinRSforWT,
+{
+    /// Returns the rank of `symbol` up to position `i` **excluded**.
+    ///
+    /// `None` is returned if `i` is out of bound or if `symbol` is not valid
+    /// (i.e., it is greater than or equal to the alphabet size).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{BWMNarrow, RankUnsigned};
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let bwm = BWMNarrow::try_from(data).unwrap();
+    ///
+    /// assert_eq!(bwm.rank(1, 2), Some(1));
+    /// assert_eq!(bwm.rank(3, 8), Some(1));
+    /// assert_eq!(bwm.rank(1, 0), Some(0));
+    /// assert_eq!(bwm.rank(1, 9), None);  // Too large position
+    /// assert_eq!(bwm.rank(6, 1), None);  // Too large symbol
+    /// ```
+    #[inline(always)]
+    fn rank(&self, symbol: Self::Item, i: usize) -> Option<usize> {
+        if i > self.n || symbol > self.sigma {
+            return None;
+        }
+
+        // SAFETY: Check the above guarantees we are not out of bound
+        Some(unsafe { self.rank_unchecked(symbol, i) })
+    }
+
+    /// Returns rank of `symbol` up to position `i` **excluded**.
+    ///
+    /// # Safety
+    /// Calling this method with a position `i` larger than the size of the sequence
+    /// of with invalid symbol is undefined behavior.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{BWMNarrow, RankUnsigned};
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let bwm = BWMNarrow::try_from(data).unwrap();
+    ///
+    /// unsafe {
+    ///     assert_eq!(bwm.rank_unchecked(1, 2), 1);
+    /// }
+    /// ```
+    #[inline(always)]
+    unsafe fn rank_unchecked(&self, symbol: Self::Item, i: usize) -> usize {
+        let mut cur_i = i;
+        let mut cur_p = 0;
+
+        for level in 0..self.n_levels {
+            let bit = (symbol >> (self.n_levels - 1 - level)).as_() & 1 == 1;
+
+            cur_p = self.next_position(level, bit, cur_p);
+            cur_i = self.next_position(level, bit, cur_i);
+        }
+
+        cur_i - cur_p
+    }
+}
+
+impl<T, BV> AccessUnsigned for BinaryWaveletMatrix<T, BV>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+    BV: BinRSforWT,
+{
+    type Item = T;
+
+    /// Returns the `i`-th symbol of the indexed sequence, `None` is returned if `i` is out of bound.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{BWMNarrow, AccessUnsigned};
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let bwm = BWMNarrow::try_from(data).unwrap();
+    ///
+    /// assert_eq!(bwm.get(2), Some(1));
+    /// assert_eq!(bwm.get(3), Some(0));
+    /// assert_eq!(bwm.get(8), None);
+    /// ```
+    #[inline(always)]
+    fn get(&self, i: usize) -> Option<Self::Item> {
+        if i >= self.n {
+            return None;
+        }
+        // SAFETY: Check before guarantees to not be out of bound
+        Some(unsafe { self.get_unchecked(i) })
+    }
+
+    /// Returns the `i`-th symbol of the indexed sequence.
+    ///
+    /// # Safety
+    /// Calling this method with a position `i` larger than the size of the sequence
+    /// is undefined behavior.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{BWMNarrow, AccessUnsigned};
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let bwm = BWMNarrow::try_from(data).unwrap();
+    ///
+    /// unsafe {
+    ///     assert_eq!(bwm.get_unchecked(2), 1);
+    /// }
+    /// ```
+    #[inline(always)]
+    unsafe fn get_unchecked(&self, i: usize) -> Self::Item {
+        let mut result = T::zero();
+
+        let mut cur_i = i;
+        for level in 0..self.n_levels {
+            let bit = self.bvs.get_unchecked(level).get_unchecked(cur_i);
+            result = (result << 1) | (bit as u8).as_();
+            cur_i = self.next_position(level, bit, cur_i);
+        }
+
+        result
+    }
+}
+
+impl<T, BV> SelectUnsigned for BinaryWaveletMatrix<T, BV>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+    BV: BinRSforWT,
+{
+    /// Returns the position of the `i+1`-th occurrence of symbol `symbol`.
+    ///
+    /// `None` is returned if the is no (i+1)th such occurrence for the symbol
+    /// or if `symbol` is not valid (i.e., it is greater than or equal to the alphabet size).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{BWMNarrow, SelectUnsigned};
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let bwm = BWMNarrow::try_from(data).unwrap();
+    ///
+    /// assert_eq!(bwm.select(1, 1), Some(2));
+    /// assert_eq!(bwm.select(0, 1), Some(3));
+    /// assert_eq!(bwm.select(0, 2), None);
+    /// assert_eq!(bwm.select(6, 0), None);
+    /// ```
+    #[inline(always)]
+    fn select(&self, symbol: Self::Item, i: usize) -> Option<usize> {
+        if symbol > self.sigma {
+            return None;
+        }
+
+        // SAFETY: `symbol` is valid and the positions are never out of bound
+        let (start, end) = unsafe {
+            let mut start = 0;
+            let mut end = self.n;
+            for level in 0..self.n_levels {
+                let bit = (symbol >> (self.n_levels - 1 - level)).as_() & 1 == 1;
+                start = self.next_position(level, bit, start);
+                end = self.next_position(level, bit, end);
+            }
+            (start, end)
+        };
+
+        if i >= end - start {
+            return None;
+        }
+
+        // SAFETY: the `i+1`-th occurrence exists, so each select is valid
+        let mut result = start + i;
+        for level in (0..self.n_levels).rev() {
+            let bit = (symbol >> (self.n_levels - 1 - level)).as_() & 1 == 1;
+            let bv = &self.bvs[level];
+            result = unsafe {
+                if bit {
+                    bv.select1_unchecked(result - self.n_zeros[level])
+                } else {
+                    bv.select0_unchecked(result)
+                }
+            };
+        }
+
+        Some(result)
+    }
+
+    /// Returns the position of the `i+1`-th occurrence of symbol `symbol`.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method with a value of `i` larger than the number of occurrences
+    /// of the `symbol`, or if the `symbol` is not valid, is undefined behavior.
+    ///
+    /// In the current implementation, there is no efficiency reason to prefer this
+    /// unsafe `select` over the safe one.
+    #[inline(always)]
+    unsafe fn select_unchecked(&self, symbol: Self::Item, i: usize) -> usize {
+        self.select(symbol, i).unwrap()
+    }
+}
+
+impl<T, BV: SpaceUsage> SpaceUsage for BinaryWaveletMatrix<T, BV> {
+    /// Gives the space usage in bytes of the struct.
+    fn space_usage_byte(&self) -> usize {
+        8 + 8
+            + self
+                .bvs
+                .iter()
+                .fold(0, |acc, ds| acc + ds.space_usage_byte())
+            + self.n_zeros.space_usage_byte()
+    }
+}
+
+impl<T, BV> AsRef<BinaryWaveletMatrix<T, BV>> for BinaryWaveletMatrix<T, BV> {
+    fn as_ref(&self) -> &BinaryWaveletMatrix<T, BV> {
+        self
+    }
+}
+
+impl<T, BV> FromIterator<T> for BinaryWaveletMatrix<T, BV>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+    BV: BinRSforWT,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        BinaryWaveletMatrix::new(&mut iter.into_iter().collect::<Vec<T>>())
+    }
+}
+
+impl<T, BV> TryFrom<Vec<T>> for BinaryWaveletMatrix<T, BV>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+    BV: BinRSforWT,
+{
+    type Error = Error;
+
+    fn try_from(mut v: Vec<T>) -> Result<Self, Error> {
+        BinaryWaveletMatrix::try_new(&mut v[..])
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,156 @@
+use super::*;
+use crate::perf_and_test_utils::gen_sequence;
+use crate::{RRRBitVector, RSNarrow, RSWide};
+
+fn test_bwm<BV: BinRSforWT>(sequence: &[u8]) {
+    let sigma = *sequence.iter().max().unwrap() as usize + 1;
+    let bwm = BinaryWaveletMatrix::<_, BV>::new(&mut sequence.to_vec());
+
+    assert_eq!(bwm.len(), sequence.len());
+
+    let mut ranks = vec![0; sigma];
+    for (i, &symbol) in sequence.iter().enumerate() {
+        let rank = ranks[symbol as usize];
+        assert_eq!(bwm.get(i), Some(symbol));
+        assert_eq!(bwm.rank(symbol, i), Some(rank));
+        assert_eq!(bwm.select(symbol, rank), Some(i));
+        ranks[symbol as usize] += 1;
+    }
+
+    for symbol in 0..sigma as u8 {
+        let rank = ranks[symbol as usize];
+        assert_eq!(bwm.rank(symbol, sequence.len()), Some(rank));
+        assert_eq!(bwm.select(symbol, rank), None);
+    }
+
+    assert_eq!(bwm.get(sequence.len()), None);
+    assert_eq!(bwm.rank(0, sequence.len() + 1), None);
+}
+
+#[test]
+fn test_small() {
+    let data: [u8; 9] = [1, 0, 1, 0, 3, 4, 5, 3, 7];
+    let bwm = BinaryWaveletMatrix::<_, RSNarrow>::new(&mut data.clone());
+
+    assert_eq!(bwm.n_levels(), 3);
+    assert_eq!(bwm.rank(1, 4), Some(2));
+    assert_eq!(bwm.rank(8, 1), None); // too large symbol
+    assert_eq!(bwm.rank(7, 9), Some(1));
+    assert_eq!(bwm.rank(1, 10), None); // too large position
+
+    assert_eq!(bwm.select(8, 1), None); // too large symbol
+    assert_eq!(bwm.select(5, 0), Some(6));
+    assert_eq!(bwm.select(6, 0), None); // absent symbol
+
+    test_bwm::<RSNarrow>(&data);
+    test_bwm::<RSWide>(&data);
+    test_bwm::<RRRBitVector>(&data);
+}
+
+#[test]
+fn test_random() {
+    let n = 10_000;
+    for sigma in [1, 2, 3, 5, 8, 17, 128, 256] {
+        let sequence = gen_sequence(n, sigma);
+        test_bwm::<RSNarrow>(&sequence);
+        test_bwm::<RSWide>(&sequence);
+        test_bwm::<RRRBitVector>(&sequence);
+    }
+}
+
+#[test]
+fn test_empty() {
+    let bwm = BinaryWaveletMatrix::<u8, RSNarrow>::new(&mut []);
+
+    assert!(bwm.is_empty());
+    assert_eq!(bwm.sigma(), None);
+    assert_eq!(bwm.get(0), None);
+    assert_eq!(bwm.rank(0, 0), Some(0));
+    assert_eq!(bwm.select(0, 0), None);
+}
+
+// A rank and select support that indexes at most 100 bits.
+#[derive(Default, Debug)]
+struct SmallRS(RSNarrow);
+
+impl TryFrom<BitVector> for SmallRS {
+    type Error = Error;
+
+    fn try_from(bv: BitVector) -> Result<Self, Error> {
+        if bv.len() > 100 {
+            return Err(Error::TooLong {
+                len: bv.len(),
+                max_len: 100,
+            });
+        }
+        Ok(Self(RSNarrow::new(bv)))
+    }
+}
+
+impl AccessBin for SmallRS {
+    fn get(&self, i: usize) -> Option<bool> {
+        self.0.get(i)
+    }
+
+    unsafe fn get_unchecked(&self, i: usize) -> bool {
+        self.0.get_unchecked(i)
+    }
+}
+
+impl RankBin for SmallRS {
+    fn rank1(&self, i: usize) -> Option<usize> {
+        self.0.rank1(i)
+    }
+
+    unsafe fn rank1_unchecked(&self, i: usize) -> usize {
+        self.0.rank1_unchecked(i)
+    }
+}
+
+impl SelectBin for SmallRS {
+    fn select1(&self, i: usize) -> Option<usize> {
+        self.0.select1(i)
+    }
+
+    unsafe fn select1_unchecked(&self, i: usize) -> usize {
+        self.0.select1_unchecked(i)
+    }
+
+    fn select0(&self, i: usize) -> Option<usize> {
+        self.0.select0(i)
+    }
+
+    unsafe fn select0_unchecked(&self, i: usize) -> usize {
+        self.0.select0_unchecked(i)
+    }
+}
+
+impl SpaceUsage for SmallRS {
+    fn space_usage_byte(&self) -> usize {
+        self.0.space_usage_byte()
+    }
+}
+
+#[test]
+fn test_try_new() {
+    let sequence = gen_sequence(100, 16);
+    test_bwm::<SmallRS>(&sequence);
+
+    let bwm = BinaryWaveletMatrix::<_, SmallRS>::try_from(sequence.clone()).unwrap();
+    assert_eq!(bwm.len(), 100);
+
+    // The error of the support of a level is returned instead of panicking
+    let sequence = gen_sequence(101, 16);
+    assert!(matches!(
+        BinaryWaveletMatrix::<_, SmallRS>::try_new(&mut sequence.clone()),
+        Err(Error::TooLong {
+            len: 101,
+            max_len: 100
+        })
+    ));
+    let err = BinaryWaveletMatrix::<_, SmallRS>::try_from(sequence).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "the input has length 101 but the largest supported length is 100"
+    );
+}
@@ -0,0 +1,491 @@
+//! This module implements [`DynamicBitVector`], a bit vector that supports `access`,
+//! `rank`, and `select` queries together with insertions, deletions, and updates
+//! of bits at arbitrary positions.
+//!
+//! The bits are stored in the leaves of a balanced tree. Each leaf is a short
+//! sequence of `DataLine`s, i.e., the same lines of a [`BitVector`](super::BitVector),
+//! and each internal node stores the number of bits and the number of zeros and ones
+//! in the subtree of each of its children.
+//! All the operations take $$O(\log n)$$ time.
+//!
+//! ## Examples
+//!
+//! ```
+//! use qwt::{AccessBin, DynamicBitVector, RankBin, SelectBin};
+//!
+//! let mut dbv: DynamicBitVector = [true, false, true, true].into_iter().collect();
+//!
+//! dbv.insert(1, true);
+//! assert_eq!(dbv.get(1), Some(true));
+//! assert_eq!(dbv.rank1(5), Some(4));
+//!
+//! assert!(dbv.remove(0));
+//! dbv.set(0, false);
+//! assert_eq!(dbv.select0(1), Some(1));
+//! assert_eq!(dbv.len(), 4);
+//! ```
+
+use super::DataLine;
+
+use crate::dynamic_tree::{DynamicLeaf, DynamicTree};
+use crate::utils::select_in_word;
+use crate::{AccessBin, RankBin, SelectBin, SpaceUsage};
+
+/// The number of `DataLine`s of a full leaf.
+const LEAF_LINES: usize = 8;
+
+// A leaf is a sequence of up to `LEAF_LINES` lines of 512 bits each.
+// The bits after the last one are always zeros.
+#[derive(Clone, Default, Debug)]
+struct BitLeaf {
+    lines: Vec<DataLine>,
+    len: usize,
+}
+
+impl BitLeaf {
+    #[inline(always)]
+    fn word(&self, w: usize) -> u64 {
+        self.lines[w >> 3].words[w & 7]
+    }
+
+    #[inline(always)]
+    fn word_mut(&mut self, w: usize) -> &mut u64 {
+        &mut self.lines[w >> 3].words[w & 7]
+    }
+
+    // Overwrites the bit at position `i`.
+    #[inline]
+    fn write(&mut self, i: usize, bit: u8) {
+        let shift = i & 63;
+        let word = self.word_mut(i >> 6);
+        *word = (*word & !(1 << shift)) | ((bit as u64 & 1) << shift);
+    }
+}
+
+// Returns a mask with the `len` least significant bits set.
+#[inline(always)]
+fn low_mask(len: usize) -> u64 {
+    if len >= 64 {
+        u64::MAX
+    } else {
+        (1 << len) - 1
+    }
+}
+
+impl DynamicLeaf<2> for BitLeaf {
+    const CAPACITY: usize = LEAF_LINES * 512;
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    fn get(&self, i: usize) -> u8 {
+        ((self.word(i >> 6) >> (i & 63)) & 1) as u8
+    }
+
+    #[inline]
+    fn rank(&self, symbol: u8, i: usize) -> usize {
+        let full_words = i >> 6;
+        let mut ones: usize = (0..full_words)
+            .map(|w| self.word(w).count_ones() as usize)
+            .sum();
+        if i & 63 != 0 {
+            ones += (self.word(full_words) & low_mask(i & 63)).count_ones() as usize;
+        }
+        if symbol == 1 {
+            ones
+        } else {
+            i - ones
+        }
+    }
+
+    fn select(&self, symbol: u8, mut i: usize) -> usize {
+        for w in 0..self.len.div_ceil(64) {
+            // Bits after the last one are zeros, so they must not be counted
+            let word = if symbol == 1 {
+                self.word(w)
+            } else {
+                !self.word(w) & low_mask(self.len - w * 64)
+            };
+
+            let cnt = word.count_ones() as usize;
+            if i < cnt {
+                return w * 64 + select_in_word(word, i as u64) as usize;
+            }
+            i -= cnt;
+        }
+        unreachable!("the tree guarantees that the occurrence exists")
+    }
+
+    fn insert(&mut self, i: usize, symbol: u8) {
+        if self.len == self.lines.len() * 512 {
+            self.lines.push(DataLine::default());
+        }
+
+        // Shifts by one position all the bits from `i` on, carrying the
+        // highest bit of each word to the next one
+        let first = i >> 6;
+        let mask = low_mask(i & 63);
+        let word = self.word_mut(first);
+        let mut carry = *word >> 63;
+        *word = (*word & mask) | ((*word & !mask) << 1);
+        for w in first + 1..=self.len >> 6 {
+            let word = self.word_mut(w);
+            let next_carry = *word >> 63;
+            *word = (*word << 1) | carry;
+            carry = next_carry;
+        }
+
+        self.len += 1;
+        self.write(i, symbol);
+    }
+
+    fn remove(&mut self, i: usize) -> u8 {
+        let symbol = self.get(i);
+
+        let first = i >> 6;
+        let last = (self.len - 1) >> 6;
+        let mask = low_mask(i & 63);
+        for w in first..=last {
+            let next_bit = if w < last { self.word(w + 1) & 1 } else { 0 };
+            let word = self.word_mut(w);
+            *word = if w == first {
+                (*word & mask) | ((*word >> 1) & !mask)
+            } else {
+                *word >> 1
+            };
+            *word |= next_bit << 63;
+        }
+
+        self.len -= 1;
+        if self.len.is_multiple_of(512) {
+            self.lines.pop();
+        }
+        symbol
+    }
+
+    #[inline]
+    fn set(&mut self, i: usize, symbol: u8) -> u8 {
+        let old = self.get(i);
+        self.write(i, symbol);
+        old
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.lines.truncate(len.div_ceil(512));
+        for w in (len >> 6)..8 * self.lines.len() {
+            let mask = if w == len >> 6 { low_mask(len & 63) } else { 0 };
+            *self.word_mut(w) &= mask;
+        }
+        self.len = len;
+    }
+
+    fn counts(&self) -> [usize; 2] {
+        let ones = self.rank(1, self.len);
+        [self.len - ones, ones]
+    }
+}
+
+impl SpaceUsage for BitLeaf {
+    fn space_usage_byte(&self) -> usize {
+        self.lines.capacity() * std::mem::size_of::<DataLine>() + self.len.space_usage_byte()
+    }
+}
+
+/// A bit vector that supports insertions, deletions, and updates of bits,
+/// besides `access`, `rank`, and `select` queries.
+#[derive(Clone, Default, Debug)]
+pub struct DynamicBitVector {
+    tree: DynamicTree<BitLeaf, 2>,
+}
+
+impl DynamicBitVector {
+    /// Creates an empty dynamic bit vector.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::DynamicBitVector;
+    ///
+    /// let dbv = DynamicBitVector::new();
+    /// assert!(dbv.is_empty());
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of bits in the bit vector.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::DynamicBitVector;
+    ///
+    /// let dbv: DynamicBitVector = [true, false].into_iter().cycle().take(10).collect();
+    /// assert_eq!(dbv.len(), 10);
+    /// ```
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Checks if the bit vector is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::DynamicBitVector;
+    ///
+    /// let dbv = DynamicBitVector::default();
+    /// assert!(dbv.is_empty());
+    /// ```
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.tree.len() == 0
+    }
+
+    /// Returns the number of ones in the bit vector.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::DynamicBitVector;
+    ///
+    /// let dbv: DynamicBitVector = [true, false, true].into_iter().collect();
+    /// assert_eq!(dbv.count_ones(), 2);
+    /// ```
+    #[must_use]
+    pub fn count_ones(&self) -> usize {
+        self.tree.occs(1)
+    }
+
+    /// Returns the number of zeros in the bit vector.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::DynamicBitVector;
+    ///
+    /// let dbv: DynamicBitVector = [true, false, true].into_iter().collect();
+    /// assert_eq!(dbv.count_zeros(), 1);
+    /// ```
+    #[must_use]
+    pub fn count_zeros(&self) -> usize {
+        self.tree.occs(0)
+    }
+
+    /// Inserts `bit` at position `i`, shifting all the following bits to the right.
+    ///
+    /// ## Panics
+    /// Panics if `i` is larger than the length.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::{AccessBin, DynamicBitVector};
+    ///
+    /// let mut dbv: DynamicBitVector = [false, false].into_iter().collect();
+    /// dbv.insert(1, true);
+    ///
+    /// assert_eq!(dbv.get(0), Some(false));
+    /// assert_eq!(dbv.get(1), Some(true));
+    /// assert_eq!(dbv.len(), 3);
+    /// ```
+    pub fn insert(&mut self, i: usize, bit: bool) {
+        assert!(i <= self.len(), "The position {i} is out of bounds.");
+        self.tree.insert(i, bit as u8);
+    }
+
+    /// Appends `bit` at the end of the bit vector.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::{AccessBin, DynamicBitVector};
+    ///
+    /// let mut dbv = DynamicBitVector::new();
+    /// dbv.push(true);
+    ///
+    /// assert_eq!(dbv.get(0), Some(true));
+    /// ```
+    pub fn push(&mut self, bit: bool) {
+        self.tree.push(bit as u8);
+    }
+
+    /// Removes and returns the bit at position `i`, shifting all the following
+    /// bits to the left.
+    ///
+    /// ## Panics
+    /// Panics if `i` is out of bounds.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::{AccessBin, DynamicBitVector};
+    ///
+    /// let mut dbv: DynamicBitVector = [false, true, false].into_iter().collect();
+    ///
+    /// assert!(dbv.remove(1));
+    /// assert_eq!(dbv.get(1), Some(false));
+    /// assert_eq!(dbv.len(), 2);
+    /// ```
+    pub fn remove(&mut self, i: usize) -> bool {
+        assert!(i < self.len(), "The position {i} is out of bounds.");
+        self.tree.remove(i) == 1
+    }
+
+    /// Replaces the bit at position `i` with `bit` and returns the old one.
+    ///
+    /// ## Panics
+    /// Panics if `i` is out of bounds.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::{DynamicBitVector, RankBin};
+    ///
+    /// let mut dbv: DynamicBitVector = [false, true, false].into_iter().collect();
+    ///
+    /// assert!(!dbv.set(2, true));
+    /// assert_eq!(dbv.rank1(3), Some(2));
+    /// ```
+    pub fn set(&mut self, i: usize, bit: bool) -> bool {
+        assert!(i < self.len(), "The position {i} is out of bounds.");
+        self.tree.set(i, bit as u8) == 1
+    }
+
+    /// Returns an iterator over the bits of the bit vector.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::DynamicBitVector;
+    ///
+    /// let dbv: DynamicBitVector = [true, false].into_iter().cycle().take(1000).collect();
+    ///
+    /// for (i, bit) in dbv.iter().enumerate() {
+    ///     assert_eq!(i % 2 == 0, bit);
+    /// }
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        self.tree
+            .leaves()
+            .into_iter()
+            .flat_map(|leaf| (0..leaf.len()).map(move |i| leaf.get(i) == 1))
+    }
+}
+
+impl AccessBin for DynamicBitVector {
+    /// Returns the bit at position `i`, `None` if `i` is out of bounds.
+    #[inline]
+    fn get(&self, i: usize) -> Option<bool> {
+        if i >= self.len() {
+            return None;
+        }
+        // SAFETY: bounds already checked
+        Some(unsafe { self.get_unchecked(i) })
+    }
+
+    /// Returns the bit at position `i`.
+    ///
+    /// # Safety
+    /// Calling this method with an out-of-bounds index is undefined behavior.
+    #[inline]
+    unsafe fn get_unchecked(&self, i: usize) -> bool {
+        self.tree.get(i) == 1
+    }
+}
+
+impl RankBin for DynamicBitVector {
+    /// Returns the number of ones up to position `i` excluded, `None` if `i`
+    /// is out of bounds.
+    #[inline]
+    fn rank1(&self, i: usize) -> Option<usize> {
+        if i > self.len() {
+            return None;
+        }
+        // SAFETY: bounds already checked
+        Some(unsafe { self.rank1_unchecked(i) })
+    }
+
+    /// Returns the number of ones up to position `i` excluded.
+    ///
+    /// # Safety
+    /// Calling this method with an out-of-bounds index is undefined behavior.
+    #[inline]
+    unsafe fn rank1_unchecked(&self, i: usize) -> usize {
+        self.tree.rank(1, i)
+    }
+}
+
+impl SelectBin for DynamicBitVector {
+    /// Returns the position of the `i+1`-th one, `None` if there is no such one.
+    #[inline]
+    fn select1(&self, i: usize) -> Option<usize> {
+        if i >= self.count_ones() {
+            return None;
+        }
+        // SAFETY: the occurrence exists
+        Some(unsafe { self.select1_unchecked(i) })
+    }
+
+    /// Returns the position of the `i+1`-th one.
+    ///
+    /// # Safety
+    /// Calling this method if the `i+1`-th one does not exist is undefined behavior.
+    #[inline]
+    unsafe fn select1_unchecked(&self, i: usize) -> usize {
+        self.tree.select(1, i)
+    }
+
+    /// Returns the position of the `i+1`-th zero, `None` if there is no such zero.
+    #[inline]
+    fn select0(&self, i: usize) -> Option<usize> {
+        if i >= self.count_zeros() {
+            return None;
+        }
+        // SAFETY: the occurrence exists
+        Some(unsafe { self.select0_unchecked(i) })
+    }
+
+    /// Returns the position of the `i+1`-th zero.
+    ///
+    /// # Safety
+    /// Calling this method if the `i+1`-th zero does not exist is undefined behavior.
+    #[inline]
+    unsafe fn select0_unchecked(&self, i: usize) -> usize {
+        self.tree.select(0, i)
+    }
+}
+
+impl SpaceUsage for DynamicBitVector {
+    /// Returns the space usage of the data structure in bytes.
+    fn space_usage_byte(&self) -> usize {
+        self.tree.space_usage_byte()
+    }
+}
+
+/// Two dynamic bit vectors are equal if they store the same bits,
+/// regardless of the shape of their trees.
+impl PartialEq for DynamicBitVector {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for DynamicBitVector {}
+
+impl FromIterator<bool> for DynamicBitVector {
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = bool>,
+    {
+        let mut dbv = DynamicBitVector::new();
+        dbv.extend(iter);
+        dbv
+    }
+}
+
+impl Extend<bool> for DynamicBitVector {
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = bool>,
+    {
+        for bit in iter {
+            self.push(bit);
+        }
+    }
+}
@@ -6,10 +6,16 @@
 //!
 //! For both data structures, it is possible to iterate over bits or positions of bits set either to zero or one.
 
+use crate::zero_copy::{LayoutError, LayoutReader, LayoutWriter, Pod, Storage, ZeroCopy};
 use crate::{utils::select_in_word, AccessBin, RankBin, SelectBin, SpaceUsage};
 
+use std::io::{self, Write};
+
 use serde::{Deserialize, Serialize};
 
+pub mod dynamic_bitvector;
+pub mod rrr;
+pub mod rs_incremental;
 pub mod rs_narrow;
 pub mod rs_wide;
 
@@ -167,7 +173,7 @@ impl SelectBin for DataLine {
 /// Implementation of an immutable bit vector.
 #[derive(Default, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub struct BitVector {
-    data: Box<[DataLine]>,
+    data: Storage<DataLine>,
     n_bits: usize,
     n_ones: usize,
 }
@@ -462,6 +468,44 @@ impl AccessBin for BitVector {
     }
 }
 
+// SAFETY: a data line is made of 64 bytes of plain words
+unsafe impl Pod for DataLine {}
+
+impl ZeroCopy for BitVector {
+    fn write_layout<W: Write>(&self, writer: &mut LayoutWriter<W>) -> io::Result<()> {
+        writer.write_u64(self.n_bits as u64)?;
+        writer.write_u64(self.n_ones as u64)?;
+        writer.write_slice(&self.data)
+    }
+
+    unsafe fn read_layout(reader: &mut LayoutReader<'_>) -> Result<Self, LayoutError> {
+        let n_bits = reader.read_usize()?;
+        let n_ones = reader.read_usize()?;
+        let data = reader.read_storage()?;
+
+        if n_bits > data.len() * 512 || n_ones > n_bits {
+            return Err(LayoutError::InvalidData("bit vector"));
+        }
+        if !reader.is_trusted() {
+            let words = cast_to_u64_slice(&data);
+            let (full, left) = (n_bits / 64, n_bits % 64);
+            let mut ones: usize = words[..full].iter().map(|w| w.count_ones() as usize).sum();
+            if left > 0 {
+                ones += (words[full] & ((1 << left) - 1)).count_ones() as usize;
+            }
+            if ones != n_ones {
+                return Err(LayoutError::InvalidData("number of ones of the bit vector"));
+            }
+        }
+
+        Ok(Self {
+            data,
+            n_bits,
+            n_ones,
+        })
+    }
+}
+
 impl SpaceUsage for BitVector {
     /// Returns the space usage in bytes.
     #[must_use]
@@ -565,7 +609,7 @@ where
 impl From<BitVectorMut> for BitVector {
     fn from(bvm: BitVectorMut) -> Self {
         Self {
-            data: bvm.data.into_boxed_slice(),
+            data: bvm.data.into(),
             n_bits: bvm.n_bits,
             n_ones: bvm.n_ones,
         }
@@ -595,7 +639,7 @@ impl From<BitVectorMut> for BitVector {
 impl From<BitVector> for BitVectorMut {
     fn from(bv: BitVector) -> Self {
         Self {
-            data: bv.data.into(),
+            data: bv.data.into_vec(),
             n_bits: bv.n_bits,
             n_ones: bv.n_ones,
         }
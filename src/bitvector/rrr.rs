@@ -0,0 +1,515 @@
+//! Implements an entropy-compressed bit vector with support for `access`, `rank`,
+//! and `select` queries, based on the encoding by Raman, Raman, and Rao (RRR).
+//!
+//! The bit vector is split into blocks of [`BLOCK_SIZE`] bits. A block with `k` ones,
+//! its *class*, is one of $$\binom{63}{k}$$ possible blocks, so it is encoded by its
+//! class in 6 bits and by its *offset*, i.e., its index among the blocks of the same
+//! class, in $$\lceil \log \binom{63}{k} \rceil$$ bits. Blocks with few or many
+//! ones have short offsets, so sparse and dense bit vectors take about
+//! $$n H_0 + 0.1n$$ bits plus the samples of the superblocks.
+//!
+//! Every [`BLOCKS_IN_SUPERBLOCK`] blocks, we sample the number of ones before the
+//! superblock and the position of its first offset. A `rank` query scans the classes
+//! of the blocks of a superblock and decodes a single block. A `select` query
+//! binary searches the samples and then proceeds in the same way.
+
+use super::MyPrimInt;
+use crate::persist::Persist;
+use crate::zero_copy::{LayoutError, LayoutReader, LayoutWriter, Storage, ZeroCopy};
+use crate::Error;
+use crate::SpaceUsage;
+use crate::{utils::select_in_word, AccessBin, BitVector, BitVectorMut, RankBin, SelectBin};
+
+use std::io::{self, Write};
+
+use serde::{Deserialize, Serialize};
+
+/// The number of bits in each block.
+pub const BLOCK_SIZE: usize = 63;
+/// The number of blocks in each superblock.
+pub const BLOCKS_IN_SUPERBLOCK: usize = 32;
+
+/// The number of bits of the class of a block.
+const CLASS_LEN: usize = 6;
+
+/// `BINOMIAL[n][k]` is the binomial coefficient $$\binom{n}{k}$$, for n, k < 64.
+static BINOMIAL: [[u64; 64]; 64] = binomial_table();
+
+const fn binomial_table() -> [[u64; 64]; 64] {
+    let mut table = [[0; 64]; 64];
+    let mut n = 0;
+    while n < 64 {
+        table[n][0] = 1;
+        let mut k = 1;
+        while k <= n {
+            table[n][k] = table[n - 1][k - 1] + table[n - 1][k];
+            k += 1;
+        }
+        n += 1;
+    }
+    table
+}
+
+/// `OFFSET_LEN[k]` is the number of bits of the offset of a block of class `k`.
+static OFFSET_LEN: [u8; BLOCK_SIZE + 1] = offset_len_table();
+
+const fn offset_len_table() -> [u8; BLOCK_SIZE + 1] {
+    let table = binomial_table();
+    let mut lens = [0; BLOCK_SIZE + 1];
+    let mut k = 0;
+    while k <= BLOCK_SIZE {
+        let n_blocks = table[BLOCK_SIZE][k];
+        lens[k] = if n_blocks == 1 {
+            0
+        } else {
+            (64 - (n_blocks - 1).leading_zeros()) as u8
+        };
+        k += 1;
+    }
+    lens
+}
+
+// Returns the offset of the `block` of class `k`, i.e., its index in the
+// enumeration of the blocks with `k` ones by the combinatorial number system.
+#[inline]
+fn encode(block: u64, k: usize) -> u64 {
+    let mut offset = 0;
+    let mut k = k;
+    let mut bits = block;
+    while bits != 0 {
+        let pos = 63 - bits.leading_zeros() as usize;
+        offset += BINOMIAL[pos][k];
+        k -= 1;
+        bits ^= 1 << pos;
+    }
+    offset
+}
+
+// Returns the block of class `k` with the given `offset`.
+#[inline]
+fn decode(offset: u64, k: usize) -> u64 {
+    if k == BLOCK_SIZE {
+        return (1 << BLOCK_SIZE) - 1;
+    }
+    let mut block = 0;
+    let mut offset = offset;
+    let mut k = k;
+    let mut pos = BLOCK_SIZE;
+    while k > 0 {
+        pos -= 1;
+        let c = BINOMIAL[pos][k];
+        if offset >= c {
+            block |= 1 << pos;
+            offset -= c;
+            k -= 1;
+        }
+    }
+    block
+}
+
+/// An RRR-compressed bit vector with support for `access`, `rank`, and `select` queries.
+///
+/// # Examples
+///
+/// ```
+/// use qwt::{AccessBin, BitVector, RRRBitVector, RankBin, SelectBin, SpaceUsage};
+///
+/// let bv: BitVector = (0..1_000_000).step_by(50).collect();
+/// let rrr = RRRBitVector::new(&bv);
+///
+/// assert_eq!(rrr.len(), bv.len());
+/// assert_eq!(rrr.get(100), Some(true));
+/// assert_eq!(rrr.rank1(1000), Some(20));
+/// assert_eq!(rrr.select1(20), Some(1000));
+/// assert_eq!(rrr.select0(49), Some(51));
+///
+/// assert!(rrr.space_usage_byte() * 2 < bv.space_usage_byte());
+/// ```
+#[derive(Clone, Default, Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub struct RRRBitVector {
+    n_bits: usize,
+    n_ones: usize,
+    classes: BitVector,
+    offsets: BitVector,
+    rank_samples: Storage<usize>, // The number of ones before each superblock
+    offset_samples: Storage<usize>, // The position of the first offset of each superblock
+}
+
+impl RRRBitVector {
+    /// Builds the RRR encoding of the bit vector `bv`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{BitVector, RRRBitVector};
+    ///
+    /// let bv: BitVector = [1, 5, 1000].into_iter().collect();
+    /// let rrr = RRRBitVector::new(&bv);
+    ///
+    /// assert_eq!(rrr.len(), 1001);
+    /// assert_eq!(rrr.count_ones(), 3);
+    /// ```
+    #[must_use]
+    pub fn new(bv: &BitVector) -> Self {
+        let n_blocks = bv.len().div_ceil(BLOCK_SIZE);
+        let mut classes = BitVectorMut::with_capacity(n_blocks * CLASS_LEN);
+        let mut offsets = BitVectorMut::new();
+        let mut rank_samples = Vec::with_capacity(n_blocks.div_ceil(BLOCKS_IN_SUPERBLOCK));
+        let mut offset_samples = Vec::with_capacity(n_blocks.div_ceil(BLOCKS_IN_SUPERBLOCK));
+
+        let mut n_ones = 0;
+        for b in 0..n_blocks {
+            if b % BLOCKS_IN_SUPERBLOCK == 0 {
+                rank_samples.push(n_ones);
+                offset_samples.push(offsets.len());
+            }
+
+            let start = b * BLOCK_SIZE;
+            let len = BLOCK_SIZE.min(bv.len() - start);
+            let block = bv.get_bits(start, len).unwrap();
+            let k = block.count_ones() as usize;
+
+            classes.append_bits(k as u64, CLASS_LEN);
+            offsets.append_bits(encode(block, k), OFFSET_LEN[k] as usize);
+            n_ones += k;
+        }
+
+        Self {
+            n_bits: bv.len(),
+            n_ones,
+            classes: classes.into(),
+            offsets: offsets.into(),
+            rank_samples: rank_samples.into(),
+            offset_samples: offset_samples.into(),
+        }
+    }
+
+    /// Returns the number of bits in the bit vector.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.n_bits
+    }
+
+    /// Checks if the bit vector is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.n_bits == 0
+    }
+
+    /// Counts the number of ones (bits set to 1) in the bit vector.
+    #[must_use]
+    pub fn count_ones(&self) -> usize {
+        self.n_ones
+    }
+
+    /// Counts the number of zeros (bits set to 0) in the bit vector.
+    #[must_use]
+    pub fn count_zeros(&self) -> usize {
+        self.n_bits - self.n_ones
+    }
+
+    #[inline(always)]
+    unsafe fn class(&self, block: usize) -> usize {
+        self.classes
+            .get_bits_unchecked(block * CLASS_LEN, CLASS_LEN) as usize
+    }
+
+    // Decodes the block of class `k` whose offset starts at position `offset_pos`.
+    #[inline(always)]
+    unsafe fn block(&self, k: usize, offset_pos: usize) -> u64 {
+        let len = OFFSET_LEN[k] as usize;
+        let offset = if len == 0 {
+            0
+        } else {
+            self.offsets.get_bits_unchecked(offset_pos, len)
+        };
+        decode(offset, k)
+    }
+
+    // Scans the blocks of the superblock of `block` up to `block` excluded, and returns the
+    // number of ones before `block` and the position of its offset.
+    #[inline(always)]
+    unsafe fn scan_superblock(&self, block: usize) -> (usize, usize) {
+        let superblock = block / BLOCKS_IN_SUPERBLOCK;
+        let mut rank = *self.rank_samples.get_unchecked(superblock);
+        let mut offset_pos = *self.offset_samples.get_unchecked(superblock);
+        for b in superblock * BLOCKS_IN_SUPERBLOCK..block {
+            let k = self.class(b);
+            rank += k;
+            offset_pos += OFFSET_LEN[k] as usize;
+        }
+        (rank, offset_pos)
+    }
+
+    // Decodes all the blocks with checked reads, or returns `None` if a class, an offset,
+    // or the padding of the last block is not valid.
+    fn decode_checked(&self) -> Option<BitVector> {
+        let mut bv = BitVectorMut::with_capacity(self.n_bits);
+        let mut offset_pos = 0;
+        for b in 0..self.n_bits.div_ceil(BLOCK_SIZE) {
+            let k = self.classes.get_bits(b * CLASS_LEN, CLASS_LEN)? as usize;
+            if k > BLOCK_SIZE {
+                return None;
+            }
+            let len = OFFSET_LEN[k] as usize;
+            let offset = if len == 0 {
+                0
+            } else {
+                self.offsets.get_bits(offset_pos, len)?
+            };
+            if offset >= BINOMIAL[BLOCK_SIZE][k] {
+                return None;
+            }
+            offset_pos += len;
+
+            let block_len = BLOCK_SIZE.min(self.n_bits - b * BLOCK_SIZE);
+            let block = decode(offset, k);
+            if block >> block_len != 0 {
+                return None;
+            }
+            bv.append_bits(block, block_len);
+        }
+        Some(bv.into())
+    }
+
+    // Returns the position of the `i+1`-th one (if `BIT`) or zero, which must exist.
+    unsafe fn select<const BIT: bool>(&self, i: usize) -> usize {
+        let superblock_bits = BLOCKS_IN_SUPERBLOCK * BLOCK_SIZE;
+        let rank = |superblock: usize| {
+            let ones = self.rank_samples[superblock];
+            if BIT {
+                ones
+            } else {
+                superblock * superblock_bits - ones
+            }
+        };
+
+        // The last superblock whose rank is at most `i`
+        let mut superblock = 0;
+        let mut hi = self.rank_samples.len() - 1;
+        while superblock < hi {
+            let mid = (superblock + hi).div_ceil(2);
+            if rank(mid) <= i {
+                superblock = mid;
+            } else {
+                hi = mid - 1;
+            }
+        }
+
+        let mut i = i - rank(superblock);
+        let mut offset_pos = self.offset_samples[superblock];
+        let mut block = superblock * BLOCKS_IN_SUPERBLOCK;
+        loop {
+            let k = self.class(block);
+            let cnt = if BIT { k } else { BLOCK_SIZE - k };
+            if i < cnt {
+                break;
+            }
+            i -= cnt;
+            offset_pos += OFFSET_LEN[k] as usize;
+            block += 1;
+        }
+
+        let bits = self.block(self.class(block), offset_pos);
+        let bits = if BIT { bits } else { !bits };
+        block * BLOCK_SIZE + select_in_word(bits, i as u64) as usize
+    }
+}
+
+impl AccessBin for RRRBitVector {
+    /// Returns the bit at the given position `i`,
+    /// or [`None`] if `i` is out of bounds.
+    #[inline]
+    fn get(&self, i: usize) -> Option<bool> {
+        if i >= self.n_bits {
+            return None;
+        }
+
+        // SAFETY: no out of bound is possible
+        Some(unsafe { self.get_unchecked(i) })
+    }
+
+    /// Returns the bit at the given position `i`.
+    ///
+    /// # Safety
+    /// Calling this method with an out-of-bounds index is undefined behavior.
+    #[inline]
+    unsafe fn get_unchecked(&self, i: usize) -> bool {
+        let block = i / BLOCK_SIZE;
+        let k = self.class(block);
+        if k == 0 || k == BLOCK_SIZE {
+            return k != 0;
+        }
+        let (_, offset_pos) = self.scan_superblock(block);
+        (self.block(k, offset_pos) >> (i % BLOCK_SIZE)) & 1 == 1
+    }
+}
+
+impl RankBin for RRRBitVector {
+    /// Returns the number of ones up to position `i` excluded, `None` if `i`
+    /// is out of bounds.
+    #[inline]
+    fn rank1(&self, i: usize) -> Option<usize> {
+        if i > self.n_bits {
+            return None;
+        }
+
+        Some(unsafe { self.rank1_unchecked(i) })
+    }
+
+    /// Returns the number of ones up to position `i` excluded.
+    ///
+    /// # Safety
+    /// Calling this method with an out-of-bounds index is undefined behavior.
+    #[inline]
+    unsafe fn rank1_unchecked(&self, i: usize) -> usize {
+        if i == self.n_bits {
+            return self.n_ones;
+        }
+        let block = i / BLOCK_SIZE;
+        let (rank, offset_pos) = self.scan_superblock(block);
+        let left = i % BLOCK_SIZE;
+        if left == 0 {
+            return rank;
+        }
+        let bits = self.block(self.class(block), offset_pos);
+        rank + (bits & ((1 << left) - 1)).count_ones() as usize
+    }
+}
+
+impl SelectBin for RRRBitVector {
+    /// Returns the position of the `i+1`-th one, `None` if there is no such one.
+    fn select1(&self, i: usize) -> Option<usize> {
+        if i >= self.n_ones {
+            return None;
+        }
+
+        Some(unsafe { self.select1_unchecked(i) })
+    }
+
+    /// Returns the position of the `i+1`-th one.
+    ///
+    /// # Safety
+    /// Calling this method if the `i+1`-th one does not exist is undefined behavior.
+    unsafe fn select1_unchecked(&self, i: usize) -> usize {
+        self.select::<true>(i)
+    }
+
+    /// Returns the position of the `i+1`-th zero, `None` if there is no such zero.
+    fn select0(&self, i: usize) -> Option<usize> {
+        if i >= self.count_zeros() {
+            return None;
+        }
+
+        Some(unsafe { self.select0_unchecked(i) })
+    }
+
+    /// Returns the position of the `i+1`-th zero.
+    ///
+    /// # Safety
+    /// Calling this method if the `i+1`-th zero does not exist is undefined behavior.
+    unsafe fn select0_unchecked(&self, i: usize) -> usize {
+        self.select::<false>(i)
+    }
+}
+
+impl SpaceUsage for RRRBitVector {
+    /// Gives the space usage in bytes of the data structure.
+    fn space_usage_byte(&self) -> usize {
+        self.n_bits.space_usage_byte()
+            + self.n_ones.space_usage_byte()
+            + self.classes.space_usage_byte()
+            + self.offsets.space_usage_byte()
+            + self.rank_samples.space_usage_byte()
+            + self.offset_samples.space_usage_byte()
+    }
+}
+
+impl Persist for RRRBitVector {
+    fn type_tag() -> String {
+        "RRRBitVector".to_string()
+    }
+
+    fn persisted_len(&self) -> usize {
+        self.n_bits
+    }
+}
+
+impl ZeroCopy for RRRBitVector {
+    fn write_layout<W: Write>(&self, writer: &mut LayoutWriter<W>) -> io::Result<()> {
+        writer.write_u64(self.n_bits as u64)?;
+        writer.write_u64(self.n_ones as u64)?;
+        self.classes.write_layout(writer)?;
+        self.offsets.write_layout(writer)?;
+        writer.write_slice(&self.rank_samples)?;
+        writer.write_slice(&self.offset_samples)
+    }
+
+    unsafe fn read_layout(reader: &mut LayoutReader<'_>) -> Result<Self, LayoutError> {
+        let n_bits = reader.read_usize()?;
+        let n_ones = reader.read_usize()?;
+        let classes = BitVector::read_layout(reader)?;
+        let offsets = BitVector::read_layout(reader)?;
+        let rank_samples: Storage<usize> = reader.read_storage()?;
+        let offset_samples: Storage<usize> = reader.read_storage()?;
+
+        // The unchecked queries rely on the consistency of the components
+        let n_blocks = n_bits.div_ceil(BLOCK_SIZE);
+        let n_superblocks = n_blocks.div_ceil(BLOCKS_IN_SUPERBLOCK);
+        if n_ones > n_bits
+            || classes.len() != n_blocks * CLASS_LEN
+            || rank_samples.len() != n_superblocks
+            || offset_samples.len() != n_superblocks
+            || offset_samples.iter().any(|&pos| pos > offsets.len())
+        {
+            return Err(LayoutError::InvalidData("RRRBitVector"));
+        }
+
+        let rrr = Self {
+            n_bits,
+            n_ones,
+            classes,
+            offsets,
+            rank_samples,
+            offset_samples,
+        };
+
+        // The classes must sum up to the samples and match the lengths of the offsets
+        if !reader.is_trusted() && rrr.decode_checked().is_none_or(|bv| Self::new(&bv) != rrr) {
+            return Err(LayoutError::InvalidData("blocks of RRRBitVector"));
+        }
+
+        Ok(rrr)
+    }
+}
+
+impl TryFrom<BitVector> for RRRBitVector {
+    type Error = Error;
+
+    /// Builds the RRR encoding of the bit vector `bv`.
+    ///
+    /// # Errors
+    /// Never fails, as [`RRRBitVector`] encodes bit vectors of any length. The conversion
+    /// is fallible for uniformity with the other rank and select supports.
+    fn try_from(bv: BitVector) -> Result<Self, Error> {
+        Ok(Self::new(&bv))
+    }
+}
+
+impl<V> FromIterator<V> for RRRBitVector
+where
+    V: MyPrimInt,
+    <V as TryInto<usize>>::Error: std::fmt::Debug,
+{
+    /// Builds the RRR encoding of the bit vector with the ones at the given positions.
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = V>,
+    {
+        let bv: BitVector = iter.into_iter().collect();
+        Self::new(&bv)
+    }
+}
+
+#[cfg(test)]
+mod tests;
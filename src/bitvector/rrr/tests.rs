@@ -0,0 +1,151 @@
+use super::*;
+use crate::perf_and_test_utils::gen_strictly_increasing_sequence;
+
+// Checks all the queries against the bit vector `bv`.
+fn check(bv: &BitVector) {
+    let rrr = RRRBitVector::new(bv);
+    let n = bv.len();
+    assert_eq!(rrr.len(), n);
+    assert_eq!(rrr.count_ones(), bv.count_ones());
+
+    let mut ones = 0;
+    for (i, bit) in bv.iter().enumerate() {
+        assert_eq!(rrr.get(i), Some(bit));
+        assert_eq!(rrr.rank1(i), Some(ones));
+        if bit {
+            assert_eq!(rrr.select1(ones), Some(i));
+            ones += 1;
+        } else {
+            assert_eq!(rrr.select0(i - ones), Some(i));
+        }
+    }
+    assert_eq!(rrr.get(n), None);
+    assert_eq!(rrr.rank1(n), Some(ones));
+    assert_eq!(rrr.rank1(n + 1), None);
+    assert_eq!(rrr.select1(ones), None);
+    assert_eq!(rrr.select0(n - ones), None);
+}
+
+#[test]
+fn test_encode_decode() {
+    for block in [
+        0u64,
+        1,
+        0b1011,
+        1 << 62,
+        (1 << 63) - 1,
+        0x5555_5555_5555_5555,
+    ] {
+        let k = block.count_ones() as usize;
+        let offset = encode(block, k);
+        assert!(offset < BINOMIAL[BLOCK_SIZE][k]);
+        assert_eq!(decode(offset, k), block);
+    }
+    assert_eq!(OFFSET_LEN[0], 0);
+    assert_eq!(OFFSET_LEN[1], 6);
+    assert_eq!(OFFSET_LEN[BLOCK_SIZE], 0);
+}
+
+#[test]
+fn test_small() {
+    check(&BitVector::default());
+    check(&[0].into_iter().collect());
+    check(&[62].into_iter().collect());
+    check(&[63].into_iter().collect());
+    check(&(0..200).collect());
+    check(
+        &[
+            0, 12, 33, 42, 55, 61, 62, 63, 128, 129, 254, 511, 2015, 2016, 5000,
+        ]
+        .into_iter()
+        .collect(),
+    );
+}
+
+#[test]
+fn test_random() {
+    // From very sparse to very dense bit vectors, across many superblocks
+    for (n, u) in [
+        (100, 100_000),
+        (3000, 100_000),
+        (50_000, 100_000),
+        (99_000, 100_000),
+    ] {
+        let vv = gen_strictly_increasing_sequence(n, u);
+        let bv: BitVector = vv.iter().copied().collect();
+        check(&bv);
+    }
+}
+
+#[test]
+fn test_space() {
+    // Presence bitmaps 1-5% dense are much smaller than the plain bit vector
+    for n in [10_000, 50_000] {
+        let vv = gen_strictly_increasing_sequence(n, 1_000_000);
+        let bv: BitVector = vv.iter().copied().collect();
+        let rrr = RRRBitVector::new(&bv);
+        assert!(rrr.space_usage_byte() * 2 < bv.space_usage_byte());
+    }
+}
+
+#[test]
+fn test_layout() {
+    use crate::zero_copy::AlignedBytes;
+
+    let vv = gen_strictly_increasing_sequence(5000, 200_000);
+    let rrr: RRRBitVector = vv.iter().copied().collect();
+
+    let mut bytes = Vec::new();
+    rrr.save_layout(&mut bytes).unwrap();
+    let buffer = AlignedBytes::from(&bytes[..]);
+    let view = RRRBitVector::view(&buffer).unwrap();
+    assert_eq!(*view, rrr);
+    assert_eq!(view.select1(4000), Some(vv[4000]));
+
+    let mut bytes = Vec::new();
+    rrr.save_to(&mut bytes).unwrap();
+    let loaded = RRRBitVector::load_from(&bytes[..]).unwrap();
+    assert_eq!(loaded, rrr);
+    assert_eq!(loaded.rank1(vv[100]), Some(100));
+}
+
+#[test]
+fn test_layout_invalid_blocks() {
+    use crate::zero_copy::AlignedBytes;
+
+    let vv = gen_strictly_increasing_sequence(5000, 200_000);
+    let rrr: RRRBitVector = vv.iter().copied().collect();
+
+    let view = |rrr: &RRRBitVector| {
+        let mut bytes = Vec::new();
+        rrr.save_layout(&mut bytes).unwrap();
+        let buffer = AlignedBytes::from(&bytes[..]);
+        RRRBitVector::view(&buffer).map(|_| ())
+    };
+    assert!(view(&rrr).is_ok());
+
+    // Rank samples that are not monotone
+    let mut tampered = rrr.clone();
+    let mut rank_samples = tampered.rank_samples.to_vec();
+    rank_samples.swap(1, 2);
+    tampered.rank_samples = rank_samples.into();
+    assert!(matches!(view(&tampered), Err(LayoutError::InvalidData(_))));
+
+    // A class that does not match the length of the offsets of its superblock
+    let mut tampered = rrr.clone();
+    let mut classes: BitVectorMut = tampered.classes.iter().collect();
+    let k = tampered.classes.get_bits(0, CLASS_LEN).unwrap();
+    classes.set_bits(0, CLASS_LEN, k + 1);
+    tampered.classes = classes.into();
+    assert!(matches!(view(&tampered), Err(LayoutError::InvalidData(_))));
+
+    // An offset too large for its class
+    let mut tampered: RRRBitVector = [3usize].into_iter().collect();
+    let mut offsets = BitVectorMut::new();
+    offsets.append_bits(
+        u64::MAX >> (64 - OFFSET_LEN[1] as usize),
+        OFFSET_LEN[1] as usize,
+    );
+    tampered.offsets = offsets.into();
+    assert!(matches!(view(&tampered), Err(LayoutError::InvalidData(_))));
+}
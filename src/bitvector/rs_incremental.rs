@@ -0,0 +1,424 @@
+//! Implements a data structure to support `rank` and `select` queries on a
+//! mutable bit vector that grows by appending bits.
+//!
+//! [`RSIncremental`] wraps a [`BitVectorMut`] and keeps its rank and select
+//! support up to date while bits are appended with `push`, `append_bits`, and
+//! `extend_with_zeros`. Every time a `DataLine` of 512 bits is filled, it is sealed:
+//! its rank counters are appended to the `block_rank_pairs`, with the same layout used
+//! by [`RSNarrow`](crate::RSNarrow), together with the select samples of its ones and zeros.
+//! The last line, which is not full yet, is never sealed and the queries on it
+//! are solved with a few popcounts. Thus, appending a bit takes $$O(1)$$ amortized
+//! time and queries are as fast as on a static bit vector.
+
+use crate::{utils::select_in_word, AccessBin, BitVectorMut, RankBin, SelectBin, SpaceUsage};
+
+use serde::{Deserialize, Serialize};
+
+// Each line has 8 words. A line stores the number of ones before it and
+// 7 9-bit entries with the number of ones from the beginning of the line.
+const WORDS_PER_LINE: usize = 8;
+
+const SELECT_ONES_PER_HINT: usize = 64 * WORDS_PER_LINE * 2; // must be > 512
+const SELECT_ZEROS_PER_HINT: usize = SELECT_ONES_PER_HINT;
+
+/// A mutable bit vector with `rank` and `select` support that is kept valid
+/// across appends.
+///
+/// # Examples
+///
+/// ```
+/// use qwt::{RSIncremental, RankBin, SelectBin};
+///
+/// let mut rs = RSIncremental::new();
+/// rs.push(true);
+/// rs.extend_with_zeros(1000);
+/// rs.append_bits(0b101, 3);
+///
+/// assert_eq!(rs.len(), 1004);
+/// assert_eq!(rs.rank1(1003), Some(2));
+/// assert_eq!(rs.select1(2), Some(1003));
+/// assert_eq!(rs.select0(999), Some(1000));
+/// ```
+#[derive(Clone, Default, Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub struct RSIncremental {
+    bv: BitVectorMut,
+    block_rank_pairs: Vec<u64>, // Two entries for each sealed line
+    select_samples: [Vec<usize>; 2],
+}
+
+impl RSIncremental {
+    /// Creates an empty bit vector with rank and select support.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::RSIncremental;
+    ///
+    /// let rs = RSIncremental::new();
+    /// assert!(rs.is_empty());
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a `bit` at the end of the bit vector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{RSIncremental, RankBin};
+    ///
+    /// let mut rs = RSIncremental::new();
+    /// for i in 0..1000 {
+    ///     rs.push(i % 3 == 0);
+    /// }
+    ///
+    /// assert_eq!(rs.rank1(1000), Some(334));
+    /// ```
+    #[inline]
+    pub fn push(&mut self, bit: bool) {
+        self.bv.push(bit);
+        self.seal_full_lines();
+    }
+
+    /// Appends `len` bits at the end of the bit vector by taking
+    /// the least significant `len` bits in the u64 value `bits`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is larger than 64 or if a bit of position
+    /// larger than `len` is set in `bits`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{RSIncremental, SelectBin};
+    ///
+    /// let mut rs = RSIncremental::new();
+    /// rs.append_bits(0b0110, 4);
+    ///
+    /// assert_eq!(rs.select1(1), Some(2));
+    /// ```
+    #[inline]
+    pub fn append_bits(&mut self, bits: u64, len: usize) {
+        self.bv.append_bits(bits, len);
+        self.seal_full_lines();
+    }
+
+    /// Extends the bit vector by adding `n` bits set to 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{RSIncremental, SelectBin};
+    ///
+    /// let mut rs = RSIncremental::new();
+    /// rs.extend_with_zeros(10_000);
+    /// rs.push(true);
+    ///
+    /// assert_eq!(rs.select1(0), Some(10_000));
+    /// ```
+    #[inline]
+    pub fn extend_with_zeros(&mut self, n: usize) {
+        self.bv.extend_with_zeros(n);
+        self.seal_full_lines();
+    }
+
+    /// Returns the number of bits in the bit vector.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.bv.len()
+    }
+
+    /// Checks if the bit vector is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.bv.is_empty()
+    }
+
+    /// Counts the number of ones (bits set to 1) in the bit vector.
+    #[must_use]
+    pub fn count_ones(&self) -> usize {
+        self.bv.count_ones()
+    }
+
+    /// Counts the number of zeros (bits set to 0) in the bit vector.
+    #[must_use]
+    pub fn count_zeros(&self) -> usize {
+        self.bv.count_zeros()
+    }
+
+    /// Returns the indexed bit vector.
+    #[must_use]
+    pub fn bit_vector(&self) -> &BitVectorMut {
+        &self.bv
+    }
+
+    /// Consumes the data structure and returns the indexed bit vector.
+    #[must_use]
+    pub fn into_inner(self) -> BitVectorMut {
+        self.bv
+    }
+
+    #[inline(always)]
+    fn n_sealed(&self) -> usize {
+        self.block_rank_pairs.len() / 2
+    }
+
+    // The number of ones before the `line`-th line, which must be sealed.
+    #[inline(always)]
+    fn line_rank(&self, line: usize) -> usize {
+        self.block_rank_pairs[line * 2] as usize
+    }
+
+    // The number of ones before the `word`-th word, whose line must be sealed.
+    #[inline(always)]
+    fn word_rank(&self, word: usize) -> usize {
+        let line = word / WORDS_PER_LINE;
+        let left = word % WORDS_PER_LINE;
+        self.line_rank(line)
+            + ((self.block_rank_pairs[line * 2 + 1] >> ((7 - left) * 9)) & 0x1FF) as usize
+    }
+
+    // The number of ones in the sealed lines.
+    #[inline(always)]
+    fn sealed_ones(&self) -> usize {
+        let n_sealed = self.n_sealed();
+        if n_sealed == 0 {
+            return 0;
+        }
+        self.word_rank(n_sealed * WORDS_PER_LINE - 1)
+            + self.bv.data[n_sealed - 1].words[WORDS_PER_LINE - 1].count_ones() as usize
+    }
+
+    // Seals all the full lines which are not sealed yet.
+    fn seal_full_lines(&mut self) {
+        let n_full = self.bv.len() / 512;
+        let mut rank = self.sealed_ones();
+        for line in self.n_sealed()..n_full {
+            let mut subranks = 0;
+            let mut cur_subrank = 0;
+            for (w, &word) in self.bv.data[line].words.iter().enumerate() {
+                if w >= 1 {
+                    subranks = (subranks << 9) | cur_subrank;
+                }
+                cur_subrank += word.count_ones() as u64;
+            }
+
+            // Samples the line for the ones and zeros of rank multiple of the hint
+            let next_rank = rank + cur_subrank as usize;
+            while self.select_samples[1].len() * SELECT_ONES_PER_HINT < next_rank {
+                self.select_samples[1].push(line);
+            }
+            let next_rank0 = (line + 1) * 512 - next_rank;
+            while self.select_samples[0].len() * SELECT_ZEROS_PER_HINT < next_rank0 {
+                self.select_samples[0].push(line);
+            }
+
+            self.block_rank_pairs.push(rank as u64);
+            self.block_rank_pairs.push(subranks);
+            rank = next_rank;
+        }
+    }
+
+    // Returns the sealed line that contains the `i+1`-th one (if `BIT`) or zero,
+    // which must be in a sealed line.
+    fn select_line<const BIT: bool>(&self, i: usize) -> usize {
+        let rank = |line: usize| {
+            if BIT {
+                self.line_rank(line)
+            } else {
+                line * 512 - self.line_rank(line)
+            }
+        };
+
+        let samples = &self.select_samples[BIT as usize];
+        let hint = if BIT {
+            i / SELECT_ONES_PER_HINT
+        } else {
+            i / SELECT_ZEROS_PER_HINT
+        };
+        let mut lo = samples[hint];
+        let mut hi = samples.get(hint + 1).map_or(self.n_sealed() - 1, |&l| l);
+
+        // The last line in [lo, hi] whose rank is at most `i`
+        while lo < hi {
+            let mid = (lo + hi).div_ceil(2);
+            if rank(mid) <= i {
+                lo = mid;
+            } else {
+                hi = mid - 1;
+            }
+        }
+        lo
+    }
+
+    // Returns the position of the `i+1`-th one (if `BIT`) or zero within the words
+    // of the bit vector starting from the `first_word`-th one.
+    fn select_in_words<const BIT: bool>(&self, first_word: usize, mut i: usize) -> usize {
+        let mut w = first_word;
+        loop {
+            let word = self.bv.data[w / WORDS_PER_LINE].words[w % WORDS_PER_LINE];
+            let word = if BIT { word } else { !word };
+            let cnt = word.count_ones() as usize;
+            if i < cnt {
+                return w * 64 + select_in_word(word, i as u64) as usize;
+            }
+            i -= cnt;
+            w += 1;
+        }
+    }
+
+    fn select<const BIT: bool>(&self, i: usize) -> usize {
+        let sealed_ones = self.sealed_ones();
+        let sealed = if BIT {
+            sealed_ones
+        } else {
+            self.n_sealed() * 512 - sealed_ones
+        };
+
+        if i >= sealed {
+            // The bit is in the last line
+            return self.select_in_words::<BIT>(self.n_sealed() * WORDS_PER_LINE, i - sealed);
+        }
+
+        let line = self.select_line::<BIT>(i);
+        let word_rank = |word: usize| {
+            if BIT {
+                self.word_rank(word)
+            } else {
+                word * 64 - self.word_rank(word)
+            }
+        };
+
+        let mut word = line * WORDS_PER_LINE;
+        while word + 1 < (line + 1) * WORDS_PER_LINE && word_rank(word + 1) <= i {
+            word += 1;
+        }
+        self.select_in_words::<BIT>(word, i - word_rank(word))
+    }
+}
+
+impl AccessBin for RSIncremental {
+    /// Returns the bit at the given position `i`,
+    /// or [`None`] if `i` is out of bounds.
+    #[inline(always)]
+    fn get(&self, i: usize) -> Option<bool> {
+        self.bv.get(i)
+    }
+
+    /// Returns the bit at the given position `i`.
+    ///
+    /// # Safety
+    /// Calling this method with an out-of-bounds index is undefined behavior.
+    #[inline(always)]
+    unsafe fn get_unchecked(&self, i: usize) -> bool {
+        self.bv.get_unchecked(i)
+    }
+}
+
+impl RankBin for RSIncremental {
+    /// Returns the number of ones up to position `i` excluded, `None` if `i`
+    /// is out of bounds.
+    #[inline(always)]
+    fn rank1(&self, i: usize) -> Option<usize> {
+        if i > self.bv.len() {
+            return None;
+        }
+
+        Some(unsafe { self.rank1_unchecked(i) })
+    }
+
+    /// Returns the number of ones up to position `i` excluded.
+    ///
+    /// # Safety
+    /// Calling this method with an out-of-bounds index is undefined behavior.
+    #[inline(always)]
+    unsafe fn rank1_unchecked(&self, i: usize) -> usize {
+        let word = i >> 6;
+        let mut result = if word < self.n_sealed() * WORDS_PER_LINE {
+            self.word_rank(word)
+        } else {
+            // The last line is not sealed, so its words are counted one by one
+            let line_start = self.n_sealed() * WORDS_PER_LINE;
+            self.sealed_ones()
+                + (line_start..word)
+                    .map(|w| self.bv.data[w / WORDS_PER_LINE].words[w % WORDS_PER_LINE])
+                    .fold(0, |acc, x| acc + x.count_ones() as usize)
+        };
+
+        if i & 63 != 0 {
+            let w = self.bv.data[word / WORDS_PER_LINE].words[word % WORDS_PER_LINE];
+            result += (w << (64 - (i & 63))).count_ones() as usize;
+        }
+        result
+    }
+}
+
+impl SelectBin for RSIncremental {
+    /// Returns the position of the `i+1`-th one, `None` if there is no such one.
+    fn select1(&self, i: usize) -> Option<usize> {
+        if i >= self.count_ones() {
+            return None;
+        }
+
+        Some(unsafe { self.select1_unchecked(i) })
+    }
+
+    /// Returns the position of the `i+1`-th one.
+    ///
+    /// # Safety
+    /// Calling this method if the `i+1`-th one does not exist is undefined behavior.
+    unsafe fn select1_unchecked(&self, i: usize) -> usize {
+        self.select::<true>(i)
+    }
+
+    /// Returns the position of the `i+1`-th zero, `None` if there is no such zero.
+    fn select0(&self, i: usize) -> Option<usize> {
+        if i >= self.count_zeros() {
+            return None;
+        }
+
+        Some(unsafe { self.select0_unchecked(i) })
+    }
+
+    /// Returns the position of the `i+1`-th zero.
+    ///
+    /// # Safety
+    /// Calling this method if the `i+1`-th zero does not exist is undefined behavior.
+    unsafe fn select0_unchecked(&self, i: usize) -> usize {
+        self.select::<false>(i)
+    }
+}
+
+impl SpaceUsage for RSIncremental {
+    /// Gives the space usage in bytes of the data structure.
+    fn space_usage_byte(&self) -> usize {
+        self.bv.space_usage_byte()
+            + self.block_rank_pairs.space_usage_byte()
+            + self.select_samples[0].space_usage_byte()
+            + self.select_samples[1].space_usage_byte()
+    }
+}
+
+impl From<BitVectorMut> for RSIncremental {
+    /// Wraps the bit vector `bv` and builds the rank and select support of its full lines.
+    fn from(bv: BitVectorMut) -> Self {
+        let mut rs = Self {
+            bv,
+            ..Self::default()
+        };
+        rs.seal_full_lines();
+        rs
+    }
+}
+
+impl AsRef<BitVectorMut> for RSIncremental {
+    fn as_ref(&self) -> &BitVectorMut {
+        &self.bv
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,96 @@
+use super::*;
+use crate::perf_and_test_utils::gen_strictly_increasing_sequence;
+use crate::{BitVector, RSNarrow};
+
+// Checks all the queries against RSNarrow built on the same bits.
+fn check(rs: &RSIncremental) {
+    let bv: BitVector = rs.bit_vector().clone().into();
+    let n = bv.len();
+    assert_eq!(rs.len(), n);
+
+    if n == 0 {
+        assert_eq!(rs.rank1(0), Some(0));
+        assert_eq!(rs.select1(0), None);
+        assert_eq!(rs.select0(0), None);
+        return;
+    }
+
+    let expected = RSNarrow::new(bv.clone());
+    for i in (0..=n).step_by(7).chain([n]) {
+        assert_eq!(rs.rank1(i), expected.rank1(i));
+        assert_eq!(rs.rank0(i), expected.rank0(i));
+    }
+    for (rank, pos) in bv.ones().enumerate() {
+        assert_eq!(rs.select1(rank), Some(pos));
+        assert_eq!(rs.get(pos), Some(true));
+    }
+    for (rank, pos) in bv.zeros().enumerate() {
+        assert_eq!(rs.select0(rank), Some(pos));
+    }
+    assert_eq!(rs.rank1(n + 1), None);
+    assert_eq!(rs.select1(rs.count_ones()), None);
+    assert_eq!(rs.select0(rs.count_zeros()), None);
+}
+
+#[test]
+fn test_empty() {
+    check(&RSIncremental::new());
+}
+
+#[test]
+fn test_push() {
+    let mut rs = RSIncremental::new();
+    for i in 0..5000 {
+        rs.push(i % 3 == 0 || i % 1000 > 900);
+        if i % 97 == 0 || i % 512 == 511 {
+            check(&rs);
+        }
+    }
+    check(&rs);
+}
+
+#[test]
+fn test_append() {
+    use rand::Rng;
+
+    let mut rng = rand::thread_rng();
+    let mut rs = RSIncremental::new();
+    for round in 0..200 {
+        match round % 3 {
+            0 => {
+                let len = rng.gen_range(0..=64);
+                let bits: u64 = rng.gen();
+                let bits = if len == 64 {
+                    bits
+                } else {
+                    bits & ((1 << len) - 1)
+                };
+                rs.append_bits(bits, len);
+            }
+            1 => rs.extend_with_zeros(rng.gen_range(0..3000)),
+            _ => rs.push(rng.gen_bool(0.5)),
+        }
+        if round % 20 == 0 {
+            check(&rs);
+        }
+    }
+    check(&rs);
+}
+
+#[test]
+fn test_from() {
+    // Sparse and dense bit vectors
+    for vv in [
+        gen_strictly_increasing_sequence(1000, 1 << 20),
+        gen_strictly_increasing_sequence(50_000, 60_000),
+    ] {
+        let bv: BitVector = vv.iter().copied().collect();
+        let bvm: BitVectorMut = bv.into();
+        let mut rs = RSIncremental::from(bvm);
+        check(&rs);
+
+        rs.extend_with_zeros(1000);
+        rs.append_bits(u64::MAX, 64);
+        check(&rs);
+    }
+}
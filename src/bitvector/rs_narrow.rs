@@ -3,8 +3,13 @@
 //!
 //! This implementation is inspired by the C++ implementation by [Giuseppe Ottaviano](https://github.com/ot/succinct/blob/master/rs_bit_vector.cpp).
 
+use crate::persist::Persist;
+use crate::zero_copy::{LayoutError, LayoutReader, LayoutWriter, Storage, ZeroCopy};
+use crate::Error;
 use crate::{utils::select_in_word, AccessBin, BitVector, RankBin, SelectBin, SpaceUsage};
 
+use std::io::{self, Write};
+
 use serde::{Deserialize, Serialize};
 
 //block_rank_pairs layout
@@ -17,25 +22,62 @@ const SELECT_ZEROS_PER_HINT: usize = SELECT_ONES_PER_HINT;
 #[derive(Clone, Default, Serialize, Deserialize, Debug, Eq, PartialEq)]
 pub struct RSNarrow {
     bv: BitVector,
-    block_rank_pairs: Box<[u64]>,
-    select_samples: [Box<[usize]>; 2],
+    block_rank_pairs: Storage<u64>,
+    select_samples: [Storage<usize>; 2],
 }
 
 impl RSNarrow {
+    /// Builds the rank and select support of the bit vector `bv`.
+    ///
+    /// The construction is sequential, also with the `parallel` feature.
     pub fn new(bv: BitVector) -> Self {
         let mut block_rank_pairs = Vec::new();
+        let mut select_samples: [Vec<usize>; 2] = [Vec::new(), Vec::new()];
+
+        Self::for_each_counter(
+            &bv,
+            |pair| block_rank_pairs.push(pair),
+            |bit, sample| select_samples[bit].push(sample),
+        );
+
+        block_rank_pairs.shrink_to_fit();
+
+        Self {
+            bv,
+            block_rank_pairs: block_rank_pairs.into(),
+            select_samples: select_samples
+                .into_iter()
+                .map(Storage::from)
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap(),
+        }
+    }
+
+    /// Computes the counters of the rank and select support of `bv` in order.
+    /// Each entry of `block_rank_pairs` is passed to `push_pair`, and each select
+    /// sample of the bit `bit` is passed to `push_sample(bit, sample)`.
+    ///
+    /// The counters are not stored, so they can be either collected or compared
+    /// with the ones of a layout without allocating them.
+    fn for_each_counter<P, S>(bv: &BitVector, mut push_pair: P, mut push_sample: S)
+    where
+        P: FnMut(u64),
+        S: FnMut(usize, usize),
+    {
+        let mut n_entries = 0;
         let mut next_rank: u64 = 0;
         let mut cur_subrank: u64 = 0;
         let mut subranks: u64 = 0;
-        block_rank_pairs.push(0);
-        let mut select_samples: [Vec<usize>; 2] = [Vec::new(), Vec::new()];
+        push_pair(0);
+        n_entries += 1;
 
         let mut cur_hint_0 = 0;
         let mut cur_hint_1 = 0;
         let mut zeros_so_far = 0;
 
-        select_samples[0].push(0);
-        select_samples[1].push(0);
+        push_sample(0, 0);
+        push_sample(1, 0);
 
         // We split data into blocks of BLOCK_SIZE = 8 words each.
         // for each block stores BLOCK_SIZE-1=7 9bit entries
@@ -57,18 +99,19 @@ impl RSNarrow {
 
                 //check for samples
                 if next_rank / SELECT_ONES_PER_HINT as u64 > cur_hint_1 {
-                    select_samples[1].push(b);
+                    push_sample(1, b);
                     cur_hint_1 += 1;
                 }
                 zeros_so_far += 64 - word_pop;
                 if zeros_so_far / SELECT_ZEROS_PER_HINT as u64 > cur_hint_0 {
-                    select_samples[0].push(b);
+                    push_sample(0, b);
                     cur_hint_0 += 1;
                 }
 
                 if shift == BLOCK_SIZE - 1 {
-                    block_rank_pairs.push(subranks);
-                    block_rank_pairs.push(next_rank);
+                    push_pair(subranks);
+                    push_pair(next_rank);
+                    n_entries += 2;
                     subranks = 0;
                     cur_subrank = 0;
                 }
@@ -80,28 +123,23 @@ impl RSNarrow {
             subranks <<= 9;
             subranks |= cur_subrank;
         }
-        block_rank_pairs.push(subranks);
+        push_pair(subranks);
+        n_entries += 1;
 
         if bv.data.len() % BLOCK_SIZE > 0 {
-            block_rank_pairs.push(next_rank);
-            block_rank_pairs.push(0);
+            push_pair(next_rank);
+            push_pair(0);
+            n_entries += 2;
         }
 
-        select_samples[0].push((block_rank_pairs.len() / 2) - 1);
-        select_samples[1].push((block_rank_pairs.len() / 2) - 1);
-
-        block_rank_pairs.shrink_to_fit();
+        push_sample(0, (n_entries / 2) - 1);
+        push_sample(1, (n_entries / 2) - 1);
+    }
 
-        Self {
-            bv,
-            block_rank_pairs: block_rank_pairs.into_boxed_slice(),
-            select_samples: select_samples
-                .into_iter()
-                .map(|x| x.into_boxed_slice())
-                .collect::<Vec<_>>()
-                .try_into()
-                .unwrap(),
-        }
+    /// Returns the number of bits in the bitvector.
+    #[inline(always)]
+    pub fn bv_len(&self) -> usize {
+        self.bv.len()
     }
 
     /// Returns the number of bits set to 1 in the bitvector.
@@ -321,5 +359,79 @@ impl SpaceUsage for RSNarrow {
     }
 }
 
+impl Persist for RSNarrow {
+    fn type_tag() -> String {
+        "RSNarrow".to_string()
+    }
+
+    fn persisted_len(&self) -> usize {
+        self.bv.len()
+    }
+}
+
+impl ZeroCopy for RSNarrow {
+    fn write_layout<W: Write>(&self, writer: &mut LayoutWriter<W>) -> io::Result<()> {
+        self.bv.write_layout(writer)?;
+        writer.write_slice(&self.block_rank_pairs)?;
+        writer.write_slice(&self.select_samples[0])?;
+        writer.write_slice(&self.select_samples[1])
+    }
+
+    unsafe fn read_layout(reader: &mut LayoutReader<'_>) -> Result<Self, LayoutError> {
+        let bv = BitVector::read_layout(reader)?;
+        let block_rank_pairs: Storage<u64> = reader.read_storage()?;
+        let select_samples = [reader.read_storage()?, reader.read_storage()?];
+
+        // A pair for each block plus a final one, see `RSNarrow::new`
+        let n_blocks = bv.data.len();
+        let n_pairs = n_blocks + 1 + (n_blocks % BLOCK_SIZE > 0) as usize;
+        if block_rank_pairs.len() != 2 * n_pairs {
+            return Err(LayoutError::InvalidData("rank support of RSNarrow"));
+        }
+        if reader.is_trusted() {
+            return Ok(Self {
+                bv,
+                block_rank_pairs,
+                select_samples,
+            });
+        }
+
+        // The counters are checked while they are recomputed, without storing them
+        let mut pairs = block_rank_pairs.iter();
+        let mut samples = [select_samples[0].iter(), select_samples[1].iter()];
+        let (mut pairs_match, mut samples_match) = (true, true);
+        Self::for_each_counter(
+            &bv,
+            |pair| pairs_match &= pairs.next() == Some(&pair),
+            |bit, sample| samples_match &= samples[bit].next() == Some(&sample),
+        );
+        if !pairs_match
+            || !samples_match
+            || pairs.next().is_some()
+            || samples.iter_mut().any(|samples| samples.next().is_some())
+        {
+            return Err(LayoutError::InvalidData("rank support of RSNarrow"));
+        }
+        Ok(Self {
+            bv,
+            block_rank_pairs,
+            select_samples,
+        })
+    }
+}
+
+impl TryFrom<BitVector> for RSNarrow {
+    type Error = Error;
+
+    /// Builds the rank and select support for the bit vector `bv`.
+    ///
+    /// # Errors
+    /// Never fails, as [`RSNarrow`] indexes bit vectors of any length. The conversion
+    /// is fallible for uniformity with the other rank and select supports.
+    fn try_from(bv: BitVector) -> Result<Self, Error> {
+        Ok(Self::new(bv))
+    }
+}
+
 #[cfg(test)]
 mod tests;
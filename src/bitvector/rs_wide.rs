@@ -1,7 +1,11 @@
 //! Implements data structure to support `rank` and `select` queries on a binary vector with 512-bit blocks.
 //!
 //! This implementation is inspired by [this paper by Florian Kurpicz] (https://link.springer.com/chapter/10.1007/978-3-031-20643-6_19)
-use crate::{AccessBin, BitVector, RankBin, SelectBin, SpaceUsage};
+use crate::persist::Persist;
+use crate::zero_copy::{LayoutError, LayoutReader, LayoutWriter, Storage, ZeroCopy};
+use crate::{AccessBin, BitVector, Error, RankBin, SelectBin, SpaceUsage};
+
+use std::io::{self, Write};
 
 use serde::{Deserialize, Serialize};
 
@@ -15,24 +19,71 @@ const SELECT_ZEROS_PER_HINT: usize = SELECT_ONES_PER_HINT;
 #[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
 pub struct RSWide {
     bv: BitVector,
-    superblock_metadata: Box<[u128]>, // in each u128 we store the pair (superblock, <7 blocks>) like so |L1  |L2|L2|L2|L2|L2|L2|L2|
-    select_samples: [Box<[usize]>; 2],
+    superblock_metadata: Storage<u128>, // in each u128 we store the pair (superblock, <7 blocks>) like so |L1  |L2|L2|L2|L2|L2|L2|L2|
+    select_samples: [Storage<usize>; 2],
 }
 
 impl RSWide {
+    /// The largest number of bits that can be indexed, as the rank of
+    /// a superblock is stored in 44 bits.
+    pub const MAX_LEN: usize = (1 << 44) - 1;
+
+    /// Builds the rank and select support for the bit vector `bv`.
+    ///
+    /// ## Panics
+    /// Panics if the bit vector is longer than [`RSWide::MAX_LEN`] bits.
+    /// Use [`RSWide::try_from`] to get an error instead.
     pub fn new(bv: BitVector) -> Self {
+        assert!(
+            bv.len() <= Self::MAX_LEN,
+            "The bit vector is longer than 2^44-1 bits."
+        );
+
         let mut superblock_metadata = Vec::new();
+        let mut select_samples: [Vec<usize>; 2] = [Vec::new(), Vec::new()];
+
+        Self::for_each_counter(
+            &bv,
+            |metadata| superblock_metadata.push(metadata),
+            |bit, sample| select_samples[bit].push(sample),
+        );
+
+        superblock_metadata.shrink_to_fit();
+
+        Self {
+            bv,
+            superblock_metadata: superblock_metadata.into(),
+            select_samples: select_samples
+                .into_iter()
+                .map(Storage::from)
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap(),
+        }
+    }
+
+    /// Computes the counters of the rank and select support of `bv` in order.
+    /// The metadata of each superblock is passed to `push_metadata`, and each
+    /// select sample of the bit `bit` is passed to `push_sample(bit, sample)`.
+    ///
+    /// The counters are not stored, so they can be either collected or compared
+    /// with the ones of a layout without allocating them.
+    fn for_each_counter<M, S>(bv: &BitVector, mut push_metadata: M, mut push_sample: S)
+    where
+        M: FnMut(u128),
+        S: FnMut(usize, usize),
+    {
+        let mut n_superblocks = 0;
         let mut total_rank: u128 = 0;
         let mut cur_metadata: u128 = 0;
         let mut word_pop: u128 = 0;
         let mut zeros_so_far: u128 = 0;
-        let mut select_samples: [Vec<usize>; 2] = [Vec::new(), Vec::new()];
 
         let mut cur_hint_0 = 0;
         let mut cur_hint_1 = 0;
 
-        select_samples[0].push(0);
-        select_samples[1].push(0);
+        push_sample(0, 0);
+        push_sample(1, 0);
 
         for (b, &dl) in bv.data.iter().enumerate() {
             if b % 8 == 0 {
@@ -58,20 +109,21 @@ impl RSWide {
 
             if (total_rank + word_pop) / SELECT_ONES_PER_HINT as u128 > cur_hint_1 {
                 //we insert a new hint for 1
-                select_samples[1].push(b / 8);
+                push_sample(1, b / 8);
                 cur_hint_1 += 1;
             }
 
             zeros_so_far += dl.n_zeros() as u128;
             if (zeros_so_far / SELECT_ZEROS_PER_HINT as u128) > cur_hint_0 {
                 //we insert a new hint for 0
-                select_samples[0].push(b / 8);
+                push_sample(0, b / 8);
                 cur_hint_0 += 1;
             }
 
             if (b + 1) % 8 == 0 {
                 //next round we reset the metadata so we push it now
-                superblock_metadata.push(cur_metadata);
+                push_metadata(cur_metadata);
+                n_superblocks += 1;
                 // println!("Pushed superblock!");
             }
         }
@@ -88,7 +140,8 @@ impl RSWide {
                 // println!("new block! added metadata count");
             }
 
-            superblock_metadata.push(cur_metadata);
+            push_metadata(cur_metadata);
+            n_superblocks += 1;
             // println!("Pushed superblock!");
         }
 
@@ -96,25 +149,13 @@ impl RSWide {
         cur_metadata = 0;
         cur_metadata |= total_rank;
         cur_metadata <<= 128 - 44;
-        superblock_metadata.push(cur_metadata);
+        push_metadata(cur_metadata);
+        n_superblocks += 1;
         // println!("Pushed LAST superblock!");
 
-        superblock_metadata.shrink_to_fit();
-
         //guard at the end
-        select_samples[0].push(superblock_metadata.len() - 1);
-        select_samples[1].push(superblock_metadata.len() - 1);
-
-        Self {
-            bv,
-            superblock_metadata: superblock_metadata.into_boxed_slice(),
-            select_samples: select_samples
-                .into_iter()
-                .map(|x| x.into_boxed_slice())
-                .collect::<Vec<_>>()
-                .try_into()
-                .unwrap(),
-        }
+        push_sample(0, n_superblocks - 1);
+        push_sample(1, n_superblocks - 1);
     }
 
     /// Returns the number of bits set to 1 in the bitvector.
@@ -392,5 +433,83 @@ impl SpaceUsage for RSWide {
     }
 }
 
+impl Persist for RSWide {
+    fn type_tag() -> String {
+        "RSWide".to_string()
+    }
+
+    fn persisted_len(&self) -> usize {
+        self.bv.len()
+    }
+}
+
+impl ZeroCopy for RSWide {
+    fn write_layout<W: Write>(&self, writer: &mut LayoutWriter<W>) -> io::Result<()> {
+        self.bv.write_layout(writer)?;
+        writer.write_slice(&self.superblock_metadata)?;
+        writer.write_slice(&self.select_samples[0])?;
+        writer.write_slice(&self.select_samples[1])
+    }
+
+    unsafe fn read_layout(reader: &mut LayoutReader<'_>) -> Result<Self, LayoutError> {
+        let bv = BitVector::read_layout(reader)?;
+        let superblock_metadata: Storage<u128> = reader.read_storage()?;
+        let select_samples = [reader.read_storage()?, reader.read_storage()?];
+
+        // A superblock every 8 blocks plus a final one, see `RSWide::new`
+        let n_superblocks = bv.data.len().div_ceil(SUPERBLOCK_SIZE / BLOCK_SIZE) + 1;
+        if bv.len() > Self::MAX_LEN || superblock_metadata.len() != n_superblocks {
+            return Err(LayoutError::InvalidData("rank support of RSWide"));
+        }
+        if reader.is_trusted() {
+            return Ok(Self {
+                bv,
+                superblock_metadata,
+                select_samples,
+            });
+        }
+
+        // The counters are checked while they are recomputed, without storing them
+        let mut metadata = superblock_metadata.iter();
+        let mut samples = [select_samples[0].iter(), select_samples[1].iter()];
+        let (mut metadata_match, mut samples_match) = (true, true);
+        Self::for_each_counter(
+            &bv,
+            |superblock| metadata_match &= metadata.next() == Some(&superblock),
+            |bit, sample| samples_match &= samples[bit].next() == Some(&sample),
+        );
+        if !metadata_match
+            || !samples_match
+            || metadata.next().is_some()
+            || samples.iter_mut().any(|samples| samples.next().is_some())
+        {
+            return Err(LayoutError::InvalidData("rank support of RSWide"));
+        }
+        Ok(Self {
+            bv,
+            superblock_metadata,
+            select_samples,
+        })
+    }
+}
+
+impl TryFrom<BitVector> for RSWide {
+    type Error = Error;
+
+    /// Builds the rank and select support for the bit vector `bv`.
+    ///
+    /// # Errors
+    /// Returns [`Error::TooLong`] if the bit vector is longer than [`RSWide::MAX_LEN`] bits.
+    fn try_from(bv: BitVector) -> Result<Self, Error> {
+        if bv.len() > Self::MAX_LEN {
+            return Err(Error::TooLong {
+                len: bv.len(),
+                max_len: Self::MAX_LEN,
+            });
+        }
+        Ok(Self::new(bv))
+    }
+}
+
 #[cfg(test)]
 mod tests;
@@ -80,3 +80,49 @@ fn test_random_select0() {
         assert_eq!(selected, Some(el));
     }
 }
+
+#[test]
+fn test_layout_invalid_counters() {
+    use crate::zero_copy::AlignedBytes;
+
+    let vv = gen_strictly_increasing_sequence(5000, 200_000);
+    let rs = RSWide::new(vv.iter().copied().collect());
+
+    let view = |rs: &RSWide| {
+        let mut bytes = Vec::new();
+        rs.save_layout(&mut bytes).unwrap();
+        let buffer = AlignedBytes::from(&bytes[..]);
+        RSWide::view(&buffer).map(|view| assert_eq!(*view, *rs))
+    };
+    assert!(view(&rs).is_ok());
+
+    // A rank counter that does not match the bits
+    let mut tampered = rs.clone();
+    let mut superblock_metadata = tampered.superblock_metadata.to_vec();
+    superblock_metadata[2] += 1;
+    tampered.superblock_metadata = superblock_metadata.into();
+    assert_eq!(
+        view(&tampered),
+        Err(LayoutError::InvalidData("rank support of RSWide"))
+    );
+
+    // A select sample that does not match the bits
+    let mut tampered = rs.clone();
+    let mut samples = tampered.select_samples[1].to_vec();
+    samples[1] += 1;
+    tampered.select_samples[1] = samples.into();
+    assert_eq!(
+        view(&tampered),
+        Err(LayoutError::InvalidData("rank support of RSWide"))
+    );
+
+    // One more select sample than the ones of the bits
+    let mut tampered = rs.clone();
+    let mut samples = tampered.select_samples[0].to_vec();
+    samples.push(*samples.last().unwrap());
+    tampered.select_samples[0] = samples.into();
+    assert_eq!(
+        view(&tampered),
+        Err(LayoutError::InvalidData("rank support of RSWide"))
+    );
+}
@@ -134,3 +134,75 @@ fn test_set_symbol() {
 
     println!("got bit: {:?}", dl.get(i));
 }
+
+// Checks all the queries of a dynamic bit vector against the bits in `expected`.
+fn check_dynamic(dbv: &dynamic_bitvector::DynamicBitVector, expected: &[bool]) {
+    assert_eq!(dbv.len(), expected.len());
+    assert!(dbv.iter().eq(expected.iter().copied()));
+
+    let mut ones = 0;
+    for (i, &bit) in expected.iter().enumerate() {
+        assert_eq!(dbv.get(i), Some(bit));
+        assert_eq!(dbv.rank1(i), Some(ones));
+        assert_eq!(dbv.rank0(i), Some(i - ones));
+        if bit {
+            assert_eq!(dbv.select1(ones), Some(i));
+            ones += 1;
+        } else {
+            assert_eq!(dbv.select0(i - ones), Some(i));
+        }
+    }
+    let n = expected.len();
+    assert_eq!(dbv.get(n), None);
+    assert_eq!(dbv.rank1(n), Some(ones));
+    assert_eq!(dbv.rank1(n + 1), None);
+    assert_eq!(dbv.count_ones(), ones);
+    assert_eq!(dbv.count_zeros(), n - ones);
+    assert_eq!(dbv.select1(ones), None);
+    assert_eq!(dbv.select0(n - ones), None);
+}
+
+#[test]
+fn test_dynamic_bitvector() {
+    use dynamic_bitvector::DynamicBitVector;
+    use rand::Rng;
+
+    let mut rng = rand::thread_rng();
+    let mut dbv = DynamicBitVector::new();
+    let mut expected: Vec<bool> = Vec::new();
+    check_dynamic(&dbv, &expected);
+
+    // Random insertions make the tree grow by a few levels
+    for _ in 0..200_000 {
+        let i = rng.gen_range(0..=expected.len());
+        let bit = rng.gen_bool(0.3);
+        dbv.insert(i, bit);
+        expected.insert(i, bit);
+    }
+    check_dynamic(&dbv, &expected);
+
+    for _ in 0..10_000 {
+        let i = rng.gen_range(0..expected.len());
+        let bit = rng.gen_bool(0.5);
+        assert_eq!(dbv.set(i, bit), expected[i]);
+        expected[i] = bit;
+    }
+    check_dynamic(&dbv, &expected);
+
+    // Removals from a prefix make leaves and nodes underfull
+    while expected.len() > 1000 {
+        let i = rng.gen_range(0..expected.len().min(10_000));
+        assert_eq!(dbv.remove(i), expected.remove(i));
+    }
+    check_dynamic(&dbv, &expected);
+
+    while !expected.is_empty() {
+        let i = rng.gen_range(0..expected.len());
+        assert_eq!(dbv.remove(i), expected.remove(i));
+    }
+    check_dynamic(&dbv, &expected);
+
+    let expected: Vec<bool> = (0..10_000).map(|i| i % 3 == 0).collect();
+    let dbv: DynamicBitVector = expected.iter().copied().collect();
+    check_dynamic(&dbv, &expected);
+}
@@ -1,6 +1,6 @@
 //! The module implements [`DArray`], a data structure that provides efficient
 //! `select1` and `select0` queries on a binary vector, supporting the [`SelectBin`] trait ([`SelectBin::select0`] and [`SelectBin::select1`] queries).
-//! The rank queries are not supported.
+//! The rank queries of the [`RankBin`] trait are optionally supported.
 //!
 //! In many applications of this data structure, the binary vector is the characteristic
 //! vector of a strictly increasing sequence.
@@ -16,16 +16,24 @@
 //! ## Example
 //! A [`DArray`] is built from a strictly increasening sequence of `usize`.
 //! A boolean const generic is used to specify the need for `select0` query support.
-//! Without this support, the query [`SelectBin::select0`] will panic.
+//! Without this support, the query [`SelectBin::select0`] returns `None`.
+//! A second boolean const generic is used to specify the need for `rank` query support.
+//! Without this support, [`DArray`] does not implement [`RankBin`].
 //!
 //! ```
 //! use qwt::DArray;
 //! use qwt::{SpaceUsage, SelectBin, RankBin};
 //!
 //! let vv: Vec<usize> = vec![0, 12, 33, 42, 55, 61, 1000];
-//! let da: DArray<false> = vv.into_iter().collect();
+//! let da: DArray<false> = vv.iter().copied().collect();
 //!
 //! assert_eq!(da.select1(1), Some(12));
+//!
+//! // A full rank/select dictionary
+//! let da: DArray<false, true> = vv.into_iter().collect();
+//!
+//! assert_eq!(da.rank1(42), Some(3));
+//! assert_eq!(da.select1(da.rank1(42).unwrap()), Some(42));
 //! ```
 //!
 //! ## Technical details
@@ -59,11 +67,23 @@
 //! These three vectors are stored in a private struct `Inventories`.
 //! The const generic BITS in this struct allows us to build and store these vectors to support
 //! `select0` as well.
+//!
+//! The `rank` support is independent of the inventories, so it does not slow down
+//! `select` queries. The binary vector is split into superblocks of
+//! `RANK_SUPERBLOCK_SIZE` = 4096 bits and blocks of `RANK_BLOCK_SIZE` = 512 bits.
+//! We store the number of ones before each superblock in 64 bits, and the number of
+//! ones before each block, relative to its superblock, in 16 bits. A `rank1(i)` query
+//! sums these two counters and the number of ones in the (at most 8) words of the
+//! block of `i` up to position `i`. The space overhead is about 4.7% of the binary vector.
 
 use crate::bitvector::{BitVectorBitPositionsIter, BitVectorIter};
+use crate::persist::Persist;
 use crate::utils::select_in_word;
-use crate::BitVector;
-use crate::{AccessBin, SelectBin, SpaceUsage};
+use crate::zero_copy::{LayoutError, LayoutReader, LayoutWriter, Storage, ZeroCopy};
+use crate::{AccessBin, RankBin, SelectBin, SpaceUsage};
+use crate::{BitVector, Error};
+
+use std::io::{self, Write};
 
 use serde::{Deserialize, Serialize};
 #[cfg(target_arch = "x86_64")]
@@ -73,15 +93,88 @@ const BLOCK_SIZE: usize = 1024;
 const SUBBLOCK_SIZE: usize = 32;
 const MAX_IN_BLOCK_DISTACE: usize = 1 << 16;
 
+const RANK_BLOCK_SIZE: usize = 512; // in bits
+const RANK_SUPERBLOCK_SIZE: usize = 8 * RANK_BLOCK_SIZE; // in bits
+
 /// Const generic SELECT0_SUPPORT may optionally add
 /// extra data structures to support fast `select0` queries,
 /// which otherwise are not supported.
-
+///
+/// Const generic RANK_SUPPORT may optionally add
+/// extra data structures to support `rank` queries,
+/// which otherwise are not supported.
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub struct DArray<const SELECT0_SUPPORT: bool = false> {
+pub struct DArray<const SELECT0_SUPPORT: bool = false, const RANK_SUPPORT: bool = false> {
     bv: BitVector,
     ones_inventories: Inventories<true>,
     zeroes_inventories: Option<Inventories<false>>,
+    rank_support: Option<RankSupport>,
+}
+
+// Helper struct for DArray that stores the sampled
+// number of ones to support rank queries
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct RankSupport {
+    superblock_ranks: Storage<u64>, // number of ones before each superblock
+    block_ranks: Storage<u16>,      // number of ones before each block within its superblock
+}
+
+impl RankSupport {
+    fn new(bv: &BitVector) -> Self {
+        let n_words = bv.len().div_ceil(64);
+        // There is a block also for position `bv.len()`
+        let n_blocks = bv.len() / RANK_BLOCK_SIZE + 1;
+        let blocks_in_superblock = RANK_SUPERBLOCK_SIZE / RANK_BLOCK_SIZE;
+
+        let mut superblock_ranks = Vec::with_capacity(n_blocks.div_ceil(blocks_in_superblock));
+        let mut block_ranks = Vec::with_capacity(n_blocks);
+
+        let mut rank = 0;
+        let mut superblock_rank = 0;
+        for block in 0..n_blocks {
+            if block % blocks_in_superblock == 0 {
+                superblock_rank = rank;
+                superblock_ranks.push(rank as u64);
+            }
+            block_ranks.push((rank - superblock_rank) as u16);
+
+            let first_word = block * RANK_BLOCK_SIZE / 64;
+            for word_id in first_word..n_words.min(first_word + RANK_BLOCK_SIZE / 64) {
+                rank += bv.get_word(word_id).count_ones() as usize;
+            }
+        }
+
+        Self {
+            superblock_ranks: superblock_ranks.into(),
+            block_ranks: block_ranks.into(),
+        }
+    }
+
+    // Checks if the counters have the lengths needed to index a bit vector of `len` bits.
+    fn has_len(&self, len: usize) -> bool {
+        let n_blocks = len / RANK_BLOCK_SIZE + 1;
+        self.block_ranks.len() == n_blocks
+            && self.superblock_ranks.len()
+                == n_blocks.div_ceil(RANK_SUPERBLOCK_SIZE / RANK_BLOCK_SIZE)
+    }
+
+    // The position `i` must be at most the length of `bv`.
+    #[inline(always)]
+    unsafe fn rank1(&self, bv: &BitVector, i: usize) -> usize {
+        let mut rank = *self
+            .superblock_ranks
+            .get_unchecked(i / RANK_SUPERBLOCK_SIZE) as usize
+            + *self.block_ranks.get_unchecked(i / RANK_BLOCK_SIZE) as usize;
+
+        let last_word = i >> 6;
+        for word_id in (i / RANK_BLOCK_SIZE) * (RANK_BLOCK_SIZE / 64)..last_word {
+            rank += bv.get_word(word_id).count_ones() as usize;
+        }
+        if i & 63 > 0 {
+            rank += (bv.get_word(last_word) & ((1 << (i & 63)) - 1)).count_ones() as usize;
+        }
+        rank
+    }
 }
 
 // Helper struct for DArray that stores
@@ -90,9 +183,9 @@ pub struct DArray<const SELECT0_SUPPORT: bool = false> {
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
 struct Inventories<const BIT: bool> {
     n_sets: usize, // number of bits set to BIT
-    block_inventory: Box<[i64]>,
-    subblock_inventory: Box<[u16]>,
-    overflow_positions: Box<[usize]>,
+    block_inventory: Storage<i64>,
+    subblock_inventory: Storage<u16>,
+    overflow_positions: Storage<usize>,
 }
 
 /// Const generic BIT specifies if we are computing statistics
@@ -150,9 +243,9 @@ impl<const BIT: bool> Inventories<BIT> {
 
         Self {
             n_sets,
-            block_inventory: block_inventory.into_boxed_slice(),
-            subblock_inventory: subblock_inventory.into_boxed_slice(),
-            overflow_positions: overflow_positions.into_boxed_slice(),
+            block_inventory: block_inventory.into(),
+            subblock_inventory: subblock_inventory.into(),
+            overflow_positions: overflow_positions.into(),
         }
     }
 
@@ -181,10 +274,20 @@ impl<const BIT: bool> Inventories<BIT> {
     }
 }
 
-/// Const genetic SELECT0_SUPPORT
-impl<const SELECT0_SUPPORT: bool> DArray<SELECT0_SUPPORT> {
+/// Const generics SELECT0_SUPPORT and RANK_SUPPORT
+impl<const SELECT0_SUPPORT: bool, const RANK_SUPPORT: bool> DArray<SELECT0_SUPPORT, RANK_SUPPORT> {
+    /// The largest number of bits that can be indexed, as positions are stored
+    /// in the inventories as 64-bit signed integers.
+    pub const MAX_LEN: usize = i64::MAX as usize;
+
     /// Creates a [`DArray`] from a [`BitVector`].
     ///
+    /// The construction is sequential, also with the `parallel` feature.
+    ///
+    /// ## Panics
+    /// Panics if the bit vector is longer than [`DArray::MAX_LEN`] bits.
+    /// Use [`DArray::try_from`] to get an error instead.
+    ///
     /// # Examples
     ///
     /// ```
@@ -198,17 +301,28 @@ impl<const SELECT0_SUPPORT: bool> DArray<SELECT0_SUPPORT> {
     /// ```
     #[must_use]
     pub fn new(bv: BitVector) -> Self {
+        assert!(
+            bv.len() <= Self::MAX_LEN,
+            "The bit vector is longer than 2^63-1 bits."
+        );
+
         let ones_inventories = Inventories::new(&bv);
         let zeroes_inventories = if SELECT0_SUPPORT {
             Some(Inventories::new(&bv))
         } else {
             None
         };
+        let rank_support = if RANK_SUPPORT {
+            Some(RankSupport::new(&bv))
+        } else {
+            None
+        };
 
         DArray {
             bv,
             ones_inventories,
             zeroes_inventories,
+            rank_support,
         }
     }
 
@@ -423,7 +537,9 @@ impl<const SELECT0_SUPPORT: bool> DArray<SELECT0_SUPPORT> {
     }
 }
 
-impl<const SELECT0_SUPPORT: bool> AccessBin for DArray<SELECT0_SUPPORT> {
+impl<const SELECT0_SUPPORT: bool, const RANK_SUPPORT: bool> AccessBin
+    for DArray<SELECT0_SUPPORT, RANK_SUPPORT>
+{
     /// Returns the bit at the given position `i`, or [`None`] if `i` is out of bounds.
     ///
     /// # Examples
@@ -463,7 +579,9 @@ impl<const SELECT0_SUPPORT: bool> AccessBin for DArray<SELECT0_SUPPORT> {
     }
 }
 
-impl<const SELECT0_SUPPORT: bool> SelectBin for DArray<SELECT0_SUPPORT> {
+impl<const SELECT0_SUPPORT: bool, const RANK_SUPPORT: bool> SelectBin
+    for DArray<SELECT0_SUPPORT, RANK_SUPPORT>
+{
     /// Answers a `select1` query.
     ///
     /// The query `select1(i)` returns the position of the (i+1)-th
@@ -521,16 +639,17 @@ impl<const SELECT0_SUPPORT: bool> SelectBin for DArray<SELECT0_SUPPORT> {
     ///
     /// assert_eq!(da.select0(1), Some(2));
     /// assert_eq!(da.select0(11), Some(13));
+    ///
+    /// // Without support for `select0`, the query has no answer
+    /// let da: DArray<false> = vec![0, 12, 33].into_iter().collect();
+    /// assert_eq!(da.select0(1), None);
     /// ```
     ///
-    /// # Panics
-    /// It panics if [`DArray`] is built without support for `select0`query.
+    /// Returns `None` if [`DArray`] is built without support for `select0` query.
     #[must_use]
     #[inline(always)]
     fn select0(&self, i: usize) -> Option<usize> {
-        assert!(SELECT0_SUPPORT);
-
-        self.select(i, self.zeroes_inventories.as_ref().unwrap())
+        self.select(i, self.zeroes_inventories.as_ref()?)
     }
 
     /// Answers a `select0` query without checkin bounds.
@@ -548,15 +667,98 @@ impl<const SELECT0_SUPPORT: bool> SelectBin for DArray<SELECT0_SUPPORT> {
     /// assert_eq!(unsafe{da.select0_unchecked(1)}, 2);
     /// assert_eq!(unsafe{da.select0_unchecked(11)}, 13);
     /// ```
+    ///
+    /// # Safety
+    /// Calling this method on a [`DArray`] built without support for `select0` query
+    /// is undefined behavior.
     #[inline(always)]
     unsafe fn select0_unchecked(&self, i: usize) -> usize {
-        assert!(SELECT0_SUPPORT);
-
         self.select(i, self.zeroes_inventories.as_ref().unwrap())
             .unwrap()
     }
 }
 
+impl<const SELECT0_SUPPORT: bool> RankBin for DArray<SELECT0_SUPPORT, true> {
+    /// Returns the number of ones up to position `i` excluded, or [`None`]
+    /// if `i` is larger than the length of the binary vector.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::{DArray, RankBin};
+    ///
+    /// let v: Vec<usize> = vec![0, 12, 33, 42, 55, 61, 1000];
+    /// let da: DArray<false, true> = v.into_iter().collect(); // <false, true> to support the rank queries
+    ///
+    /// assert_eq!(da.rank1(0), Some(0));
+    /// assert_eq!(da.rank1(13), Some(2));
+    /// assert_eq!(da.rank0(13), Some(11));
+    /// assert_eq!(da.rank1(1001), Some(7));
+    /// assert_eq!(da.rank1(1002), None);
+    /// ```
+    #[inline(always)]
+    fn rank1(&self, i: usize) -> Option<usize> {
+        if i > self.bv.len() {
+            return None;
+        }
+        // SAFETY: bounds already checked
+        Some(unsafe { self.rank1_unchecked(i) })
+    }
+
+    /// Returns the number of ones up to position `i` excluded.
+    ///
+    /// # Safety
+    /// Calling this method with a position `i` larger than the length of the
+    /// binary vector is undefined behavior.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::{DArray, RankBin};
+    ///
+    /// let v: Vec<usize> = vec![0, 12, 33, 42, 55, 61, 1000];
+    /// let da: DArray<false, true> = v.into_iter().collect();
+    ///
+    /// assert_eq!(unsafe { da.rank1_unchecked(56) }, 5);
+    /// ```
+    #[inline(always)]
+    unsafe fn rank1_unchecked(&self, i: usize) -> usize {
+        match self.rank_support.as_ref() {
+            Some(rank_support) => rank_support.rank1(&self.bv, i),
+            None => 0, // Only an empty DArray, e.g., obtained with `Default`, has no rank support
+        }
+    }
+}
+
+/// Creates a [`DArray`] from a [`BitVector`].
+///
+/// # Errors
+/// Returns [`Error::TooLong`] if the bit vector is longer than [`DArray::MAX_LEN`] bits.
+///
+/// # Examples
+///
+/// ```
+/// use qwt::{BitVector, DArray, SelectBin};
+///
+/// let bv: BitVector = vec![0, 2, 3].into_iter().collect();
+/// let da = DArray::<false>::try_from(bv).unwrap();
+///
+/// assert_eq!(da.select1(1), Some(2));
+/// ```
+impl<const SELECT0_SUPPORT: bool, const RANK_SUPPORT: bool> TryFrom<BitVector>
+    for DArray<SELECT0_SUPPORT, RANK_SUPPORT>
+{
+    type Error = Error;
+
+    fn try_from(bv: BitVector) -> Result<Self, Error> {
+        if bv.len() > Self::MAX_LEN {
+            return Err(Error::TooLong {
+                len: bv.len(),
+                max_len: Self::MAX_LEN,
+            });
+        }
+        Ok(Self::new(bv))
+    }
+}
+
 /// Creates a [`DArray`] from an iterator over `bool` values.
 ///
 /// # Examples
@@ -570,12 +772,14 @@ impl<const SELECT0_SUPPORT: bool> SelectBin for DArray<SELECT0_SUPPORT> {
 /// assert_eq!(da.len(), 3);
 /// assert_eq!(da.get(1), Some(false));
 /// ```
-impl<const SELECT0_SUPPORT: bool> FromIterator<bool> for DArray<SELECT0_SUPPORT> {
+impl<const SELECT0_SUPPORT: bool, const RANK_SUPPORT: bool> FromIterator<bool>
+    for DArray<SELECT0_SUPPORT, RANK_SUPPORT>
+{
     fn from_iter<T>(iter: T) -> Self
     where
         T: IntoIterator<Item = bool>,
     {
-        DArray::<SELECT0_SUPPORT>::new(BitVector::from_iter(iter))
+        DArray::<SELECT0_SUPPORT, RANK_SUPPORT>::new(BitVector::from_iter(iter))
     }
 }
 
@@ -595,7 +799,8 @@ impl<const SELECT0_SUPPORT: bool> FromIterator<bool> for DArray<SELECT0_SUPPORT>
 /// assert_eq!(da.len(), 6);
 /// assert_eq!(da.get(3), Some(true));
 /// ```
-impl<V, const SELECT0_SUPPORT: bool> FromIterator<V> for DArray<SELECT0_SUPPORT>
+impl<V, const SELECT0_SUPPORT: bool, const RANK_SUPPORT: bool> FromIterator<V>
+    for DArray<SELECT0_SUPPORT, RANK_SUPPORT>
 where
     V: crate::bitvector::MyPrimInt + PartialOrd,
     <V as TryInto<usize>>::Error: std::fmt::Debug,
@@ -613,11 +818,13 @@ where
             "Sequence must be strictly increasing"
         );
 
-        DArray::<SELECT0_SUPPORT>::new(BitVector::from_iter(data))
+        DArray::<SELECT0_SUPPORT, RANK_SUPPORT>::new(BitVector::from_iter(data))
     }
 }
 
-impl<const SELECT0_SUPPORT: bool> SpaceUsage for DArray<SELECT0_SUPPORT> {
+impl<const SELECT0_SUPPORT: bool, const RANK_SUPPORT: bool> SpaceUsage
+    for DArray<SELECT0_SUPPORT, RANK_SUPPORT>
+{
     /// Returns the space usage of the data structure in bytes.
     fn space_usage_byte(&self) -> usize {
         let mut space = self.bv.space_usage_byte() + self.ones_inventories.space_usage_byte();
@@ -625,10 +832,133 @@ impl<const SELECT0_SUPPORT: bool> SpaceUsage for DArray<SELECT0_SUPPORT> {
         if let Some(p) = self.zeroes_inventories.as_ref() {
             space += p.space_usage_byte();
         }
+        if let Some(r) = self.rank_support.as_ref() {
+            space += r.space_usage_byte();
+        }
         space
     }
 }
 
+impl<const SELECT0_SUPPORT: bool, const RANK_SUPPORT: bool> Persist
+    for DArray<SELECT0_SUPPORT, RANK_SUPPORT>
+{
+    fn type_tag() -> String {
+        format!("DArray<{SELECT0_SUPPORT}, {RANK_SUPPORT}>")
+    }
+
+    fn persisted_len(&self) -> usize {
+        self.bv.len()
+    }
+}
+
+impl<const SELECT0_SUPPORT: bool, const RANK_SUPPORT: bool> ZeroCopy
+    for DArray<SELECT0_SUPPORT, RANK_SUPPORT>
+{
+    fn write_layout<W: Write>(&self, writer: &mut LayoutWriter<W>) -> io::Result<()> {
+        self.bv.write_layout(writer)?;
+        self.ones_inventories.write_layout(writer)?;
+        writer.write_u64(SELECT0_SUPPORT as u64)?;
+        if let Some(zeroes_inventories) = self.zeroes_inventories.as_ref() {
+            zeroes_inventories.write_layout(writer)?;
+        }
+        writer.write_u64(RANK_SUPPORT as u64)?;
+        if let Some(rank_support) = self.rank_support.as_ref() {
+            rank_support.write_layout(writer)?;
+        }
+        Ok(())
+    }
+
+    unsafe fn read_layout(reader: &mut LayoutReader<'_>) -> Result<Self, LayoutError> {
+        let bv = BitVector::read_layout(reader)?;
+        let ones_inventories = Inventories::read_layout(reader)?;
+        if reader.read_u64()? != SELECT0_SUPPORT as u64 {
+            return Err(LayoutError::InvalidData("select0 support of DArray"));
+        }
+        let zeroes_inventories = if SELECT0_SUPPORT {
+            Some(Inventories::read_layout(reader)?)
+        } else {
+            None
+        };
+        if reader.read_u64()? != RANK_SUPPORT as u64 {
+            return Err(LayoutError::InvalidData("rank support of DArray"));
+        }
+        let rank_support = if RANK_SUPPORT {
+            Some(RankSupport::read_layout(reader)?)
+        } else {
+            None
+        };
+
+        if bv.len() > Self::MAX_LEN
+            || ones_inventories.n_sets != bv.count_ones()
+            || zeroes_inventories
+                .as_ref()
+                .is_some_and(|inventories| inventories.n_sets != bv.count_zeros())
+        {
+            return Err(LayoutError::InvalidData("inventories of DArray"));
+        }
+        // The inventories are checked by recomputing them
+        if !reader.is_trusted()
+            && (ones_inventories != Inventories::new(&bv)
+                || zeroes_inventories
+                    .as_ref()
+                    .is_some_and(|inventories| *inventories != Inventories::new(&bv)))
+        {
+            return Err(LayoutError::InvalidData("inventories of DArray"));
+        }
+        if rank_support.as_ref().is_some_and(|rank_support| {
+            !rank_support.has_len(bv.len())
+                || (!reader.is_trusted() && *rank_support != RankSupport::new(&bv))
+        }) {
+            return Err(LayoutError::InvalidData("rank support of DArray"));
+        }
+
+        Ok(Self {
+            bv,
+            ones_inventories,
+            zeroes_inventories,
+            rank_support,
+        })
+    }
+}
+
+impl ZeroCopy for RankSupport {
+    fn write_layout<W: Write>(&self, writer: &mut LayoutWriter<W>) -> io::Result<()> {
+        writer.write_slice(&self.superblock_ranks)?;
+        writer.write_slice(&self.block_ranks)
+    }
+
+    unsafe fn read_layout(reader: &mut LayoutReader<'_>) -> Result<Self, LayoutError> {
+        Ok(Self {
+            superblock_ranks: reader.read_storage()?,
+            block_ranks: reader.read_storage()?,
+        })
+    }
+}
+
+impl SpaceUsage for RankSupport {
+    fn space_usage_byte(&self) -> usize {
+        self.superblock_ranks.space_usage_byte() + self.block_ranks.space_usage_byte()
+    }
+}
+
+impl<const BIT: bool> ZeroCopy for Inventories<BIT> {
+    fn write_layout<W: Write>(&self, writer: &mut LayoutWriter<W>) -> io::Result<()> {
+        writer.write_u64(self.n_sets as u64)?;
+        writer.write_slice(&self.block_inventory)?;
+        writer.write_slice(&self.subblock_inventory)?;
+        writer.write_slice(&self.overflow_positions)
+    }
+
+    unsafe fn read_layout(reader: &mut LayoutReader<'_>) -> Result<Self, LayoutError> {
+        Ok(Self {
+            n_sets: reader.read_usize()?,
+            block_inventory: reader.read_storage()?,
+            subblock_inventory: reader.read_storage()?,
+            overflow_positions: reader.read_storage()?,
+        })
+    }
+}
+
 impl<const BIT: bool> SpaceUsage for Inventories<BIT> {
     fn space_usage_byte(&self) -> usize {
         self.n_sets.space_usage_byte()
@@ -642,6 +972,7 @@ impl<const BIT: bool> SpaceUsage for Inventories<BIT> {
 mod tests {
     use super::*;
     use crate::perf_and_test_utils::{gen_strictly_increasing_sequence, negate_vector};
+    use crate::zero_copy::AlignedBytes;
 
     #[test]
     fn test_select1() {
@@ -689,5 +1020,80 @@ mod tests {
             let res = da.select0(i);
             assert_eq!(res.unwrap(), sel);
         }
+
+        // Without support for select0, the query has no answer instead of panicking
+        let da = DArray::<false>::try_from(da.bv.clone()).unwrap();
+        assert_eq!(da.select0(0), None);
+    }
+
+    fn check_rank<const SELECT0_SUPPORT: bool>(bv: &BitVector) {
+        let da = DArray::<SELECT0_SUPPORT, true>::new(bv.clone());
+
+        let mut rank = 0;
+        for i in 0..bv.len() {
+            assert_eq!(da.rank1(i), Some(rank));
+            assert_eq!(da.rank0(i), Some(i - rank));
+            if bv.get(i).unwrap() {
+                assert_eq!(da.select1(rank), Some(i));
+                rank += 1;
+            }
+        }
+        assert_eq!(da.rank1(bv.len()), Some(rank));
+        assert_eq!(da.rank1(bv.len() + 1), None);
+    }
+
+    #[test]
+    fn test_rank() {
+        check_rank::<false>(&BitVector::default());
+        assert_eq!(DArray::<false, true>::default().rank1(0), Some(0));
+
+        // Lengths at the boundaries of blocks and superblocks
+        for n in [1, 63, 64, 511, 512, 513, 4095, 4096, 4097, 10_000] {
+            let dense: BitVector = (0..n).map(|i| i % 3 != 0).collect();
+            check_rank::<false>(&dense);
+            let full: BitVector = (0..n).map(|_| true).collect();
+            check_rank::<true>(&full);
+        }
+
+        let vv = gen_strictly_increasing_sequence(1024 * 4, 1 << 16);
+        let bv: BitVector = vv.iter().copied().collect();
+        check_rank::<true>(&bv);
+
+        let da = DArray::<false, true>::new(bv.clone());
+        assert!(da.space_usage_byte() > DArray::<false>::new(bv).space_usage_byte());
+    }
+
+    #[test]
+    fn test_rank_support_layout() {
+        let bv: BitVector = (0..10_000).map(|i| i % 3 != 0).collect();
+        let view_err = |da: &DArray<false, true>| {
+            let mut bytes = Vec::new();
+            da.save_layout(&mut bytes).unwrap();
+            let buffer = AlignedBytes::from(&bytes[..]);
+            DArray::<false, true>::view(&buffer)
+                .map(|_| ())
+                .unwrap_err()
+        };
+
+        // A block missing
+        let mut da = DArray::<false, true>::new(bv.clone());
+        let rank_support = da.rank_support.as_mut().unwrap();
+        let block_ranks = rank_support.block_ranks.to_vec();
+        rank_support.block_ranks = block_ranks[..block_ranks.len() - 1].to_vec().into();
+        assert_eq!(
+            view_err(&da),
+            LayoutError::InvalidData("rank support of DArray")
+        );
+
+        // A wrong counter
+        let mut da = DArray::<false, true>::new(bv);
+        let rank_support = da.rank_support.as_mut().unwrap();
+        let mut superblock_ranks = rank_support.superblock_ranks.to_vec();
+        superblock_ranks[1] += 1;
+        rank_support.superblock_ranks = superblock_ranks.into();
+        assert_eq!(
+            view_err(&da),
+            LayoutError::InvalidData("rank support of DArray")
+        );
     }
 }
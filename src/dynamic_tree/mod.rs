@@ -0,0 +1,494 @@
+//! This module implements [`DynamicTree`], a B-tree over a sequence of small
+//! symbols in [0, SIGMA) that supports `access`, `rank`, and `select` queries
+//! together with insertions, deletions, and updates of symbols at arbitrary positions.
+//!
+//! The symbols are stored in leaves, each one a short sequence of at most
+//! [`DynamicLeaf::CAPACITY`] symbols packed in `DataLine`s. Each internal node stores,
+//! for each of its children, the number of symbols in the subtree of the child and
+//! the number of occurrences of each symbol in that subtree. A query descends from the
+//! root to a leaf by summing up the counters of the children to its left, and then
+//! solves the query within the leaf with a few popcounts. An update descends in
+//! the same way and fixes the counters on its way back.
+//!
+//! A leaf that exceeds its capacity is split in two halves. A leaf with less than
+//! a quarter of its capacity is merged with a sibling, and the result is split again
+//! if it is too large. Internal nodes are handled in the same way with a fanout
+//! between [`MIN_CHILDREN`] and [`MAX_CHILDREN`]. Thus, the tree is balanced and
+//! all the operations take $$O(\log n)$$ time, where $$n$$ is the length of the sequence.
+//!
+//! The module is shared by the dynamic data structures of the crate, which
+//! provide the leaves for their alphabet, i.e., [`DynamicQVector`](crate::qvector::dynamic_qvector::DynamicQVector)
+//! and [`DynamicBitVector`](crate::DynamicBitVector).
+
+use crate::SpaceUsage;
+
+/// The largest number of children of an internal node.
+const MAX_CHILDREN: usize = 16;
+/// The smallest number of children of an internal node but the root.
+const MIN_CHILDREN: usize = MAX_CHILDREN / 4;
+
+/// A leaf of a [`DynamicTree`], i.e., a short sequence of symbols in [0, SIGMA).
+///
+/// Positions are not checked: the tree guarantees that they are within the bounds
+/// of the leaf and that the symbols are smaller than SIGMA.
+pub(crate) trait DynamicLeaf<const SIGMA: usize>: Default + Clone + SpaceUsage {
+    /// The number of symbols above which a leaf is split.
+    const CAPACITY: usize;
+
+    /// Returns the number of symbols in the leaf.
+    fn len(&self) -> usize;
+
+    /// Returns the symbol at position `i`.
+    fn get(&self, i: usize) -> u8;
+
+    /// Returns the number of occurrences of `symbol` up to position `i` excluded.
+    fn rank(&self, symbol: u8, i: usize) -> usize;
+
+    /// Returns the position of the `i+1`-th occurrence of `symbol`, which must exist.
+    fn select(&self, symbol: u8, i: usize) -> usize;
+
+    /// Inserts `symbol` at position `i`, shifting the following symbols to the right.
+    fn insert(&mut self, i: usize, symbol: u8);
+
+    /// Removes and returns the symbol at position `i`, shifting the following
+    /// symbols to the left.
+    fn remove(&mut self, i: usize) -> u8;
+
+    /// Replaces the symbol at position `i` with `symbol` and returns the old one.
+    fn set(&mut self, i: usize, symbol: u8) -> u8;
+
+    /// Shortens the leaf to its first `len` symbols.
+    fn truncate(&mut self, len: usize);
+
+    /// Appends `symbol` at the end of the leaf.
+    #[inline]
+    fn push(&mut self, symbol: u8) {
+        self.insert(self.len(), symbol);
+    }
+
+    /// Splits the leaf at position `at` and returns the symbols from `at` on.
+    fn split_off(&mut self, at: usize) -> Self {
+        let mut right = Self::default();
+        for i in at..self.len() {
+            right.push(self.get(i));
+        }
+        self.truncate(at);
+        right
+    }
+
+    /// Appends the symbols of `other` at the end of the leaf.
+    fn append(&mut self, other: &Self) {
+        for i in 0..other.len() {
+            self.push(other.get(i));
+        }
+    }
+
+    /// Returns the number of occurrences of each symbol in the leaf.
+    fn counts(&self) -> [usize; SIGMA] {
+        let mut counts = [0; SIGMA];
+        for (symbol, count) in counts.iter_mut().enumerate() {
+            *count = self.rank(symbol as u8, self.len());
+        }
+        counts
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Node<L, const SIGMA: usize> {
+    Leaf(L),
+    Internal(Internal<L, SIGMA>),
+}
+
+#[derive(Clone, Debug)]
+struct Internal<L, const SIGMA: usize> {
+    children: Vec<Node<L, SIGMA>>,
+    lens: Vec<usize>, // The number of symbols in the subtree of each child
+    counts: Vec<[usize; SIGMA]>, // The occurrences of each symbol in the subtree of each child
+}
+
+/// A balanced tree over a sequence of symbols in [0, SIGMA), whose leaves are of type `L`.
+#[derive(Clone, Debug)]
+pub(crate) struct DynamicTree<L, const SIGMA: usize> {
+    root: Node<L, SIGMA>,
+    len: usize,
+    counts: [usize; SIGMA], // The occurrences of each symbol in the sequence
+}
+
+impl<L, const SIGMA: usize> Default for DynamicTree<L, SIGMA>
+where
+    L: DynamicLeaf<SIGMA>,
+{
+    fn default() -> Self {
+        Self {
+            root: Node::Leaf(L::default()),
+            len: 0,
+            counts: [0; SIGMA],
+        }
+    }
+}
+
+impl<L, const SIGMA: usize> DynamicTree<L, SIGMA>
+where
+    L: DynamicLeaf<SIGMA>,
+{
+    /// Returns the number of symbols in the sequence.
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the number of occurrences of `symbol` in the sequence.
+    #[inline]
+    pub(crate) fn occs(&self, symbol: u8) -> usize {
+        self.counts[symbol as usize]
+    }
+
+    /// Returns the symbol at position `i`, which must be smaller than the length.
+    pub(crate) fn get(&self, mut i: usize) -> u8 {
+        debug_assert!(i < self.len);
+        let mut node = &self.root;
+        loop {
+            match node {
+                Node::Leaf(leaf) => return leaf.get(i),
+                Node::Internal(internal) => {
+                    let child = internal.child_of(&mut i);
+                    node = &internal.children[child];
+                }
+            }
+        }
+    }
+
+    /// Returns the number of occurrences of `symbol` up to position `i` excluded.
+    /// The position must be at most the length.
+    pub(crate) fn rank(&self, symbol: u8, mut i: usize) -> usize {
+        debug_assert!(i <= self.len);
+        let mut rank = 0;
+        let mut node = &self.root;
+        loop {
+            match node {
+                Node::Leaf(leaf) => return rank + leaf.rank(symbol, i),
+                Node::Internal(internal) => {
+                    let mut child = 0;
+                    while child + 1 < internal.children.len() && i >= internal.lens[child] {
+                        i -= internal.lens[child];
+                        rank += internal.counts[child][symbol as usize];
+                        child += 1;
+                    }
+                    node = &internal.children[child];
+                }
+            }
+        }
+    }
+
+    /// Returns the position of the `i+1`-th occurrence of `symbol`, which must exist.
+    pub(crate) fn select(&self, symbol: u8, mut i: usize) -> usize {
+        debug_assert!(i < self.occs(symbol));
+        let mut pos = 0;
+        let mut node = &self.root;
+        loop {
+            match node {
+                Node::Leaf(leaf) => return pos + leaf.select(symbol, i),
+                Node::Internal(internal) => {
+                    let mut child = 0;
+                    while i >= internal.counts[child][symbol as usize] {
+                        i -= internal.counts[child][symbol as usize];
+                        pos += internal.lens[child];
+                        child += 1;
+                    }
+                    node = &internal.children[child];
+                }
+            }
+        }
+    }
+
+    /// Inserts `symbol` at position `i`, which must be at most the length.
+    pub(crate) fn insert(&mut self, i: usize, symbol: u8) {
+        debug_assert!(i <= self.len);
+        if let Some(right) = self.root.insert(i, symbol) {
+            // The root has been split, so the tree grows by one level
+            let left = std::mem::replace(&mut self.root, Node::Leaf(L::default()));
+            self.root = Node::Internal(Internal::from_children(vec![left, right]));
+        }
+        self.len += 1;
+        self.counts[symbol as usize] += 1;
+    }
+
+    /// Removes and returns the symbol at position `i`, which must be smaller than the length.
+    pub(crate) fn remove(&mut self, i: usize) -> u8 {
+        debug_assert!(i < self.len);
+        let symbol = self.root.remove(i);
+        self.len -= 1;
+        self.counts[symbol as usize] -= 1;
+
+        // A root with a single child is removed, so the tree shrinks by one level
+        while let Node::Internal(internal) = &mut self.root {
+            if internal.children.len() > 1 {
+                break;
+            }
+            self.root = internal.children.pop().unwrap();
+        }
+        symbol
+    }
+
+    /// Replaces the symbol at position `i`, which must be smaller than the length,
+    /// with `symbol` and returns the old one.
+    pub(crate) fn set(&mut self, i: usize, symbol: u8) -> u8 {
+        debug_assert!(i < self.len);
+        let old = self.root.set(i, symbol);
+        self.counts[old as usize] -= 1;
+        self.counts[symbol as usize] += 1;
+        old
+    }
+
+    /// Appends `symbol` at the end of the sequence.
+    #[inline]
+    pub(crate) fn push(&mut self, symbol: u8) {
+        self.insert(self.len, symbol);
+    }
+
+    /// Returns the leaves of the tree, from left to right.
+    pub(crate) fn leaves(&self) -> Vec<&L> {
+        let mut leaves = Vec::new();
+        self.root.collect_leaves(&mut leaves);
+        leaves
+    }
+}
+
+impl<L, const SIGMA: usize> Node<L, SIGMA>
+where
+    L: DynamicLeaf<SIGMA>,
+{
+    fn len(&self) -> usize {
+        match self {
+            Self::Leaf(leaf) => leaf.len(),
+            Self::Internal(internal) => internal.lens.iter().sum(),
+        }
+    }
+
+    fn counts(&self) -> [usize; SIGMA] {
+        match self {
+            Self::Leaf(leaf) => leaf.counts(),
+            Self::Internal(internal) => {
+                let mut counts = [0; SIGMA];
+                for child_counts in internal.counts.iter() {
+                    for (count, child_count) in counts.iter_mut().zip(child_counts) {
+                        *count += child_count;
+                    }
+                }
+                counts
+            }
+        }
+    }
+
+    fn is_underfull(&self) -> bool {
+        match self {
+            Self::Leaf(leaf) => leaf.len() < L::CAPACITY / 4,
+            Self::Internal(internal) => internal.children.len() < MIN_CHILDREN,
+        }
+    }
+
+    fn is_overfull(&self) -> bool {
+        match self {
+            Self::Leaf(leaf) => leaf.len() > L::CAPACITY,
+            Self::Internal(internal) => internal.children.len() > MAX_CHILDREN,
+        }
+    }
+
+    // Moves the second half of the node to a new node, which is returned.
+    fn split_half(&mut self) -> Self {
+        match self {
+            Self::Leaf(leaf) => Self::Leaf(leaf.split_off(leaf.len() / 2)),
+            Self::Internal(internal) => {
+                let at = internal.children.len() / 2;
+                Self::Internal(Internal {
+                    children: internal.children.split_off(at),
+                    lens: internal.lens.split_off(at),
+                    counts: internal.counts.split_off(at),
+                })
+            }
+        }
+    }
+
+    // Appends the content of `other`, which is at the same depth, to the node.
+    fn append(&mut self, other: Self) {
+        match (self, other) {
+            (Self::Leaf(leaf), Self::Leaf(other)) => leaf.append(&other),
+            (Self::Internal(internal), Self::Internal(mut other)) => {
+                internal.children.append(&mut other.children);
+                internal.lens.append(&mut other.lens);
+                internal.counts.append(&mut other.counts);
+            }
+            _ => unreachable!("all the leaves are at the same depth"),
+        }
+    }
+
+    // Inserts the symbol and returns the new right sibling of the node if it has been split.
+    fn insert(&mut self, mut i: usize, symbol: u8) -> Option<Self> {
+        match self {
+            Self::Leaf(leaf) => {
+                if leaf.len() < L::CAPACITY {
+                    leaf.insert(i, symbol);
+                    return None;
+                }
+                let mut right = leaf.split_off(leaf.len() / 2);
+                if i <= leaf.len() {
+                    leaf.insert(i, symbol);
+                } else {
+                    right.insert(i - leaf.len(), symbol);
+                }
+                Some(Self::Leaf(right))
+            }
+            Self::Internal(internal) => {
+                // Insertions at the end of a child go to that child
+                let mut child = 0;
+                while child + 1 < internal.children.len() && i > internal.lens[child] {
+                    i -= internal.lens[child];
+                    child += 1;
+                }
+
+                let split = internal.children[child].insert(i, symbol);
+                internal.lens[child] += 1;
+                internal.counts[child][symbol as usize] += 1;
+
+                let right = split?;
+                internal.move_to_new_child(child, right);
+                if internal.children.len() > MAX_CHILDREN {
+                    return Some(self.split_half());
+                }
+                None
+            }
+        }
+    }
+
+    fn remove(&mut self, mut i: usize) -> u8 {
+        match self {
+            Self::Leaf(leaf) => leaf.remove(i),
+            Self::Internal(internal) => {
+                let child = internal.child_of(&mut i);
+                let symbol = internal.children[child].remove(i);
+                internal.lens[child] -= 1;
+                internal.counts[child][symbol as usize] -= 1;
+
+                if internal.children[child].is_underfull() && internal.children.len() > 1 {
+                    internal.rebalance(child);
+                }
+                symbol
+            }
+        }
+    }
+
+    fn set(&mut self, mut i: usize, symbol: u8) -> u8 {
+        match self {
+            Self::Leaf(leaf) => leaf.set(i, symbol),
+            Self::Internal(internal) => {
+                let child = internal.child_of(&mut i);
+                let old = internal.children[child].set(i, symbol);
+                internal.counts[child][old as usize] -= 1;
+                internal.counts[child][symbol as usize] += 1;
+                old
+            }
+        }
+    }
+
+    fn collect_leaves<'a>(&'a self, leaves: &mut Vec<&'a L>) {
+        match self {
+            Self::Leaf(leaf) => leaves.push(leaf),
+            Self::Internal(internal) => {
+                for child in internal.children.iter() {
+                    child.collect_leaves(leaves);
+                }
+            }
+        }
+    }
+}
+
+impl<L, const SIGMA: usize> Internal<L, SIGMA>
+where
+    L: DynamicLeaf<SIGMA>,
+{
+    fn from_children(children: Vec<Node<L, SIGMA>>) -> Self {
+        let lens = children.iter().map(|child| child.len()).collect();
+        let counts = children.iter().map(|child| child.counts()).collect();
+        Self {
+            children,
+            lens,
+            counts,
+        }
+    }
+
+    // Returns the child containing position `i` and makes `i` relative to that child.
+    #[inline]
+    fn child_of(&self, i: &mut usize) -> usize {
+        let mut child = 0;
+        while child + 1 < self.children.len() && *i >= self.lens[child] {
+            *i -= self.lens[child];
+            child += 1;
+        }
+        child
+    }
+
+    // Inserts `right`, which has been split from `child`, just after it.
+    fn move_to_new_child(&mut self, child: usize, right: Node<L, SIGMA>) {
+        let right_len = right.len();
+        let right_counts = right.counts();
+        self.lens[child] -= right_len;
+        for (count, right_count) in self.counts[child].iter_mut().zip(right_counts) {
+            *count -= right_count;
+        }
+        self.children.insert(child + 1, right);
+        self.lens.insert(child + 1, right_len);
+        self.counts.insert(child + 1, right_counts);
+    }
+
+    // Merges the underfull `child` with a sibling, and splits the result again if it is too large.
+    fn rebalance(&mut self, child: usize) {
+        let left = if child + 1 < self.children.len() {
+            child
+        } else {
+            child - 1
+        };
+        let right = self.children.remove(left + 1);
+        self.lens.remove(left + 1);
+        self.counts.remove(left + 1);
+
+        let node = &mut self.children[left];
+        node.append(right);
+        self.lens[left] = node.len();
+        self.counts[left] = node.counts();
+
+        if node.is_overfull() {
+            let right = node.split_half();
+            self.move_to_new_child(left, right);
+        }
+    }
+}
+
+impl<L, const SIGMA: usize> SpaceUsage for DynamicTree<L, SIGMA>
+where
+    L: DynamicLeaf<SIGMA>,
+{
+    /// Returns the space usage of the tree in bytes.
+    fn space_usage_byte(&self) -> usize {
+        self.root.space_usage_byte() + std::mem::size_of::<Self>()
+    }
+}
+
+impl<L, const SIGMA: usize> SpaceUsage for Node<L, SIGMA>
+where
+    L: DynamicLeaf<SIGMA>,
+{
+    fn space_usage_byte(&self) -> usize {
+        match self {
+            Self::Leaf(leaf) => leaf.space_usage_byte(),
+            Self::Internal(internal) => {
+                internal
+                    .children
+                    .iter()
+                    .map(|child| child.space_usage_byte())
+                    .sum::<usize>()
+                    + internal.lens.capacity() * std::mem::size_of::<usize>()
+                    + internal.counts.capacity() * std::mem::size_of::<[usize; SIGMA]>()
+            }
+        }
+    }
+}
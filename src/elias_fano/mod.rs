@@ -0,0 +1,450 @@
+//! The module implements [`EliasFano`], a compressed representation of a
+//! non-decreasing sequence of `usize`, e.g., a posting list of an inverted index.
+//!
+//! A [`DArray`] can represent a strictly increasing sequence with its characteristic
+//! vector, which needs as many bits as the largest value of the sequence. This is
+//! a waste of space for sparse sequences. The Elias–Fano representation needs
+//! instead at most 2 + log(u/n) bits per value, where u is the largest value and
+//! n is the length of the sequence.
+//!
+//! Besides accessing the i-th value with [`AccessUnsigned::get`], [`EliasFano`] supports
+//! [`EliasFano::rank`], i.e., the number of values smaller than x,
+//! [`EliasFano::successor`], i.e., the smallest value larger than or equal to x,
+//! [`EliasFano::predecessor`], i.e., the largest value smaller than or equal to x,
+//! and iteration over the values.
+//!
+//! ## Example
+//!
+//! ```
+//! use qwt::{AccessUnsigned, EliasFano, SpaceUsage};
+//!
+//! let v: Vec<usize> = vec![3, 12, 12, 33, 42, 55, 61, 1000];
+//! let ef: EliasFano = v.iter().copied().collect();
+//!
+//! assert_eq!(ef.len(), 8);
+//! assert_eq!(ef.get(3), Some(33));
+//! assert_eq!(ef.rank(42), 4);
+//! assert_eq!(ef.successor(43), Some(55));
+//! assert_eq!(ef.predecessor(43), Some(42));
+//! assert_eq!(ef.iter().collect::<Vec<_>>(), v);
+//!
+//! println!("Space usage: {} bytes", ef.space_usage_byte());
+//! ```
+//!
+//! ## Technical details
+//! The Elias–Fano representation has been introduced in *P. Elias. Efficient storage and retrieval by content and address of static files. Journal of the ACM, 1974*
+//! and in *R. M. Fano. On the number of bits required to implement an associative memory. Memorandum 61, Computer Structures Group, MIT, 1971*.
+//!
+//! Each value is split into its `low_len` = ⌊log(u/n)⌋ least significant bits, the *low bits*,
+//! and the remaining most significant bits, the *high bits*. The low bits of all the values
+//! are concatenated in a [`BitVector`]. The high bits are non-decreasing, so they are
+//! encoded in unary as gaps: the i-th value sets the bit at position `high + i` of a
+//! binary vector of about 2n bits. This binary vector is indexed by a [`DArray<true>`].
+//!
+//! Thus, the high bits of the i-th value are `select1(i) - i`. The values with high bits
+//! equal to h are the ones between the (h-1)-th and the h-th zero of the binary vector,
+//! which we find with two `select0` queries. The queries `rank`, `successor`, and
+//! `predecessor` scan these values to compare their low bits.
+
+use crate::bitvector::BitVectorBitPositionsIter;
+use crate::persist::Persist;
+use crate::zero_copy::{LayoutError, LayoutReader, LayoutWriter, ZeroCopy};
+use crate::{AccessBin, AccessUnsigned, BitVector, BitVectorMut, DArray, SelectBin, SpaceUsage};
+
+use std::io::{self, Write};
+
+use serde::{Deserialize, Serialize};
+
+/// An Elias–Fano representation of a non-decreasing sequence of `usize`.
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EliasFano {
+    high_bits: DArray<true>,
+    low_bits: BitVector,
+    low_len: usize,
+    n: usize,
+}
+
+impl EliasFano {
+    /// Creates an [`EliasFano`] from a non-decreasing sequence of `usize`.
+    ///
+    /// ## Panics
+    /// Panics if the sequence is not sorted in non-decreasing order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{AccessUnsigned, EliasFano};
+    ///
+    /// let ef = EliasFano::new(&[0, 2, 2, 3, 4, 5]);
+    ///
+    /// assert_eq!(ef.len(), 6);
+    /// assert_eq!(ef.get(2), Some(2));
+    /// ```
+    #[must_use]
+    pub fn new(values: &[usize]) -> Self {
+        assert!(
+            values.windows(2).all(|w| w[0] <= w[1]),
+            "The sequence is not sorted in non-decreasing order."
+        );
+
+        let n = values.len();
+        let Some(&last) = values.last() else {
+            return Self::default();
+        };
+
+        // ⌊log(u/n)⌋ with u = last + 1, computed without overflowing. It is 64 only
+        // for a single value larger than 2^63, so we cap it to keep the shifts in range.
+        let ratio = (last as u128 + 1) / n as u128;
+        let low_len = if ratio > 1 {
+            (ratio.ilog2() as usize).min(usize::BITS as usize - 1)
+        } else {
+            0
+        };
+
+        let mut low_bits = BitVectorMut::with_capacity(n * low_len);
+        if low_len > 0 {
+            let mask = (1 << low_len) - 1;
+            for &v in values {
+                low_bits.append_bits((v & mask) as u64, low_len);
+            }
+        }
+
+        let high_bits: DArray<true> = values
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| (v >> low_len) + i)
+            .collect();
+
+        Self {
+            high_bits,
+            low_bits: low_bits.into(),
+            low_len,
+            n,
+        }
+    }
+
+    /// Returns the number of values smaller than `x`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::EliasFano;
+    ///
+    /// let ef = EliasFano::new(&[3, 12, 12, 33, 1000]);
+    ///
+    /// assert_eq!(ef.rank(0), 0);
+    /// assert_eq!(ef.rank(12), 1);
+    /// assert_eq!(ef.rank(13), 3);
+    /// assert_eq!(ef.rank(usize::MAX), 5);
+    /// ```
+    #[must_use]
+    pub fn rank(&self, x: usize) -> usize {
+        let high = x >> self.low_len;
+        // The number of zeros is the largest high bits in the sequence
+        if self.n == 0 || high > self.high_bits.count_zeros() {
+            return self.n;
+        }
+        let low = x & self.low_mask();
+
+        // SAFETY: high is at most the number of zeros
+        let mut rank = unsafe { self.bucket_start(high) };
+        let mut pos = rank + high;
+        // SAFETY: positions of ones in the high bits are the ones of the values
+        while pos < self.high_bits.len()
+            && unsafe { self.high_bits.get_unchecked(pos) }
+            && unsafe { self.get_low(rank) } < low
+        {
+            rank += 1;
+            pos += 1;
+        }
+        rank
+    }
+
+    /// Returns the smallest value larger than or equal to `x`, or [`None`]
+    /// if all the values are smaller than `x`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::EliasFano;
+    ///
+    /// let ef = EliasFano::new(&[3, 12, 12, 33, 1000]);
+    ///
+    /// assert_eq!(ef.successor(0), Some(3));
+    /// assert_eq!(ef.successor(12), Some(12));
+    /// assert_eq!(ef.successor(34), Some(1000));
+    /// assert_eq!(ef.successor(1001), None);
+    /// ```
+    #[must_use]
+    pub fn successor(&self, x: usize) -> Option<usize> {
+        self.get(self.rank(x))
+    }
+
+    /// Returns the largest value smaller than or equal to `x`, or [`None`]
+    /// if all the values are larger than `x`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::EliasFano;
+    ///
+    /// let ef = EliasFano::new(&[3, 12, 12, 33, 1000]);
+    ///
+    /// assert_eq!(ef.predecessor(2), None);
+    /// assert_eq!(ef.predecessor(12), Some(12));
+    /// assert_eq!(ef.predecessor(999), Some(33));
+    /// assert_eq!(ef.predecessor(usize::MAX), Some(1000));
+    /// ```
+    #[must_use]
+    pub fn predecessor(&self, x: usize) -> Option<usize> {
+        let rank = match x.checked_add(1) {
+            Some(y) => self.rank(y),
+            None => self.n,
+        };
+        rank.checked_sub(1).and_then(|i| self.get(i))
+    }
+
+    /// Returns an iterator over the values of the sequence.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::EliasFano;
+    ///
+    /// let ef = EliasFano::new(&[3, 12, 12, 33, 1000]);
+    ///
+    /// assert_eq!(ef.iter().collect::<Vec<_>>(), vec![3, 12, 12, 33, 1000]);
+    /// assert_eq!(ef.iter().skip(2).next(), Some(12));
+    /// ```
+    pub fn iter(&self) -> EliasFanoIter<'_> {
+        EliasFanoIter {
+            ef: self,
+            high_ones: self.high_bits.ones(),
+            i: 0,
+        }
+    }
+
+    /// Returns the number of values in the sequence.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::EliasFano;
+    ///
+    /// let ef = EliasFano::new(&[3, 12, 12, 33, 1000]);
+    ///
+    /// assert_eq!(ef.len(), 5);
+    /// ```
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Checks if the sequence is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::EliasFano;
+    ///
+    /// let ef = EliasFano::new(&[]);
+    ///
+    /// assert!(ef.is_empty());
+    /// ```
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    #[inline(always)]
+    fn low_mask(&self) -> usize {
+        (1 << self.low_len) - 1
+    }
+
+    // Returns the low bits of the i-th value.
+    #[inline(always)]
+    unsafe fn get_low(&self, i: usize) -> usize {
+        if self.low_len == 0 {
+            return 0;
+        }
+        self.low_bits
+            .get_bits_unchecked(i * self.low_len, self.low_len) as usize
+    }
+
+    // Returns the number of values whose high bits are smaller than `high`.
+    // The caller must guarantee that `high` is at most the number of zeros of the high bits.
+    #[inline(always)]
+    unsafe fn bucket_start(&self, high: usize) -> usize {
+        if high == 0 {
+            return 0;
+        }
+        self.high_bits.select0_unchecked(high - 1) + 1 - high
+    }
+}
+
+impl AccessUnsigned for EliasFano {
+    type Item = usize;
+
+    /// Returns the `i`-th value of the sequence, or [`None`] if `i` is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{AccessUnsigned, EliasFano};
+    ///
+    /// let ef = EliasFano::new(&[3, 12, 12, 33, 1000]);
+    ///
+    /// assert_eq!(ef.get(4), Some(1000));
+    /// assert_eq!(ef.get(5), None);
+    /// ```
+    #[inline(always)]
+    fn get(&self, i: usize) -> Option<usize> {
+        if i >= self.n {
+            return None;
+        }
+        // SAFETY: bounds already checked
+        Some(unsafe { self.get_unchecked(i) })
+    }
+
+    /// Returns the `i`-th value of the sequence.
+    ///
+    /// # Safety
+    /// Calling this method with an out-of-bounds index is undefined behavior.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{AccessUnsigned, EliasFano};
+    ///
+    /// let ef = EliasFano::new(&[3, 12, 12, 33, 1000]);
+    ///
+    /// assert_eq!(unsafe { ef.get_unchecked(1) }, 12);
+    /// ```
+    #[inline(always)]
+    unsafe fn get_unchecked(&self, i: usize) -> usize {
+        let high = self.high_bits.select1_unchecked(i) - i;
+        (high << self.low_len) | self.get_low(i)
+    }
+}
+
+/// Creates an [`EliasFano`] from an iterator over a non-decreasing sequence of `usize`.
+///
+/// ## Panics
+/// Panics if the sequence is not sorted in non-decreasing order.
+///
+/// # Examples
+///
+/// ```
+/// use qwt::{AccessUnsigned, EliasFano};
+///
+/// let ef: EliasFano = (0..100).map(|x| x * x).collect();
+///
+/// assert_eq!(ef.get(10), Some(100));
+/// ```
+impl FromIterator<usize> for EliasFano {
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = usize>,
+    {
+        let values: Vec<usize> = iter.into_iter().collect();
+        EliasFano::new(&values)
+    }
+}
+
+/// An iterator over the values of an [`EliasFano`].
+pub struct EliasFanoIter<'a> {
+    ef: &'a EliasFano,
+    high_ones: BitVectorBitPositionsIter<'a, true>,
+    i: usize,
+}
+
+impl Iterator for EliasFanoIter<'_> {
+    type Item = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let pos = self.high_ones.next()?;
+        let high = pos - self.i;
+        // SAFETY: there is a value for each bit set in the high bits
+        let low = unsafe { self.ef.get_low(self.i) };
+        self.i += 1;
+        Some((high << self.ef.low_len) | low)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.ef.n - self.i;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for EliasFanoIter<'_> {}
+
+impl<'a> IntoIterator for &'a EliasFano {
+    type Item = usize;
+    type IntoIter = EliasFanoIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl SpaceUsage for EliasFano {
+    /// Returns the space usage of the data structure in bytes.
+    fn space_usage_byte(&self) -> usize {
+        self.high_bits.space_usage_byte()
+            + self.low_bits.space_usage_byte()
+            + self.low_len.space_usage_byte()
+            + self.n.space_usage_byte()
+    }
+}
+
+impl Persist for EliasFano {
+    fn type_tag() -> String {
+        "EliasFano".to_owned()
+    }
+
+    fn persisted_len(&self) -> usize {
+        self.n
+    }
+}
+
+impl ZeroCopy for EliasFano {
+    fn write_layout<W: Write>(&self, writer: &mut LayoutWriter<W>) -> io::Result<()> {
+        writer.write_u64(self.n as u64)?;
+        writer.write_u64(self.low_len as u64)?;
+        self.high_bits.write_layout(writer)?;
+        self.low_bits.write_layout(writer)
+    }
+
+    unsafe fn read_layout(reader: &mut LayoutReader<'_>) -> Result<Self, LayoutError> {
+        let n = reader.read_usize()?;
+        let low_len = reader.read_usize()?;
+        let high_bits = DArray::read_layout(reader)?;
+        let low_bits = BitVector::read_layout(reader)?;
+
+        // The unchecked queries rely on the consistency of the components
+        if low_len >= usize::BITS as usize
+            || high_bits.count_ones() != n
+            || n.checked_mul(low_len) != Some(low_bits.len())
+        {
+            return Err(LayoutError::InvalidData("EliasFano"));
+        }
+
+        let ef = Self {
+            high_bits,
+            low_bits,
+            low_len,
+            n,
+        };
+
+        // The queries scan the low bits of a bucket in order, so the values must be sorted
+        if !reader.is_trusted() && !ef.iter().is_sorted() {
+            return Err(LayoutError::InvalidData("values of EliasFano"));
+        }
+
+        Ok(ef)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,123 @@
+use super::*;
+use crate::perf_and_test_utils::gen_strictly_increasing_sequence;
+use crate::zero_copy::AlignedBytes;
+
+// Checks all the queries against a naive implementation on the sorted `values`.
+fn check(values: &[usize]) {
+    let ef = EliasFano::new(values);
+
+    assert_eq!(ef.len(), values.len());
+    assert_eq!(ef.is_empty(), values.is_empty());
+    for (i, &v) in values.iter().enumerate() {
+        assert_eq!(ef.get(i), Some(v));
+    }
+    assert_eq!(ef.get(values.len()), None);
+    assert_eq!(ef.iter().len(), values.len());
+    assert!(ef.iter().eq(values.iter().copied()));
+
+    let max = values.last().copied().unwrap_or(0);
+    let queries = values
+        .iter()
+        .flat_map(|&v| [v.saturating_sub(1), v, v.saturating_add(1)])
+        .chain([0, max.saturating_add(100)]);
+    for x in queries {
+        let rank = values.partition_point(|&v| v < x);
+        assert_eq!(ef.rank(x), rank);
+        assert_eq!(ef.successor(x), values.get(rank).copied());
+
+        let n_leq = values.partition_point(|&v| v <= x);
+        assert_eq!(ef.predecessor(x), n_leq.checked_sub(1).map(|i| values[i]));
+    }
+}
+
+#[test]
+fn test_small() {
+    check(&[]);
+    check(&[0]);
+    check(&[42]);
+    check(&[usize::MAX]);
+    check(&[0, 0, 0, 0]);
+    check(&[5, 5, 5, 1 << 20, 1 << 20]);
+    check(&[3, 12, 12, 33, 42, 55, 61, 1000]);
+    check(&(0..1000).collect::<Vec<_>>());
+
+    // The largest values do not overflow
+    let values = [0, 1, usize::MAX / 2, usize::MAX - 1, usize::MAX];
+    let ef = EliasFano::new(&values);
+    assert!(ef.iter().eq(values.iter().copied()));
+    assert_eq!(ef.rank(usize::MAX), 4);
+    assert_eq!(ef.successor(usize::MAX), Some(usize::MAX));
+    assert_eq!(ef.predecessor(usize::MAX - 2), Some(usize::MAX / 2));
+}
+
+#[test]
+fn test_random() {
+    for (n, u) in [
+        (1000, 1010),
+        (1000, 1 << 16),
+        (10_000, 1 << 30),
+        (100_000, 1 << 20),
+    ] {
+        let values = gen_strictly_increasing_sequence(n, u);
+        check(&values);
+
+        // Duplicates are allowed
+        let values: Vec<usize> = values.iter().map(|&v| v / 7).collect();
+        check(&values);
+    }
+}
+
+#[test]
+fn test_space() {
+    // Sparse sequences take about 2 + log(u/n) bits per value
+    let values = gen_strictly_increasing_sequence(100_000, 1 << 30);
+    let ef = EliasFano::new(&values);
+    let da: DArray = values.iter().copied().collect();
+
+    let bits_per_value = ef.space_usage_byte() as f64 * 8.0 / values.len() as f64;
+    assert!(bits_per_value < 2.0 + 14.0 + 1.0);
+    assert!(ef.space_usage_byte() * 100 < da.space_usage_byte());
+}
+
+#[test]
+#[should_panic]
+fn test_unsorted() {
+    let _ = EliasFano::new(&[1, 3, 2]);
+}
+
+#[test]
+fn test_layout() {
+    let values = gen_strictly_increasing_sequence(10_000, 1 << 24);
+    let ef = EliasFano::new(&values);
+
+    let mut bytes = Vec::new();
+    ef.save_layout(&mut bytes).unwrap();
+    let buffer = AlignedBytes::from(&bytes[..]);
+    let view = EliasFano::view(&buffer).unwrap();
+    assert_eq!(*view, ef);
+    assert_eq!(view.successor(values[42] + 1), Some(values[43]));
+
+    let mut bytes = Vec::new();
+    ef.save_to(&mut bytes).unwrap();
+    let loaded = EliasFano::load_from(&bytes[..]).unwrap();
+    assert_eq!(loaded, ef);
+    assert!(loaded.iter().eq(values.iter().copied()));
+}
+
+#[test]
+fn test_layout_unsorted() {
+    // Swap the low bits of [2, 3], which share the same high bits
+    let mut ef = EliasFano::new(&[2, 3]);
+    assert_eq!(ef.low_len, 1);
+    ef.low_bits = [true, false].into_iter().collect();
+
+    let mut bytes = Vec::new();
+    ef.save_layout(&mut bytes).unwrap();
+    let buffer = AlignedBytes::from(&bytes[..]);
+    assert!(matches!(
+        EliasFano::view(&buffer),
+        Err(LayoutError::InvalidData(_))
+    ));
+    // The unchecked view trusts the values and only checks the lengths
+    assert!(unsafe { EliasFano::view_unchecked(&buffer) }.is_ok());
+}
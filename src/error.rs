@@ -0,0 +1,70 @@
+//! This module defines [`Error`], the error returned by the fallible constructors
+//! of the data structures of the crate, e.g., [`QWaveletTree::try_from`](crate::QWaveletTree)
+//! and [`RSWide::try_from`](crate::RSWide).
+//!
+//! ## Examples
+//!
+//! ```
+//! use qwt::{AccessUnsigned, Error, QWT256};
+//!
+//! let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+//!
+//! match QWT256::try_from(data) {
+//!     Ok(qwt) => assert_eq!(qwt.get(4), Some(2)),
+//!     Err(Error::TooLong { len, max_len }) => {
+//!         println!("Cannot index {len} symbols, split the sequence in chunks of {max_len} symbols")
+//!     }
+//!     Err(err) => panic!("{err}"),
+//! }
+//! ```
+
+use crate::persist::PersistError;
+
+use std::fmt;
+use std::io;
+
+/// The errors that may occur when building, saving, or loading a data structure.
+#[derive(Debug)]
+pub enum Error {
+    /// The input is longer than the largest length supported by the data structure.
+    TooLong { len: usize, max_len: usize },
+    /// The data structure cannot be saved to or loaded from a file.
+    Persist(PersistError),
+    /// An I/O operation failed, e.g., on the temporary files of a streaming construction.
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLong { len, max_len } => write!(
+                f,
+                "the input has length {len} but the largest supported length is {max_len}"
+            ),
+            Self::Persist(err) => write!(f, "{err}"),
+            Self::Io(err) => write!(f, "I/O error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Persist(err) => Some(err),
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<PersistError> for Error {
+    fn from(err: PersistError) -> Self {
+        Self::Persist(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
@@ -0,0 +1,334 @@
+//! This module implements an FM-index, a compressed full-text index of a text of bytes,
+//! on top of the Quad Wavelet Tree.
+//!
+//! The FM-index stores the Burrows-Wheeler Transform (BWT) of the text in a [`QWaveletTree`] and
+//! supports the following operations:
+//!
+//! - `count(p)`: Counts the number of occurrences of the pattern `p` in the text.
+//! - `locate(p)`: Returns the positions of the occurrences of the pattern `p` in the text.
+//! - `extract(l..r)`: Returns the substring `T[l..r)` of the text.
+//!
+//! The BWT is computed from the suffix array of the text, which is built in linear time with SA-IS. The text is conceptually terminated by
+//! a sentinel symbol smaller than any byte. Queries `count` are solved with the backward search, which
+//! performs two `rank` queries on the wavelet tree for each symbol of the pattern. The two queries
+//! share a single traversal of the wavelet tree with `backward_search_step`, which performs three
+//! `rank` operations per level instead of four.
+//! Queries `locate` and `extract` use a sample of the suffix array and of its inverse, taken every `sample_rate` positions of the text.
+//! Larger sample rates reduce the space usage and increase the query time.
+//!
+//! We have two aliases types: `FMIndex256` and `FMIndex512`, where 256 and 512 are the block sizes of the wavelet tree storing the BWT.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use qwt::FMIndex256;
+//!
+//! let text = b"mississippi";
+//! let fm_index = FMIndex256::new(text, 4);
+//!
+//! assert_eq!(fm_index.count(b"ssi"), 2);
+//! assert_eq!(fm_index.locate(b"ssi"), vec![2, 5]);
+//! assert_eq!(fm_index.extract(4..8), Some(b"issi".to_vec()));
+//! ```
+
+use crate::quadwt::RSforWT;
+use crate::SpaceUsage;
+use crate::{
+    AccessBin, AccessUnsigned, BitVectorMut, QWaveletTree, RSNarrow, RankBin, RankUnsigned,
+};
+
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+mod sais;
+use sais::suffix_array;
+
+/// The generic RS is the data structure used by the [`QWaveletTree`] that
+/// stores the BWT of the text.
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FMIndex<RS> {
+    n: usize,                  // The length of the text
+    bwt: QWaveletTree<u8, RS>, // The BWT, the sentinel is replaced by a 0 at position `primary`
+    primary: usize, // The row of the BWT of the suffix starting at position 0, i.e., the one preceded by the sentinel
+    counts: Vec<usize>, // counts[c] is one (for the sentinel) plus the number of symbols smaller than c in the text
+    sample_rate: usize,
+    sampled_rows: RSNarrow, // Marks the rows of the suffixes starting at a position multiple of sample_rate
+    sa_samples: Vec<usize>, // The starting positions of the suffixes of the marked rows
+    isa_samples: Vec<usize>, // isa_samples[i] is the row of the suffix starting at position i * sample_rate
+}
+
+impl<RS: RSforWT> FMIndex<RS> {
+    /// Builds the FM-index of `text`. The suffix array and its inverse are sampled
+    /// every `sample_rate` positions of the text.
+    ///
+    /// The suffix array is computed with SA-IS in linear time. The construction needs
+    /// $$8(n+1)$$ bytes for the suffix array, at most $$4n$$ more bytes while computing it,
+    /// and $$n$$ bytes for the BWT, where $$n$$ is the length of the text.
+    ///
+    /// ## Panics
+    /// Panics if `sample_rate` is zero.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::FMIndex256;
+    ///
+    /// let fm_index = FMIndex256::new(b"mississippi", 4);
+    ///
+    /// assert_eq!(fm_index.len(), 11);
+    /// ```
+    #[must_use]
+    pub fn new(text: &[u8], sample_rate: usize) -> Self {
+        assert!(sample_rate > 0, "The sample rate must be positive.");
+
+        let n = text.len();
+        let sa = suffix_array(text);
+
+        let mut counts = vec![0; 257];
+        for &c in text {
+            counts[c as usize + 1] += 1;
+        }
+        counts[0] = 1; // The sentinel
+        for c in 1..counts.len() {
+            counts[c] += counts[c - 1];
+        }
+        counts.pop();
+
+        let mut primary = 0;
+        let mut bwt = Vec::with_capacity(n + 1);
+        let mut sampled_rows = BitVectorMut::with_capacity(n + 1);
+        let mut sa_samples = Vec::with_capacity(n / sample_rate + 1);
+        let mut isa_samples = vec![0; n.div_ceil(sample_rate)];
+
+        for (row, &pos) in sa.iter().enumerate() {
+            if pos == 0 {
+                primary = row;
+                bwt.push(0);
+            } else {
+                bwt.push(text[pos - 1]);
+            }
+
+            let sampled = pos % sample_rate == 0;
+            sampled_rows.push(sampled);
+            if sampled {
+                sa_samples.push(pos);
+                if pos < n {
+                    isa_samples[pos / sample_rate] = row;
+                }
+            }
+        }
+        drop(sa);
+
+        Self {
+            n,
+            bwt: QWaveletTree::new(&mut bwt),
+            primary,
+            counts,
+            sample_rate,
+            sampled_rows: RSNarrow::new(sampled_rows.into()),
+            sa_samples,
+            isa_samples,
+        }
+    }
+
+    /// Returns the length of the indexed text.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::FMIndex256;
+    ///
+    /// let fm_index = FMIndex256::new(b"mississippi", 4);
+    ///
+    /// assert_eq!(fm_index.len(), 11);
+    /// ```
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Checks if the indexed text is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::FMIndex256;
+    ///
+    /// let fm_index = FMIndex256::new(b"", 4);
+    ///
+    /// assert!(fm_index.is_empty());
+    /// ```
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Returns the sample rate of the suffix array.
+    #[must_use]
+    pub fn sample_rate(&self) -> usize {
+        self.sample_rate
+    }
+
+    /// Returns a reference to the wavelet tree storing the BWT of the text.
+    ///
+    /// The BWT has length `n+1`. The sentinel at row `primary` is replaced by a `0`.
+    #[must_use]
+    pub fn bwt(&self) -> &QWaveletTree<u8, RS> {
+        &self.bwt
+    }
+
+    /// Returns the number of occurrences of `pattern` in the text.
+    ///
+    /// The empty pattern occurs at each of the `n+1` positions of the text, end included.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::FMIndex256;
+    ///
+    /// let fm_index = FMIndex256::new(b"mississippi", 4);
+    ///
+    /// assert_eq!(fm_index.count(b"issi"), 2);
+    /// assert_eq!(fm_index.count(b"i"), 4);
+    /// assert_eq!(fm_index.count(b"pippo"), 0);
+    /// ```
+    #[must_use]
+    pub fn count(&self, pattern: &[u8]) -> usize {
+        let range = self.backward_search(pattern);
+        range.end - range.start
+    }
+
+    /// Returns the positions of the occurrences of `pattern` in the text in increasing order.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::FMIndex256;
+    ///
+    /// let fm_index = FMIndex256::new(b"mississippi", 4);
+    ///
+    /// assert_eq!(fm_index.locate(b"issi"), vec![1, 4]);
+    /// assert_eq!(fm_index.locate(b"pippo"), vec![]);
+    /// ```
+    #[must_use]
+    pub fn locate(&self, pattern: &[u8]) -> Vec<usize> {
+        let mut positions: Vec<usize> = self
+            .backward_search(pattern)
+            .map(|row| self.locate_row(row))
+            .collect();
+        positions.sort_unstable();
+        positions
+    }
+
+    /// Returns the substring of the text in the range of positions `range`,
+    /// `None` if the range is out of bound.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::FMIndex256;
+    ///
+    /// let fm_index = FMIndex256::new(b"mississippi", 4);
+    ///
+    /// assert_eq!(fm_index.extract(0..4), Some(b"miss".to_vec()));
+    /// assert_eq!(fm_index.extract(7..11), Some(b"ippi".to_vec()));
+    /// assert_eq!(fm_index.extract(7..12), None);
+    /// ```
+    #[must_use]
+    pub fn extract(&self, range: Range<usize>) -> Option<Vec<u8>> {
+        if range.start > range.end || range.end > self.n {
+            return None;
+        }
+
+        // Start from the first sampled position at or after the end of the range.
+        // The suffix starting at position n is the first row.
+        let sample = range.end.div_ceil(self.sample_rate);
+        let (mut pos, mut row) = if sample < self.isa_samples.len() {
+            (sample * self.sample_rate, self.isa_samples[sample])
+        } else {
+            (self.n, 0)
+        };
+
+        let mut result = Vec::with_capacity(pos - range.start);
+        while pos > range.start {
+            // SAFETY: row is always smaller than n+1
+            let c = unsafe { self.bwt.get_unchecked(row) };
+            result.push(c);
+            row = self.lf(row, c);
+            pos -= 1;
+        }
+
+        result.reverse();
+        result.truncate(range.end - range.start);
+
+        Some(result)
+    }
+
+    /// Returns the range of rows of the suffixes prefixed by `pattern`.
+    fn backward_search(&self, pattern: &[u8]) -> Range<usize> {
+        let mut range = 0..self.n + 1;
+
+        for &c in pattern.iter().rev() {
+            if range.is_empty() {
+                break;
+            }
+
+            // Both ranks are computed with a single traversal of the wavelet tree
+            let Some(ranks) = self.bwt.backward_search_step(range.clone(), c) else {
+                return 0..0; // c does not occur in the text
+            };
+
+            // The placeholder of the sentinel is a 0 and must not be counted
+            let sentinel = |i: usize| (c == 0 && self.primary < i) as usize;
+            let offset = self.counts[c as usize];
+            range = (offset + ranks.start - sentinel(range.start))
+                ..(offset + ranks.end - sentinel(range.end));
+        }
+
+        range
+    }
+
+    /// Returns the number of occurrences of `c` in the BWT up to row `i` excluded.
+    #[inline(always)]
+    fn rank(&self, c: u8, i: usize) -> usize {
+        // The placeholder of the sentinel is a 0 and must not be counted
+        let sentinel = (c == 0 && self.primary < i) as usize;
+        self.bwt.rank(c, i).unwrap_or(0) - sentinel
+    }
+
+    /// The LF mapping: returns the row of the suffix preceded by `c` whose
+    /// rank among the ones preceded by `c` is the rank of `c` up to row `i`.
+    #[inline(always)]
+    fn lf(&self, i: usize, c: u8) -> usize {
+        self.counts[c as usize] + self.rank(c, i)
+    }
+
+    /// Returns the starting position of the suffix at row `row`.
+    fn locate_row(&self, mut row: usize) -> usize {
+        let mut steps = 0;
+
+        // The row of the suffix starting at position 0 is always sampled,
+        // so we never go past the sentinel.
+        // SAFETY: row is always smaller than n+1
+        while !unsafe { self.sampled_rows.get_unchecked(row) } {
+            // SAFETY: row is always smaller than n+1
+            let c = unsafe { self.bwt.get_unchecked(row) };
+            row = self.lf(row, c);
+            steps += 1;
+        }
+
+        // SAFETY: row is always smaller than n+1
+        let sample = unsafe { self.sampled_rows.rank1_unchecked(row) };
+        self.sa_samples[sample] + steps
+    }
+}
+
+impl<RS: SpaceUsage> SpaceUsage for FMIndex<RS> {
+    /// Gives the space usage in bytes of the struct.
+    fn space_usage_byte(&self) -> usize {
+        8 + 8
+            + 8
+            + self.bwt.space_usage_byte()
+            + self.counts.space_usage_byte()
+            + self.sampled_rows.space_usage_byte()
+            + self.sa_samples.space_usage_byte()
+            + self.isa_samples.space_usage_byte()
+    }
+}
+
+#[cfg(test)]
+mod tests;
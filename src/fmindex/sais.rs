@@ -0,0 +1,223 @@
+//! Computes the suffix array of a text with the SA-IS algorithm by Nong, Zhang, and Chan,
+//! which runs in linear time.
+//!
+//! The implementation follows the original one of the authors: the reduced problem of each
+//! level of the recursion is stored in the second half of the suffix array itself and its
+//! suffix array in the first half. Besides the suffix array of $$n+1$$ entries, each level
+//! needs one bit per symbol to store the types of the suffixes and one bucket per distinct symbol.
+//! Thus, the extra space is at most $$n/4$$ bytes for the types and $$4n$$ bytes for the buckets
+//! of the first recursive level, whose alphabet has at most $$n/2$$ symbols.
+
+use crate::{AccessBin, BitVectorMut};
+
+/// Marks an empty entry of the suffix array during the construction.
+const EMPTY: usize = usize::MAX;
+
+/// A text whose last symbol is a unique sentinel smaller than any other symbol.
+trait Text {
+    fn len(&self) -> usize;
+
+    /// Returns the `i`-th symbol, which is smaller than the alphabet size.
+    fn symbol(&self, i: usize) -> usize;
+}
+
+/// A text of bytes terminated by an implicit sentinel. Bytes are shifted by one to make
+/// room for the sentinel 0, so the alphabet size is 257.
+struct ByteText<'a>(&'a [u8]);
+
+impl Text for ByteText<'_> {
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.0.len() + 1
+    }
+
+    #[inline(always)]
+    fn symbol(&self, i: usize) -> usize {
+        self.0.get(i).map_or(0, |&c| c as usize + 1)
+    }
+}
+
+/// The reduced text of a recursive level, whose sentinel is already the name 0.
+impl Text for &mut [usize] {
+    #[inline(always)]
+    fn len(&self) -> usize {
+        <[usize]>::len(self)
+    }
+
+    #[inline(always)]
+    fn symbol(&self, i: usize) -> usize {
+        self[i]
+    }
+}
+
+/// Computes the suffix array of `text` terminated by a sentinel smaller than
+/// any other symbol. The result has `text.len() + 1` entries and the first one is `text.len()`.
+pub(crate) fn suffix_array(text: &[u8]) -> Vec<usize> {
+    let mut sa = vec![EMPTY; text.len() + 1];
+    sa_is(&ByteText(text), &mut sa, 257);
+    sa
+}
+
+/// Checks if the suffix at position `i` is a leftmost S-type suffix, i.e., a suffix
+/// smaller than the next one that follows a suffix larger than the next one.
+#[inline(always)]
+fn is_lms(types: &BitVectorMut, i: usize) -> bool {
+    i > 0 && i != EMPTY && types.get(i).unwrap() && !types.get(i - 1).unwrap()
+}
+
+/// Computes in `buckets` the start (or the end, if `end`) of the bucket of each symbol.
+fn get_buckets<S: Text>(text: &S, buckets: &mut [usize], end: bool) {
+    buckets.fill(0);
+    for i in 0..text.len() {
+        buckets[text.symbol(i)] += 1;
+    }
+    let mut sum = 0;
+    for bucket in buckets.iter_mut() {
+        sum += *bucket;
+        *bucket = if end { sum } else { sum - *bucket };
+    }
+}
+
+/// Induces the order of the L-type suffixes from the sorted suffixes in `sa`.
+fn induce_l<S: Text>(text: &S, types: &BitVectorMut, sa: &mut [usize], buckets: &mut [usize]) {
+    get_buckets(text, buckets, false);
+    for i in 0..sa.len() {
+        if sa[i] != EMPTY && sa[i] > 0 {
+            let j = sa[i] - 1;
+            if !types.get(j).unwrap() {
+                let c = text.symbol(j);
+                sa[buckets[c]] = j;
+                buckets[c] += 1;
+            }
+        }
+    }
+}
+
+/// Induces the order of the S-type suffixes from the sorted L-type suffixes in `sa`.
+fn induce_s<S: Text>(text: &S, types: &BitVectorMut, sa: &mut [usize], buckets: &mut [usize]) {
+    get_buckets(text, buckets, true);
+    for i in (0..sa.len()).rev() {
+        if sa[i] != EMPTY && sa[i] > 0 {
+            let j = sa[i] - 1;
+            if types.get(j).unwrap() {
+                let c = text.symbol(j);
+                buckets[c] -= 1;
+                sa[buckets[c]] = j;
+            }
+        }
+    }
+}
+
+/// Computes in `sa` the suffix array of `text`, whose symbols are smaller than `sigma`.
+fn sa_is<S: Text>(text: &S, sa: &mut [usize], sigma: usize) {
+    let n = text.len();
+    debug_assert_eq!(sa.len(), n);
+    if n == 1 {
+        sa[0] = 0;
+        return;
+    }
+
+    // The type of each suffix: true for S-type, i.e., smaller than the next suffix
+    let mut types = BitVectorMut::with_zeros(n);
+    types.set(n - 1, true);
+    for i in (0..n - 2).rev() {
+        let (c, next) = (text.symbol(i), text.symbol(i + 1));
+        if c < next || (c == next && types.get(i + 1).unwrap()) {
+            types.set(i, true);
+        }
+    }
+
+    // Stage 1: sort the LMS substrings by inducing from the LMS suffixes in any order
+    let mut buckets = vec![0; sigma];
+    get_buckets(text, &mut buckets, true);
+    sa.fill(EMPTY);
+    for i in 1..n {
+        if is_lms(&types, i) {
+            let c = text.symbol(i);
+            buckets[c] -= 1;
+            sa[buckets[c]] = i;
+        }
+    }
+    induce_l(text, &types, sa, &mut buckets);
+    induce_s(text, &types, sa, &mut buckets);
+    drop(buckets);
+
+    // Move the sorted LMS substrings to the beginning of `sa`
+    let mut n1 = 0;
+    for i in 0..n {
+        if is_lms(&types, sa[i]) {
+            sa[n1] = sa[i];
+            n1 += 1;
+        }
+    }
+
+    // Name the LMS substrings. No two LMS positions are adjacent, so the name of the
+    // substring at position `pos` is stored at position `n1 + pos / 2`.
+    sa[n1..].fill(EMPTY);
+    let mut n_names = 0;
+    let mut prev = EMPTY;
+    for i in 0..n1 {
+        let pos = sa[i];
+        let mut different = prev == EMPTY;
+        let mut d = 0;
+        while !different {
+            if text.symbol(pos + d) != text.symbol(prev + d)
+                || types.get(pos + d).unwrap() != types.get(prev + d).unwrap()
+            {
+                different = true;
+            } else if d > 0 && (is_lms(&types, pos + d) || is_lms(&types, prev + d)) {
+                break;
+            }
+            d += 1;
+        }
+        if different {
+            n_names += 1;
+            prev = pos;
+        }
+        sa[n1 + pos / 2] = n_names - 1;
+    }
+    let mut j = n;
+    for i in (n1..n).rev() {
+        if sa[i] != EMPTY {
+            j -= 1;
+            sa[j] = sa[i];
+        }
+    }
+
+    // Stage 2: sort the LMS suffixes by computing the suffix array of the reduced text
+    // stored at the end of `sa`
+    let (sa1, text1) = sa.split_at_mut(n - n1);
+    let sa1 = &mut sa1[..n1];
+    if n_names < n1 {
+        sa_is(&text1, sa1, n_names);
+    } else {
+        for i in 0..n1 {
+            sa1[text1[i]] = i;
+        }
+    }
+
+    // Stage 3: induce the suffix array from the sorted LMS suffixes
+    let mut j = 0;
+    for i in 1..n {
+        if is_lms(&types, i) {
+            text1[j] = i;
+            j += 1;
+        }
+    }
+    for i in 0..n1 {
+        sa1[i] = text1[sa1[i]];
+    }
+    sa[n1..].fill(EMPTY);
+
+    let mut buckets = vec![0; sigma];
+    get_buckets(text, &mut buckets, true);
+    for i in (0..n1).rev() {
+        let j = sa[i];
+        sa[i] = EMPTY;
+        let c = text.symbol(j);
+        buckets[c] -= 1;
+        sa[buckets[c]] = j;
+    }
+    induce_l(text, &types, sa, &mut buckets);
+    induce_s(text, &types, sa, &mut buckets);
+}
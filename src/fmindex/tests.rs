@@ -0,0 +1,104 @@
+use super::*;
+use crate::perf_and_test_utils::gen_sequence;
+use crate::{RSQVector256, RSQVector512};
+
+/// Returns the positions of the occurrences of `pattern` in `text` with a naive scan.
+fn naive_locate(text: &[u8], pattern: &[u8]) -> Vec<usize> {
+    (0..=text.len().saturating_sub(pattern.len()))
+        .filter(|&i| text[i..].starts_with(pattern))
+        .collect()
+}
+
+fn test_fm_index<RS: RSforWT>(text: &[u8], sample_rate: usize) {
+    let fm_index = FMIndex::<RS>::new(text, sample_rate);
+
+    assert_eq!(fm_index.len(), text.len());
+
+    for len in [1, 2, 3, 5] {
+        for start in (0..text.len().saturating_sub(len)).step_by(7) {
+            let pattern = &text[start..start + len];
+            let expected = naive_locate(text, pattern);
+            assert_eq!(fm_index.count(pattern), expected.len());
+            assert_eq!(fm_index.locate(pattern), expected);
+        }
+    }
+
+    for pattern in [
+        &b"\xff\xff\xff"[..],
+        &b"\x00\x00\x00\x00\x00\x00\x00\x00"[..],
+    ] {
+        let expected = naive_locate(text, pattern);
+        assert_eq!(fm_index.count(pattern), expected.len());
+        assert_eq!(fm_index.locate(pattern), expected);
+    }
+
+    for start in 0..=text.len().min(100) {
+        for end in start..=text.len().min(start + 20) {
+            assert_eq!(
+                fm_index.extract(start..end),
+                Some(text[start..end].to_vec())
+            );
+        }
+    }
+    assert_eq!(fm_index.extract(0..text.len()), Some(text.to_vec()));
+    assert_eq!(fm_index.extract(0..text.len() + 1), None);
+}
+
+#[test]
+fn test_suffix_array() {
+    assert_eq!(suffix_array(b""), vec![0]);
+    assert_eq!(
+        suffix_array(b"banana"),
+        vec![6, 5, 3, 1, 0, 4, 2] // $, a$, ana$, anana$, banana$, na$, nana$
+    );
+    assert_eq!(suffix_array(b"aaaa"), vec![4, 3, 2, 1, 0]);
+
+    // Compare with a naive sort of the suffixes, the sentinel sorts first as an empty suffix
+    for sigma in [1, 2, 3, 4, 26, 256] {
+        for n in [1, 2, 3, 10, 100, 1000] {
+            let text = gen_sequence(n, sigma);
+            let mut expected: Vec<usize> = (0..=n).collect();
+            expected.sort_by_key(|&i| &text[i..]);
+            assert_eq!(suffix_array(&text), expected);
+        }
+    }
+}
+
+#[test]
+fn test_small() {
+    let text = b"mississippi";
+    let fm_index = FMIndex::<RSQVector256>::new(text, 3);
+
+    assert_eq!(fm_index.count(b""), 12);
+    assert_eq!(fm_index.count(b"ss"), 2);
+    assert_eq!(fm_index.count(b"mississippi"), 1);
+    assert_eq!(fm_index.count(b"mississippii"), 0);
+    assert_eq!(fm_index.locate(b"i"), vec![1, 4, 7, 10]);
+    assert_eq!(fm_index.locate(b"ppi"), vec![8]);
+    assert_eq!(fm_index.extract(3..3), Some(vec![]));
+
+    for sample_rate in [1, 2, 3, 4, 11, 12, 100] {
+        test_fm_index::<RSQVector256>(text, sample_rate);
+    }
+}
+
+#[test]
+fn test_random() {
+    for sigma in [1, 2, 4, 26, 256] {
+        let text = gen_sequence(1000, sigma);
+        test_fm_index::<RSQVector256>(&text, 8);
+        test_fm_index::<RSQVector512>(&text, 32);
+    }
+}
+
+#[test]
+fn test_empty() {
+    let fm_index = FMIndex::<RSQVector256>::new(b"", 4);
+
+    assert!(fm_index.is_empty());
+    assert_eq!(fm_index.count(b"a"), 0);
+    assert_eq!(fm_index.count(b""), 1);
+    assert_eq!(fm_index.locate(b""), vec![0]);
+    assert_eq!(fm_index.extract(0..0), Some(vec![]));
+    assert_eq!(fm_index.extract(0..1), None);
+}
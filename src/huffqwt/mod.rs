@@ -0,0 +1,684 @@
+//! This module implements a Huffman-shaped Quad Wavelet Tree, providing implementations of [`AccessUnsigned`], [`RankUnsigned`], and [`SelectUnsigned`] for a vector of unsigned integers.
+//!
+//! The shape of the wavelet tree is given by a 4-ary Huffman code of the symbols of the sequence, so that
+//! frequent symbols have short codes. Each occurrence of a symbol is represented by the quaternary digits
+//! of its code, one per level. Thus, the space usage is close to $$n H_0$$ bits, where $$H_0$$ is the
+//! zero-order empirical entropy of the sequence, instead of the $$n \lceil\log \sigma\rceil$$ bits of
+//! the [`QWaveletTree`](crate::QWaveletTree). This is a big saving on skewed distributions, such as
+//! the symbols of a natural language text.
+//!
+//! Like the Quad Wavelet Tree, the Huffman-shaped one is a wavelet matrix. The codes are assigned so that,
+//! at every level, the occurrences of the symbols whose code ends at that level are at the end of the level.
+//! This way, these occurrences are simply dropped from the next levels and all the queries work as in
+//! the Quad Wavelet Tree.
+//!
+//! We have two aliases types: `HQWT256<T>` and `HQWT512<T>`. The generic type `T` is the type of the indexed unsigned integer values.
+//! The values 256 and 512 are the employed block sizes in the internal representation.
+//!
+//! ## Performance
+//!
+//! The time of a query for a symbol is proportional to the length of its code, which is
+//! $$O(\log n)$$ in the worst case. On average, queries on frequent symbols are faster than in the [`QWaveletTree`](crate::QWaveletTree).
+//!
+//! Only the codes of the symbols that occur in the sequence are stored, sorted by symbol, so the space usage
+//! does not depend on the largest symbol. The code of a symbol is found with a binary search, which takes
+//! $$O(\log d)$$ time, where $$d$$ is the number of distinct symbols.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use qwt::{HQWT256, AccessUnsigned, RankUnsigned, SelectUnsigned, SpaceUsage};
+//!
+//! let data: Vec<u8> = (0..1000).map(|i| if i % 10 == 0 { (i % 7) as u8 } else { 0 }).collect();
+//!
+//! let hqwt = HQWT256::try_from(data.clone()).unwrap();
+//!
+//! assert_eq!(hqwt.get(70), Some(0));
+//! assert_eq!(hqwt.get(30), Some(2));
+//! assert_eq!(hqwt.rank(2, 100), Some(1));
+//! assert_eq!(hqwt.select(2, 0), Some(30));
+//! ```
+
+use crate::quadwt::RSforWT;
+use crate::Error;
+use crate::{AccessUnsigned, RankUnsigned, SelectUnsigned, SpaceUsage, WTIndexable};
+use crate::{QVector, QVectorBuilder};
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::mem;
+
+// Traits bound
+use num_traits::AsPrimitive;
+
+/// The maximum length, in quaternary digits, of a code.
+/// No sequence of at most 2^{43} symbols gets a longer code.
+const MAX_CODE_LEN: usize = 64;
+
+/// The code of a symbol. The `i`th quaternary digit of the code is stored in
+/// bits `2i` and `2i+1` of `code`.
+#[derive(Default, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+struct HuffCode {
+    code: u128,
+    len: usize,
+}
+
+impl HuffCode {
+    #[inline(always)]
+    fn digit(&self, level: usize) -> u8 {
+        ((self.code >> (2 * level)) & 3) as u8
+    }
+}
+
+/// The generic RS is the data structure we use to index a quaternary
+/// sequence to support `access, `rank`, and `select` queries.
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct HuffQWaveletTree<T, RS> {
+    n: usize,               // The length of the represented sequence
+    n_levels: usize,        // The number of levels, i.e., the length of the longest code
+    sigma: T, // The largest symbol in the sequence. *NOTE*: It's not +1 because it may overflow
+    qvs: Vec<RS>, // A quad vector for each level
+    n_internal: Vec<usize>, // The number of internal nodes of each depth of the tree
+    leaves: Vec<Vec<T>>, // The symbols of the leaves of each depth, in the order of the wavelet matrix
+    symbols: Vec<T>,     // The distinct symbols of the sequence, sorted
+    codes: Vec<HuffCode>, // The code of each symbol in `symbols`
+}
+
+impl<T, RS> HuffQWaveletTree<T, RS>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+    RS: RSforWT,
+{
+    /// Builds the Huffman-shaped wavelet tree of the `sequence` of unsigned integers.
+    /// The input `sequence`` will be **destroyed**.
+    ///
+    /// ## Panics
+    /// Panics if the sequence is longer than the largest possible length.
+    /// The largest possible length is 2^{43}-1 symbols.
+    /// Use [`HuffQWaveletTree::try_new`] to get an error instead.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::HQWT256;
+    ///
+    /// let mut data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let hqwt = HQWT256::new(&mut data);
+    ///
+    /// assert_eq!(hqwt.len(), 8);
+    /// ```
+    #[must_use]
+    pub fn new(sequence: &mut [T]) -> Self {
+        match Self::try_new(sequence) {
+            Ok(hqwt) => hqwt,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Builds the Huffman-shaped wavelet tree of the `sequence` of unsigned integers
+    /// as [`HuffQWaveletTree::new`] does, but returns an error instead of panicking.
+    /// The input `sequence`` will be **destroyed**.
+    ///
+    /// # Errors
+    /// Returns [`Error::TooLong`] if the sequence is longer than the largest
+    /// possible length, i.e., 2^{43}-1 symbols. The sequence is left untouched in this case.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::HQWT256;
+    ///
+    /// let mut data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let hqwt = HQWT256::try_new(&mut data).unwrap();
+    ///
+    /// assert_eq!(hqwt.len(), 8);
+    /// ```
+    pub fn try_new(sequence: &mut [T]) -> Result<Self, Error> {
+        if sequence.len() > RS::MAX_LEN {
+            return Err(Error::TooLong {
+                len: sequence.len(),
+                max_len: RS::MAX_LEN,
+            });
+        }
+
+        if sequence.is_empty() {
+            return Ok(Self {
+                n: 0,
+                n_levels: 0,
+                sigma: T::zero(),
+                qvs: Vec::new(),
+                n_internal: Vec::new(),
+                leaves: Vec::new(),
+                symbols: Vec::new(),
+                codes: Vec::new(),
+            });
+        }
+
+        let sigma = *sequence.iter().max().unwrap();
+
+        // Symbols are hashed by their value as u128, which holds any unsigned integer
+        let mut freqs_map = HashMap::<u128, (T, usize)>::new();
+        for &symbol in sequence.iter() {
+            freqs_map
+                .entry(symbol.to_u128().unwrap())
+                .or_insert((symbol, 0))
+                .1 += 1;
+        }
+        let mut freqs: Vec<(T, usize)> = freqs_map.into_values().collect();
+        freqs.sort_unstable();
+
+        let (n_internal, leaves, codes) = Self::build_codes(&freqs);
+        let symbols: Vec<T> = freqs.into_iter().map(|(symbol, _)| symbol).collect();
+        let n_levels = n_internal.len() - 1;
+
+        let mut qvs = Vec::<RS>::with_capacity(n_levels);
+        let mut cur_len = sequence.len(); // Only the prefix of this length is indexed by the current level
+
+        for level in 0..n_levels {
+            let mut cur_qv = QVectorBuilder::with_capacity(cur_len);
+            let mut next = [Vec::new(), Vec::new(), Vec::new(), Vec::new()];
+
+            for &symbol in sequence[..cur_len].iter() {
+                let code = codes[symbols.binary_search(&symbol).unwrap()];
+                let two_bits = code.digit(level);
+                cur_qv.push(two_bits);
+
+                // The occurrences of the symbols whose code ends here are at the end
+                // of the next level, so we can drop them.
+                if code.len > level + 1 {
+                    next[two_bits as usize].push(symbol);
+                }
+            }
+
+            let qv: QVector = cur_qv.build();
+            qvs.push(RS::from(qv));
+
+            cur_len = 0;
+            for symbols in next {
+                sequence[cur_len..cur_len + symbols.len()].copy_from_slice(&symbols);
+                cur_len += symbols.len();
+            }
+        }
+
+        Ok(Self {
+            n: sequence.len(),
+            n_levels,
+            sigma,
+            qvs,
+            n_internal,
+            leaves,
+            symbols,
+            codes,
+        })
+    }
+
+    /// Computes the codes of the distinct symbols with the given frequencies,
+    /// given as pairs (symbol, frequency) sorted by symbol.
+    ///
+    /// Returns the number of internal nodes of each depth, the symbols of the leaves
+    /// of each depth in the order of the wavelet matrix, and the codes of the symbols
+    /// in the same order as `freqs`.
+    #[allow(clippy::type_complexity)]
+    fn build_codes(freqs: &[(T, usize)]) -> (Vec<usize>, Vec<Vec<T>>, Vec<HuffCode>) {
+        // Build the 4-ary Huffman tree. Nodes 0..freqs.len() are the leaves,
+        // then we have the dummy leaves needed to make the tree full, and the internal nodes.
+        // A node is always created after its children.
+        let n_leaves = match freqs.len() {
+            0 | 1 => freqs.len(),
+            m => m + (3 - (m - 1) % 3) % 3, // Each merge removes 3 nodes
+        };
+
+        let mut heap: BinaryHeap<Reverse<(usize, usize)>> = (0..n_leaves)
+            .map(|id| Reverse((freqs.get(id).map_or(0, |&(_, freq)| freq), id)))
+            .collect();
+        let mut parent = vec![usize::MAX; n_leaves];
+
+        while heap.len() > 1 {
+            let id = parent.len();
+            parent.push(usize::MAX);
+            let mut weight = 0;
+            for _ in 0..4 {
+                let Reverse((w, child)) = heap.pop().unwrap();
+                weight += w;
+                parent[child] = id;
+            }
+            heap.push(Reverse((weight, id)));
+        }
+
+        // Parents have larger ids than their children, the root is the last node
+        let mut depth = vec![0; parent.len()];
+        for id in (0..parent.len().saturating_sub(1)).rev() {
+            depth[id] = depth[parent[id]] + 1;
+        }
+
+        let n_levels = depth.iter().copied().max().unwrap();
+        assert!(n_levels <= MAX_CODE_LEN, "Codes are too long.");
+
+        let mut n_internal = vec![0; n_levels + 1];
+        for &d in depth.iter().skip(n_leaves) {
+            n_internal[d] += 1;
+        }
+
+        // The leaves of each depth are the ids of the symbols in `freqs`
+        let mut leaves = vec![Vec::new(); n_levels + 1];
+        for (id, &d) in depth.iter().enumerate().take(freqs.len()) {
+            leaves[d].push(id);
+        }
+
+        // Assign the codes depth by depth. The children of the internal nodes of
+        // depth l are ordered as in the wavelet matrix, i.e., by digit and then by parent.
+        // The first ones are the internal nodes of depth l+1 and the remaining ones are leaves.
+        // The code of the only symbol of a sequence with a single distinct symbol is empty.
+        let mut codes = vec![HuffCode::default(); freqs.len()];
+        let mut internal_codes = vec![0u128];
+
+        for level in 0..n_levels {
+            let m = n_internal[level];
+            let mut next_internal_codes = Vec::with_capacity(n_internal[level + 1]);
+            for j in 0..4 * m {
+                let code = internal_codes[j % m] | (((j / m) as u128) << (2 * level));
+                if j < n_internal[level + 1] {
+                    next_internal_codes.push(code);
+                } else if let Some(&id) = leaves[level + 1].get(j - n_internal[level + 1]) {
+                    codes[id] = HuffCode {
+                        code,
+                        len: level + 1,
+                    };
+                }
+            }
+            internal_codes = next_internal_codes;
+        }
+
+        let leaves = leaves
+            .into_iter()
+            .map(|ids| ids.into_iter().map(|id| freqs[id].0).collect())
+            .collect();
+
+        (n_internal, leaves, codes)
+    }
+
+    /// Returns the length of the indexed sequence.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::HQWT256;
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let hqwt = HQWT256::try_from(data).unwrap();
+    ///
+    /// assert_eq!(hqwt.len(), 8);
+    /// ```
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns the largest value in the sequence, or `None` if the sequence is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::HQWT256;
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let hqwt = HQWT256::try_from(data).unwrap();
+    ///
+    /// assert_eq!(hqwt.sigma(), Some(5));
+    /// ```
+    #[must_use]
+    pub fn sigma(&self) -> Option<T> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.sigma)
+        }
+    }
+
+    /// Checks if the indexed sequence is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::HQWT256;
+    ///
+    /// let hqwt = HQWT256::<u8>::default();
+    ///
+    /// assert_eq!(hqwt.is_empty(), true);
+    /// ```
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Returns the number of levels in the wavelet tree, i.e., the length of the
+    /// longest code.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::HQWT256;
+    ///
+    /// let data = vec![0u8, 0, 0, 0, 0, 0, 0, 1, 2, 3];
+    ///
+    /// let hqwt = HQWT256::try_from(data).unwrap();
+    ///
+    /// assert_eq!(hqwt.n_levels(), 1);
+    /// ```
+    #[must_use]
+    pub fn n_levels(&self) -> usize {
+        self.n_levels
+    }
+
+    /// Returns the code of `symbol`, or `None` if `symbol` does not occur.
+    #[inline(always)]
+    fn code(&self, symbol: T) -> Option<HuffCode> {
+        let id = self.symbols.binary_search(&symbol).ok()?;
+        // SAFETY: `codes` and `symbols` have the same length
+        Some(unsafe { *self.codes.get_unchecked(id) })
+    }
+}
+
+impl<T, RS> RankUnsigned for HuffQWaveletTree<T, RS>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+    RS: RSforWT,
+{
+    /// Returns the rank of `symbol` up to position `i` **excluded**.
+    ///
+    /// `None` is returned if `i` is out of bound or if `symbol` is not valid
+    /// (i.e., it is greater than or equal to the alphabet size).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{HQWT256, RankUnsigned};
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let hqwt = HQWT256::try_from(data).unwrap();
+    ///
+    /// assert_eq!(hqwt.rank(1, 2), Some(1));
+    /// assert_eq!(hqwt.rank(3, 8), Some(1));
+    /// assert_eq!(hqwt.rank(1, 0), Some(0));
+    /// assert_eq!(hqwt.rank(1, 9), None);  // Too large position
+    /// assert_eq!(hqwt.rank(6, 1), None);  // Too large symbol
+    /// ```
+    #[inline(always)]
+    fn rank(&self, symbol: Self::Item, i: usize) -> Option<usize> {
+        if i > self.n || symbol > self.sigma {
+            return None;
+        }
+
+        // SAFETY: Check the above guarantees we are not out of bound
+        Some(unsafe { self.rank_unchecked(symbol, i) })
+    }
+
+    /// Returns rank of `symbol` up to position `i` **excluded**.
+    ///
+    /// # Safety
+    /// Calling this method with a position `i` larger than the size of the sequence
+    /// of with invalid symbol is undefined behavior.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{HQWT256, RankUnsigned};
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let hqwt = HQWT256::try_from(data).unwrap();
+    ///
+    /// unsafe {
+    ///     assert_eq!(hqwt.rank_unchecked(1, 2), 1);
+    /// }
+    /// ```
+    #[inline(always)]
+    unsafe fn rank_unchecked(&self, symbol: Self::Item, i: usize) -> usize {
+        let Some(code) = self.code(symbol) else {
+            return 0;
+        };
+
+        let mut cur_i = i;
+        let mut cur_p = 0;
+
+        for level in 0..code.len {
+            let two_bits = code.digit(level);
+            let qv = self.qvs.get_unchecked(level);
+
+            // SAFETY: Here we are sure that two_bits is a symbol in [0..3]
+            let offset = qv.occs_smaller_unchecked(two_bits);
+            cur_p = qv.rank_unchecked(two_bits, cur_p) + offset;
+            cur_i = qv.rank_unchecked(two_bits, cur_i) + offset;
+        }
+
+        cur_i - cur_p
+    }
+}
+
+impl<T, RS> AccessUnsigned for HuffQWaveletTree<T, RS>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+    RS: RSforWT,
+{
+    type Item = T;
+
+    /// Returns the `i`-th symbol of the indexed sequence, `None` is returned if `i` is out of bound.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{HQWT256, AccessUnsigned};
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let hqwt = HQWT256::try_from(data).unwrap();
+    ///
+    /// assert_eq!(hqwt.get(2), Some(1));
+    /// assert_eq!(hqwt.get(3), Some(0));
+    /// assert_eq!(hqwt.get(8), None);
+    /// ```
+    #[inline(always)]
+    fn get(&self, i: usize) -> Option<Self::Item> {
+        if i >= self.n {
+            return None;
+        }
+        // SAFETY: Check before guarantees to not be out of bound
+        Some(unsafe { self.get_unchecked(i) })
+    }
+
+    /// Returns the `i`-th symbol of the indexed sequence.
+    ///
+    /// # Safety
+    /// Calling this method with a position `i` larger than the size of the sequence
+    /// is undefined behavior.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{HQWT256, AccessUnsigned};
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let hqwt = HQWT256::try_from(data).unwrap();
+    ///
+    /// unsafe {
+    ///     assert_eq!(hqwt.get_unchecked(2), 1);
+    /// }
+    /// ```
+    #[inline(always)]
+    unsafe fn get_unchecked(&self, i: usize) -> Self::Item {
+        let mut cur_i = i;
+        let mut node = 0; // The rank of the current node among the ones of its depth
+
+        for level in 0..self.n_levels {
+            let qv = self.qvs.get_unchecked(level);
+            qv.prefetch_info(cur_i);
+            let two_bits = qv.get_unchecked(cur_i);
+
+            let child = two_bits as usize * self.n_internal.get_unchecked(level) + node;
+            let n_internal = *self.n_internal.get_unchecked(level + 1);
+            if child >= n_internal {
+                return *self
+                    .leaves
+                    .get_unchecked(level + 1)
+                    .get_unchecked(child - n_internal);
+            }
+
+            // SAFETY: Here we are sure that two_bits is a symbol in [0..3]
+            let offset = qv.occs_smaller_unchecked(two_bits);
+            cur_i = qv.rank_unchecked(two_bits, cur_i) + offset;
+            node = child;
+        }
+
+        // The code of the only symbol of the sequence is empty
+        *self.leaves.get_unchecked(0).get_unchecked(0)
+    }
+}
+
+impl<T, RS> SelectUnsigned for HuffQWaveletTree<T, RS>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+    RS: RSforWT,
+{
+    /// Returns the position of the `i+1`-th occurrence of symbol `symbol`.
+    ///
+    /// `None` is returned if the is no (i+1)th such occurrence for the symbol
+    /// or if `symbol` is not valid (i.e., it is greater than or equal to the alphabet size).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{HQWT256, SelectUnsigned};
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let hqwt = HQWT256::try_from(data).unwrap();
+    ///
+    /// assert_eq!(hqwt.select(1, 1), Some(2));
+    /// assert_eq!(hqwt.select(0, 1), Some(3));
+    /// assert_eq!(hqwt.select(0, 2), None);
+    /// assert_eq!(hqwt.select(6, 0), None);
+    /// ```
+    #[inline(always)]
+    fn select(&self, symbol: Self::Item, i: usize) -> Option<usize> {
+        if symbol > self.sigma {
+            return None;
+        }
+
+        let code = self.code(symbol)?;
+
+        if code.len == 0 {
+            return if i < self.n { Some(i) } else { None };
+        }
+
+        let mut path_off = Vec::with_capacity(code.len);
+        let mut rank_path_off = Vec::with_capacity(code.len);
+
+        let mut b = 0;
+
+        for level in 0..code.len {
+            path_off.push(b);
+
+            let two_bits = code.digit(level);
+
+            let rank_b = self.qvs[level].rank(two_bits, b)?;
+
+            // Safety: we are sure the symbol `two_bits` is in [0..3]
+            b = rank_b + unsafe { self.qvs[level].occs_smaller_unchecked(two_bits) };
+
+            rank_path_off.push(rank_b);
+        }
+
+        let mut result = i;
+        for level in (0..code.len).rev() {
+            b = path_off[level];
+            let rank_b = rank_path_off[level];
+            let two_bits = code.digit(level);
+
+            result = self.qvs[level].select(two_bits, rank_b + result)? - b;
+        }
+
+        Some(result)
+    }
+
+    /// Returns the position of the `i+1`-th occurrence of symbol `symbol`.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method with a value of `i` larger than the number of occurrences
+    /// of the `symbol`, or if the `symbol` is not valid, is undefined behavior.
+    ///
+    /// In the current implementation, there is no efficiency reason to prefer this
+    /// unsafe `select` over the safe one.
+    #[inline(always)]
+    unsafe fn select_unchecked(&self, symbol: Self::Item, i: usize) -> usize {
+        self.select(symbol, i).unwrap()
+    }
+}
+
+impl<T, RS: SpaceUsage> SpaceUsage for HuffQWaveletTree<T, RS> {
+    /// Gives the space usage in bytes of the struct.
+    fn space_usage_byte(&self) -> usize {
+        let space_leaves: usize = self
+            .leaves
+            .iter()
+            .map(|leaves| mem::size_of::<Vec<T>>() + mem::size_of::<T>() * leaves.capacity())
+            .sum();
+        let space_codes = mem::size_of::<Vec<T>>()
+            + mem::size_of_val(self.symbols.as_slice())
+            + mem::size_of::<Vec<HuffCode>>()
+            + mem::size_of_val(self.codes.as_slice());
+
+        8 + 8
+            + self
+                .qvs
+                .iter()
+                .fold(0, |acc, ds| acc + ds.space_usage_byte())
+            + self.n_internal.space_usage_byte()
+            + space_leaves
+            + space_codes
+    }
+}
+
+impl<T, RS> AsRef<HuffQWaveletTree<T, RS>> for HuffQWaveletTree<T, RS> {
+    fn as_ref(&self) -> &HuffQWaveletTree<T, RS> {
+        self
+    }
+}
+
+impl<T, RS> FromIterator<T> for HuffQWaveletTree<T, RS>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+    RS: RSforWT,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        HuffQWaveletTree::new(&mut iter.into_iter().collect::<Vec<T>>())
+    }
+}
+
+impl<T, RS> TryFrom<Vec<T>> for HuffQWaveletTree<T, RS>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+    RS: RSforWT,
+{
+    type Error = Error;
+
+    fn try_from(mut v: Vec<T>) -> Result<Self, Error> {
+        HuffQWaveletTree::try_new(&mut v[..])
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,146 @@
+use super::*;
+use crate::perf_and_test_utils::{gen_sequence, SmallRSQVector};
+use crate::{RSQVector256, RSQVector512, QWT256};
+
+use rand::Rng;
+
+/// Generates a sequence of `n` symbols with a skewed distribution: symbol `c`
+/// has probability about 2^{-(c+1)}.
+fn gen_skewed_sequence(n: usize) -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    (0..n)
+        .map(|_| rng.gen::<u32>().trailing_zeros() as u8)
+        .collect()
+}
+
+fn test_hqwt<RS: RSforWT>(sequence: &[u8]) {
+    let sigma = *sequence.iter().max().unwrap() as usize + 1;
+    let hqwt = HuffQWaveletTree::<_, RS>::new(&mut sequence.to_vec());
+
+    assert_eq!(hqwt.len(), sequence.len());
+
+    let mut ranks = vec![0; sigma];
+    for (i, &symbol) in sequence.iter().enumerate() {
+        let rank = ranks[symbol as usize];
+        assert_eq!(hqwt.get(i), Some(symbol));
+        assert_eq!(hqwt.rank(symbol, i), Some(rank));
+        assert_eq!(hqwt.select(symbol, rank), Some(i));
+        ranks[symbol as usize] += 1;
+    }
+
+    for symbol in 0..sigma as u8 {
+        let rank = ranks[symbol as usize];
+        assert_eq!(hqwt.rank(symbol, sequence.len()), Some(rank));
+        assert_eq!(hqwt.select(symbol, rank), None);
+    }
+
+    assert_eq!(hqwt.get(sequence.len()), None);
+    assert_eq!(hqwt.rank(0, sequence.len() + 1), None);
+}
+
+#[test]
+fn test_small() {
+    let data: [u8; 9] = [1, 0, 1, 0, 3, 4, 5, 3, 7];
+    let hqwt = HuffQWaveletTree::<_, RSQVector512>::new(&mut data.clone());
+
+    assert_eq!(hqwt.rank(1, 4), Some(2));
+    assert_eq!(hqwt.rank(8, 1), None); // too large symbol
+    assert_eq!(hqwt.rank(6, 9), Some(0)); // absent symbol
+    assert_eq!(hqwt.rank(1, 10), None); // too large position
+
+    assert_eq!(hqwt.select(8, 1), None); // too large symbol
+    assert_eq!(hqwt.select(5, 0), Some(6));
+    assert_eq!(hqwt.select(6, 0), None); // absent symbol
+
+    test_hqwt::<RSQVector256>(&data);
+    test_hqwt::<RSQVector512>(&data);
+}
+
+#[test]
+fn test_random() {
+    let n = 10_000;
+    for sigma in [1, 2, 3, 4, 5, 8, 17, 128, 256] {
+        let sequence = gen_sequence(n, sigma);
+        test_hqwt::<RSQVector256>(&sequence);
+        test_hqwt::<RSQVector512>(&sequence);
+    }
+
+    let sequence = gen_skewed_sequence(n);
+    test_hqwt::<RSQVector256>(&sequence);
+    test_hqwt::<RSQVector512>(&sequence);
+}
+
+#[test]
+fn test_single_symbol() {
+    let sequence = vec![7u8; 1000];
+    let hqwt = HuffQWaveletTree::<_, RSQVector256>::new(&mut sequence.clone());
+
+    assert_eq!(hqwt.n_levels(), 0);
+    test_hqwt::<RSQVector256>(&sequence);
+    assert_eq!(hqwt.rank(3, 10), Some(0));
+}
+
+#[test]
+fn test_large_symbols() {
+    // The space usage depends on the distinct symbols, not on the largest one
+    let sequence: Vec<u64> = (0..1000).map(|i| (i % 5) << 60 | (i % 3)).collect();
+    let hqwt = HuffQWaveletTree::<_, RSQVector256>::new(&mut sequence.clone());
+
+    for (i, &symbol) in sequence.iter().enumerate() {
+        assert_eq!(hqwt.get(i), Some(symbol));
+    }
+    assert_eq!(hqwt.rank(4 << 60 | 1, 1000), Some(67));
+    assert_eq!(hqwt.rank(3 << 60 | 3, 1000), Some(0)); // absent symbol
+    assert_eq!(hqwt.select(4 << 60 | 1, 0), Some(4));
+    assert_eq!(hqwt.sigma(), Some(4 << 60 | 2));
+    assert!(hqwt.space_usage_byte() < 4096);
+}
+
+#[test]
+fn test_try_new() {
+    let sequence = gen_skewed_sequence(100);
+    test_hqwt::<SmallRSQVector>(&sequence);
+
+    let hqwt = HuffQWaveletTree::<_, SmallRSQVector>::try_from(sequence.clone()).unwrap();
+    assert_eq!(hqwt.len(), 100);
+
+    // A too long sequence is rejected before building any level and is left untouched
+    let sequence = gen_skewed_sequence(101);
+    let mut input = sequence.clone();
+    assert!(matches!(
+        HuffQWaveletTree::<_, SmallRSQVector>::try_new(&mut input),
+        Err(Error::TooLong {
+            len: 101,
+            max_len: 100
+        })
+    ));
+    assert_eq!(input, sequence);
+    let err = HuffQWaveletTree::<_, SmallRSQVector>::try_from(sequence).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "the input has length 101 but the largest supported length is 100"
+    );
+}
+
+#[test]
+fn test_empty() {
+    let hqwt = HuffQWaveletTree::<u8, RSQVector256>::new(&mut []);
+
+    assert!(hqwt.is_empty());
+    assert_eq!(hqwt.sigma(), None);
+    assert_eq!(hqwt.get(0), None);
+    assert_eq!(hqwt.rank(0, 0), Some(0));
+    assert_eq!(hqwt.select(0, 0), None);
+}
+
+#[test]
+fn test_space_usage() {
+    let n = 1 << 16;
+    let mut sequence = gen_skewed_sequence(n);
+    sequence[0] = 255; // Force 8 bits per symbol in the QWaveletTree
+
+    let hqwt = HuffQWaveletTree::<_, RSQVector256>::new(&mut sequence.clone());
+    let qwt = QWT256::new(&mut sequence);
+
+    assert!(2 * hqwt.space_usage_byte() < qwt.space_usage_byte());
+}
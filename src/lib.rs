@@ -4,12 +4,16 @@
     feature(stdarch_aarch64_prefetch)
 )]
 
+mod dynamic_tree;
 pub mod perf_and_test_utils;
 pub mod qvector;
 pub use qvector::QVector;
 pub use qvector::QVectorBuilder;
 
 pub mod bitvector;
+pub use bitvector::dynamic_bitvector::DynamicBitVector;
+pub use bitvector::rrr::RRRBitVector;
+pub use bitvector::rs_incremental::RSIncremental;
 pub use bitvector::rs_narrow::RSNarrow;
 pub use bitvector::rs_wide::RSWide;
 pub use bitvector::BitVector;
@@ -22,15 +26,39 @@ pub use qvector::rs_qvector::RSQVector256;
 pub use qvector::rs_qvector::RSQVector512;
 
 pub mod quadwt;
+pub use quadwt::AppendOnlyQWaveletTree;
+pub use quadwt::DynamicQWaveletTree;
 pub use quadwt::QWaveletTree;
+pub use quadwt::QWaveletTreeBuilder;
 pub use quadwt::WTIndexable;
 
+pub mod binwt;
+pub use binwt::BinaryWaveletMatrix;
+
+pub mod huffqwt;
+pub use huffqwt::HuffQWaveletTree;
+
+pub mod fmindex;
+pub use fmindex::FMIndex;
+
 pub mod space_usage;
 pub use space_usage::SpaceUsage;
 
+pub mod zero_copy;
+pub use zero_copy::ZeroCopy;
+
+pub mod persist;
+pub use persist::Persist;
+
+pub mod error;
+pub use error::Error;
+
 pub mod darray;
 pub use darray::DArray;
 
+pub mod elias_fano;
+pub use elias_fano::EliasFano;
+
 /// Type alias for a Quad Wavelet Tree with block size of 256
 pub type QWT256<T> = QWaveletTree<T, RSQVector256>;
 /// Type alias for a Quad Wavelet Tree with block size of 512
@@ -39,6 +67,24 @@ pub type QWT512<T> = QWaveletTree<T, RSQVector512>;
 pub type QWT256Pfs<T> = QWaveletTree<T, RSQVector256, true>;
 /// Type alias for a Quad Wavelet Tree with block size of 512 with prefetching support enabled
 pub type QWT512Pfs<T> = QWaveletTree<T, RSQVector512, true>;
+/// Type alias for an append-only Quad Wavelet Tree with block size of 256
+pub type AppendOnlyQWT256<T> = AppendOnlyQWaveletTree<T, 256>;
+/// Type alias for an append-only Quad Wavelet Tree with block size of 512
+pub type AppendOnlyQWT512<T> = AppendOnlyQWaveletTree<T, 512>;
+/// Type alias for a Huffman-shaped Quad Wavelet Tree with block size of 256
+pub type HQWT256<T> = HuffQWaveletTree<T, RSQVector256>;
+/// Type alias for a Huffman-shaped Quad Wavelet Tree with block size of 512
+pub type HQWT512<T> = HuffQWaveletTree<T, RSQVector512>;
+/// Type alias for an FM-index whose BWT is stored in a Quad Wavelet Tree with block size of 256
+pub type FMIndex256 = FMIndex<RSQVector256>;
+/// Type alias for an FM-index whose BWT is stored in a Quad Wavelet Tree with block size of 512
+pub type FMIndex512 = FMIndex<RSQVector512>;
+/// Type alias for a binary Wavelet Matrix with levels indexed by [`RSNarrow`]
+pub type BWMNarrow<T> = BinaryWaveletMatrix<T, RSNarrow>;
+/// Type alias for a binary Wavelet Matrix with levels indexed by [`RSWide`]
+pub type BWMWide<T> = BinaryWaveletMatrix<T, RSWide>;
+/// Type alias for a binary Wavelet Matrix with levels compressed by [`RRRBitVector`]
+pub type BWMRRR<T> = BinaryWaveletMatrix<T, RRRBitVector>;
 
 use num_traits::Unsigned;
 
@@ -206,6 +252,10 @@ pub trait SelectQuad {
 /// A trait for the operations that a quad vector implementation needs
 /// to provide to be used in a Quad Wavelet Tree.
 pub trait WTSupport: AccessQuad + RankQuad + SelectQuad {
+    /// The largest length of a quad vector that can be indexed.
+    /// The default is no limit.
+    const MAX_LEN: usize = usize::MAX;
+
     /// Returns the number of occurrences of `symbol` in the indexed sequence,
     /// `None` if `symbol` is larger than 3, i.e., `symbol` is not valid.  
     fn occs(&self, symbol: u8) -> Option<usize>;
@@ -242,4 +292,9 @@ pub trait WTSupport: AccessQuad + RankQuad + SelectQuad {
 
     /// Prefetches data containing the position `pos`.
     fn prefetch_data(&self, pos: usize);
+
+    /// Prefetches the counters accessed first by the query `select(symbol, i)`.
+    /// The default implementation prefetches nothing.
+    #[inline(always)]
+    fn prefetch_select(&self, _symbol: u8, _i: usize) {}
 }
@@ -4,11 +4,11 @@
 //! In particular, it provides functions to generate random increasing sequences and
 //! random queries, to measure rank and select queries, and so on.
 
-use crate::AccessUnsigned;
+use crate::{AccessUnsigned, Error, Persist};
 use num_traits::Unsigned;
 use rand::Rng;
-use serde::{Deserialize, Serialize};
 use std::fs;
+use std::io::{BufReader, BufWriter};
 use std::path::Path;
 use std::time::Instant;
 
@@ -177,57 +177,150 @@ pub fn negate_vector(v: &[usize]) -> Vec<usize> {
     vv
 }
 
+/// Loads the data structure from the file `output_filename` if it exists and
+/// contains a data structure of type `DS`. Otherwise, builds the data structure
+/// of `text` and saves it to the file.
+///
+/// # Errors
+/// Returns an error if the data structure cannot be built or saved.
 pub fn load_or_build_and_save_qwt<DS>(
     output_filename: &str,
     text: &[<DS as AccessUnsigned>::Item],
-) -> DS
+) -> Result<DS, Error>
 where
-    DS: Serialize
-        + for<'a> Deserialize<'a>
-        + From<Vec<<DS as AccessUnsigned>::Item>>
-        + AccessUnsigned,
+    DS: Persist + TryFrom<Vec<<DS as AccessUnsigned>::Item>, Error = Error> + AccessUnsigned,
     <DS as AccessUnsigned>::Item: Clone,
 {
-    let ds: DS;
     let path = Path::new(&output_filename);
     if path.exists() {
         println!(
             "The data structure already exists. Filename: {}. I'm going to load it ...",
             output_filename
         );
-        let serialized = fs::read(path).unwrap();
-        println!("Serialized size: {:?} bytes", serialized.len());
-        ds = bincode::deserialize::<DS>(&serialized).unwrap();
-    } else {
-        let mut t = TimingQueries::new(1, 1); // measure building time
-        t.start();
-        ds = DS::from(text.to_owned());
-        t.stop();
-        let (t_min, _, _) = t.get();
-        println!("Construction time {:?} millisecs", t_min / 1000000);
-
-        let serialized = bincode::serialize(&ds).unwrap();
-        println!("Serialized size: {:?} bytes", serialized.len());
-        fs::write(path, serialized).unwrap();
-    }
-
-    ds
+        println!("Serialized size: {:?} bytes", fs::metadata(path)?.len());
+        match DS::load_from(BufReader::new(fs::File::open(path)?)) {
+            Ok(ds) => return Ok(ds),
+            Err(err) => {
+                println!("Cannot load the data structure: {err}. I'm going to build it ...")
+            }
+        }
+    }
+
+    let ds = build_qwt::<DS>(text)?;
+
+    ds.save_to(BufWriter::new(fs::File::create(path)?))?;
+    println!("Serialized size: {:?} bytes", fs::metadata(path)?.len());
+
+    Ok(ds)
 }
 
-pub fn build_qwt<DS>(text: &[<DS as AccessUnsigned>::Item]) -> DS
+/// Builds the data structure of `text` and prints its construction time.
+///
+/// # Errors
+/// Returns an error if the data structure cannot be built, e.g., because `text` is too long.
+pub fn build_qwt<DS>(text: &[<DS as AccessUnsigned>::Item]) -> Result<DS, Error>
 where
-    DS: Serialize
-        + for<'a> Deserialize<'a>
-        + From<Vec<<DS as AccessUnsigned>::Item>>
-        + AccessUnsigned,
+    DS: TryFrom<Vec<<DS as AccessUnsigned>::Item>, Error = Error> + AccessUnsigned,
     <DS as AccessUnsigned>::Item: Clone,
 {
     let mut t = TimingQueries::new(1, 1); // measure building time
     t.start();
-    let ds = DS::from(text.to_owned());
+    let ds = DS::try_from(text.to_owned())?;
     t.stop();
     let (t_min, _, _) = t.get();
     println!("Construction time {:?} millisecs", t_min / 1000000);
 
-    ds
+    Ok(ds)
+}
+
+#[cfg(test)]
+pub(crate) use small_rs_qvector::SmallRSQVector;
+
+#[cfg(test)]
+mod small_rs_qvector {
+    use crate::{AccessQuad, QVector, RSQVector256, RankQuad, SelectQuad, SpaceUsage, WTSupport};
+
+    /// A rank and select support for quad vectors that indexes at most 100 symbols.
+    /// It is used to test the errors of too long sequences without allocating 2^{43} symbols.
+    #[derive(Default, Clone, PartialEq, Debug)]
+    pub(crate) struct SmallRSQVector(RSQVector256);
+
+    impl From<QVector> for SmallRSQVector {
+        fn from(qv: QVector) -> Self {
+            assert!(qv.len() <= Self::MAX_LEN, "The quad vector is too long.");
+            Self(qv.into())
+        }
+    }
+
+    impl AccessQuad for SmallRSQVector {
+        fn get(&self, i: usize) -> Option<u8> {
+            self.0.get(i)
+        }
+
+        unsafe fn get_unchecked(&self, i: usize) -> u8 {
+            self.0.get_unchecked(i)
+        }
+    }
+
+    impl RankQuad for SmallRSQVector {
+        fn rank(&self, symbol: u8, i: usize) -> Option<usize> {
+            self.0.rank(symbol, i)
+        }
+
+        unsafe fn rank_unchecked(&self, symbol: u8, i: usize) -> usize {
+            self.0.rank_unchecked(symbol, i)
+        }
+    }
+
+    impl SelectQuad for SmallRSQVector {
+        fn select(&self, symbol: u8, i: usize) -> Option<usize> {
+            self.0.select(symbol, i)
+        }
+
+        unsafe fn select_unchecked(&self, symbol: u8, i: usize) -> usize {
+            self.0.select_unchecked(symbol, i)
+        }
+    }
+
+    impl WTSupport for SmallRSQVector {
+        const MAX_LEN: usize = 100;
+
+        fn occs(&self, symbol: u8) -> Option<usize> {
+            self.0.occs(symbol)
+        }
+
+        unsafe fn occs_unchecked(&self, symbol: u8) -> usize {
+            self.0.occs_unchecked(symbol)
+        }
+
+        fn occs_smaller(&self, symbol: u8) -> Option<usize> {
+            self.0.occs_smaller(symbol)
+        }
+
+        unsafe fn rank_block_unchecked(&self, symbol: u8, i: usize) -> usize {
+            self.0.rank_block_unchecked(symbol, i)
+        }
+
+        unsafe fn occs_smaller_unchecked(&self, symbol: u8) -> usize {
+            self.0.occs_smaller_unchecked(symbol)
+        }
+
+        fn prefetch_info(&self, pos: usize) {
+            self.0.prefetch_info(pos)
+        }
+
+        fn prefetch_data(&self, pos: usize) {
+            self.0.prefetch_data(pos)
+        }
+
+        fn prefetch_select(&self, symbol: u8, i: usize) {
+            self.0.prefetch_select(symbol, i)
+        }
+    }
+
+    impl SpaceUsage for SmallRSQVector {
+        fn space_usage_byte(&self) -> usize {
+            self.0.space_usage_byte()
+        }
+    }
 }
@@ -0,0 +1,351 @@
+//! This module defines a versioned and checksummed file format for the data
+//! structures of the crate, so that a file written by a version of the crate
+//! is either loaded correctly or rejected with a [`PersistError`] by another one.
+//!
+//! A data structure implementing [`Persist`] is written with [`Persist::save_to`]
+//! and read back with [`Persist::load_from`].
+//!
+//! ## Format
+//!
+//! A file consists of the following little-endian 64-bit words and bytes:
+//!
+//! - the magic number "QWTSTORE";
+//! - the version of the format, i.e., [`FORMAT_VERSION`];
+//! - the length of the type tag, followed by the tag. The tag is the name of the
+//!   data structure with all the parameters that affect its layout, e.g.,
+//!   `QWaveletTree<u8, RSQVector<256>, false>` for a [`QWT256<u8>`](crate::QWT256);
+//! - the length of the data structure, i.e., its number of symbols or bits;
+//! - the length in bytes of the payload, followed by the payload, which is the
+//!   [`ZeroCopy`] layout of the data structure;
+//! - a 64-bit checksum of all the previous bytes.
+//!
+//! The payload is loaded into a single buffer aligned to 64 bytes, which is shared
+//! by all the arrays of the data structure, so loading does not copy them again.
+//!
+//! ## Examples
+//!
+//! ```
+//! use qwt::{Persist, QWT256, QWT512, RankUnsigned};
+//! use qwt::persist::PersistError;
+//!
+//! let qwt = QWT256::try_from(vec![1u8, 0, 1, 0, 2, 4, 5, 3]).unwrap();
+//!
+//! let mut bytes = Vec::new();
+//! qwt.save_to(&mut bytes).unwrap();
+//!
+//! let loaded = QWT256::<u8>::load_from(&bytes[..]).unwrap();
+//! assert_eq!(loaded.rank(1, 4), Some(2));
+//! assert_eq!(loaded, qwt);
+//!
+//! // The type of the data structure must match the one in the file
+//! assert!(matches!(
+//!     QWT512::<u8>::load_from(&bytes[..]),
+//!     Err(PersistError::TypeMismatch { .. })
+//! ));
+//! ```
+
+use crate::zero_copy::{self, AlignedBytes, LayoutError};
+use crate::ZeroCopy;
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+/// The magic number at the beginning of a file, i.e., "QWTSTORE" in little-endian.
+const MAGIC: u64 = u64::from_le_bytes(*b"QWTSTORE");
+
+/// The version of the file format. It changes whenever the header changes.
+/// Changes of the payload are tracked by [`LAYOUT_VERSION`](crate::zero_copy::LAYOUT_VERSION).
+pub const FORMAT_VERSION: u64 = 1;
+
+/// The longest type tag that is read from a file.
+const MAX_TAG_LEN: usize = 1024;
+
+/// The errors that may occur when loading a data structure.
+#[derive(Debug)]
+pub enum PersistError {
+    /// The reader or the writer failed, e.g., because the file is truncated.
+    Io(io::Error),
+    /// The file does not start with the magic number of the format.
+    InvalidMagic,
+    /// The file has been written with a different version of the format.
+    UnsupportedVersion(u64),
+    /// The file contains a data structure of a different type.
+    TypeMismatch { expected: String, found: String },
+    /// The length of the loaded data structure differs from the one in the header.
+    LengthMismatch { expected: usize, found: usize },
+    /// The checksum of the file does not match its content.
+    ChecksumMismatch { expected: u64, found: u64 },
+    /// The payload is not a valid layout of the data structure.
+    Layout(LayoutError),
+}
+
+impl fmt::Display for PersistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "I/O error: {err}"),
+            Self::InvalidMagic => write!(f, "the file does not contain a qwt data structure"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "the file has version {version} but version {FORMAT_VERSION} is supported"
+            ),
+            Self::TypeMismatch { expected, found } => {
+                write!(f, "the file contains a {found} instead of a {expected}")
+            }
+            Self::LengthMismatch { expected, found } => write!(
+                f,
+                "the header declares length {expected} but the data structure has length {found}"
+            ),
+            Self::ChecksumMismatch { expected, found } => write!(
+                f,
+                "the checksum of the file is {found:#018x} instead of {expected:#018x}"
+            ),
+            Self::Layout(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for PersistError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Layout(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PersistError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<LayoutError> for PersistError {
+    fn from(err: LayoutError) -> Self {
+        Self::Layout(err)
+    }
+}
+
+/// A trait for the data structures that can be saved to and loaded from a file.
+pub trait Persist: ZeroCopy {
+    /// Returns the tag that identifies the type of the data structure in a file.
+    fn type_tag() -> String;
+
+    /// Returns the length of the data structure stored in the header.
+    fn persisted_len(&self) -> usize;
+
+    /// Writes the data structure to the `writer`, header and checksum included.
+    ///
+    /// # Errors
+    /// Returns an error if the writer fails.
+    fn save_to<W: Write>(&self, writer: W) -> Result<(), PersistError> {
+        let mut writer = ChecksumWriter::new(writer);
+        let tag = Self::type_tag();
+        writer.write_u64(MAGIC)?;
+        writer.write_u64(FORMAT_VERSION)?;
+        writer.write_u64(tag.len() as u64)?;
+        writer.write_all(tag.as_bytes())?;
+        writer.write_u64(self.persisted_len() as u64)?;
+        writer.write_u64(self.layout_len() as u64)?;
+        self.save_layout(&mut writer)?;
+
+        let checksum = writer.checksum.finish();
+        writer.inner.write_all(&checksum.to_le_bytes())?;
+        writer.inner.flush()?;
+        Ok(())
+    }
+
+    /// Reads a data structure written by [`Persist::save_to`] from the `reader`.
+    ///
+    /// The payload is checked as [`ZeroCopy::view`] does, so a file crafted with a
+    /// valid checksum but an invalid data structure is rejected as well.
+    ///
+    /// # Errors
+    /// Returns an error if the reader fails, if the file contains a data structure of
+    /// a different type or written with a different version of the format, or if
+    /// the file is corrupted.
+    fn load_from<R: Read>(reader: R) -> Result<Self, PersistError> {
+        let mut reader = ChecksumReader::new(reader);
+        if reader.read_u64()? != MAGIC {
+            return Err(PersistError::InvalidMagic);
+        }
+        let version = reader.read_u64()?;
+        if version != FORMAT_VERSION {
+            return Err(PersistError::UnsupportedVersion(version));
+        }
+
+        let expected_tag = Self::type_tag();
+        let tag_len = reader.read_len()?;
+        if tag_len > MAX_TAG_LEN {
+            return Err(PersistError::TypeMismatch {
+                expected: expected_tag,
+                found: format!("type tag of {tag_len} bytes"),
+            });
+        }
+        let mut tag = vec![0; tag_len];
+        reader.read_exact(&mut tag)?;
+        if tag != expected_tag.as_bytes() {
+            return Err(PersistError::TypeMismatch {
+                expected: expected_tag,
+                found: String::from_utf8_lossy(&tag).into_owned(),
+            });
+        }
+
+        let len = reader.read_len()?;
+        let payload_len = reader.read_len()?;
+        let buffer = AlignedBytes::read_from(&mut reader, payload_len)?;
+
+        let found = reader.checksum.finish();
+        let mut expected = [0; 8];
+        reader.inner.read_exact(&mut expected)?;
+        let expected = u64::from_le_bytes(expected);
+        if found != expected {
+            return Err(PersistError::ChecksumMismatch { expected, found });
+        }
+
+        // The checksum detects accidental corruptions only, so the payload is
+        // checked as thoroughly as the buffer of a view
+        let ds: Self = zero_copy::load_layout(Arc::new(buffer))?;
+        if ds.persisted_len() != len {
+            return Err(PersistError::LengthMismatch {
+                expected: len,
+                found: ds.persisted_len(),
+            });
+        }
+        Ok(ds)
+    }
+}
+
+/// A 64-bit checksum of a stream of bytes, which are processed as little-endian
+/// 64-bit words. Each word is mixed into the state with a bijective step, so the
+/// corruption of any single word is always detected.
+struct Checksum {
+    state: u64,
+    tail: [u8; 8],
+    tail_len: usize,
+    len: u64,
+}
+
+impl Checksum {
+    const MULTIPLIER: u64 = 0x9E37_79B9_7F4A_7C15;
+
+    fn new() -> Self {
+        Self {
+            state: 0xCBF2_9CE4_8422_2325,
+            tail: [0; 8],
+            tail_len: 0,
+            len: 0,
+        }
+    }
+
+    #[inline(always)]
+    fn mix(&mut self, word: u64) {
+        self.state = (self.state ^ word)
+            .wrapping_mul(Self::MULTIPLIER)
+            .rotate_left(29);
+    }
+
+    fn update(&mut self, mut bytes: &[u8]) {
+        self.len += bytes.len() as u64;
+
+        if self.tail_len > 0 {
+            let n = bytes.len().min(8 - self.tail_len);
+            self.tail[self.tail_len..self.tail_len + n].copy_from_slice(&bytes[..n]);
+            self.tail_len += n;
+            bytes = &bytes[n..];
+            if self.tail_len < 8 {
+                return;
+            }
+            self.mix(u64::from_le_bytes(self.tail));
+            self.tail_len = 0;
+        }
+
+        let mut words = bytes.chunks_exact(8);
+        for word in &mut words {
+            self.mix(u64::from_le_bytes(word.try_into().unwrap()));
+        }
+        let rest = words.remainder();
+        self.tail[..rest.len()].copy_from_slice(rest);
+        self.tail_len = rest.len();
+    }
+
+    fn finish(&self) -> u64 {
+        let mut checksum = Self {
+            tail: [0; 8],
+            ..*self
+        };
+        checksum.tail[..self.tail_len].copy_from_slice(&self.tail[..self.tail_len]);
+        checksum.mix(u64::from_le_bytes(checksum.tail));
+        checksum.mix(self.len);
+        checksum.state
+    }
+}
+
+/// A writer that computes the checksum of the bytes written through it.
+struct ChecksumWriter<W> {
+    inner: W,
+    checksum: Checksum,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            checksum: Checksum::new(),
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) -> io::Result<()> {
+        self.write_all(&value.to_le_bytes())
+    }
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.checksum.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A reader that computes the checksum of the bytes read through it.
+struct ChecksumReader<R> {
+    inner: R,
+    checksum: Checksum,
+}
+
+impl<R: Read> ChecksumReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            checksum: Checksum::new(),
+        }
+    }
+
+    fn read_u64(&mut self) -> io::Result<u64> {
+        let mut bytes = [0; 8];
+        self.read_exact(&mut bytes)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn read_len(&mut self) -> Result<usize, PersistError> {
+        usize::try_from(self.read_u64()?)
+            .map_err(|_| PersistError::Layout(LayoutError::InvalidData("too large length")))
+    }
+}
+
+impl<R: Read> Read for ChecksumReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.checksum.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,191 @@
+use super::*;
+use crate::perf_and_test_utils::gen_sequence;
+use crate::{
+    AccessUnsigned, BitVector, DArray, QWT256Pfs, RSNarrow, RSQVector256, RSWide, RankBin,
+    RankQuad, RankUnsigned, SelectBin, SelectUnsigned, QWT256, QWT512,
+};
+
+fn save<S: Persist>(ds: &S) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ds.save_to(&mut bytes).unwrap();
+    bytes
+}
+
+// Recomputes the checksum of a file after changing its content.
+fn fix_checksum(bytes: &mut [u8]) {
+    let len = bytes.len() - 8;
+    let mut checksum = Checksum::new();
+    checksum.update(&bytes[..len]);
+    bytes[len..].copy_from_slice(&checksum.finish().to_le_bytes());
+}
+
+#[test]
+fn test_save_load_qwt() {
+    for sigma in [1, 2, 3, 4, 17, 256] {
+        let sequence = gen_sequence(10_000, sigma);
+
+        let qwt = QWT256::try_from(sequence.clone()).unwrap();
+        let loaded = QWT256::<u8>::load_from(&save(&qwt)[..]).unwrap();
+        assert_eq!(loaded, qwt);
+        for (i, &symbol) in sequence.iter().enumerate().step_by(7) {
+            assert_eq!(loaded.get(i), Some(symbol));
+            assert_eq!(loaded.rank(symbol, i), qwt.rank(symbol, i));
+            assert_eq!(loaded.select(symbol, 3), qwt.select(symbol, 3));
+        }
+
+        let sequence: Vec<u16> = sequence.iter().map(|&c| c as u16 * 100).collect();
+        let qwt = QWT256Pfs::try_from(sequence).unwrap();
+        let loaded = QWT256Pfs::<u16>::load_from(&save(&qwt)[..]).unwrap();
+        assert_eq!(loaded, qwt);
+    }
+
+    let qwt = QWT512::<u32>::try_from(Vec::new()).unwrap();
+    assert_eq!(QWT512::<u32>::load_from(&save(&qwt)[..]).unwrap(), qwt);
+
+    // The loaded data structure outlives the bytes it has been read from
+    let bytes = save(&RSQVector256::new(&[0u8, 1, 2, 3, 3, 2]));
+    let rsqv = RSQVector256::load_from(&bytes[..]).unwrap();
+    drop(bytes);
+    assert_eq!(rsqv.rank(3, 6), Some(2));
+}
+
+#[test]
+fn test_save_load_bit_vectors() {
+    let bv: BitVector = gen_sequence(100_000, 2)
+        .into_iter()
+        .map(|c| c == 1)
+        .collect();
+
+    let rs = RSNarrow::new(bv.clone());
+    let loaded = RSNarrow::load_from(&save(&rs)[..]).unwrap();
+    assert_eq!(loaded, rs);
+    assert_eq!(loaded.rank1(50_000), rs.rank1(50_000));
+
+    let rs = RSWide::new(bv.clone());
+    let loaded = RSWide::load_from(&save(&rs)[..]).unwrap();
+    assert_eq!(loaded, rs);
+    assert_eq!(loaded.select0(1000), rs.select0(1000));
+
+    let da = DArray::<true>::new(bv.clone());
+    let loaded = DArray::<true>::load_from(&save(&da)[..]).unwrap();
+    assert_eq!(loaded, da);
+    assert_eq!(loaded.select0(1000), da.select0(1000));
+
+    let da = DArray::<false, true>::new(bv.clone());
+    let loaded = DArray::<false, true>::load_from(&save(&da)[..]).unwrap();
+    assert_eq!(loaded, da);
+    assert_eq!(loaded.rank1(50_000), da.rank1(50_000));
+
+    let da = DArray::<false>::new(bv);
+    let bytes = save(&da);
+    assert_eq!(DArray::<false>::load_from(&bytes[..]).unwrap(), da);
+    assert!(matches!(
+        DArray::<false, true>::load_from(&bytes[..]),
+        Err(PersistError::TypeMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_load_errors() {
+    let qwt = QWT256::try_from(gen_sequence(1000, 16)).unwrap();
+    let bytes = save(&qwt);
+
+    // Wrong types
+    for result in [
+        QWT512::<u8>::load_from(&bytes[..]).map(|_| ()),
+        QWT256::<u16>::load_from(&bytes[..]).map(|_| ()),
+        QWT256Pfs::<u8>::load_from(&bytes[..]).map(|_| ()),
+        RSNarrow::load_from(&bytes[..]).map(|_| ()),
+    ] {
+        assert!(matches!(result, Err(PersistError::TypeMismatch { .. })));
+    }
+    match QWT512::<u8>::load_from(&bytes[..]) {
+        Err(PersistError::TypeMismatch { expected, found }) => {
+            assert_eq!(expected, "QWaveletTree<u8, RSQVector<512>, false>");
+            assert_eq!(found, "QWaveletTree<u8, RSQVector<256>, false>");
+        }
+        _ => panic!("expected a type mismatch"),
+    }
+
+    // Wrong magic number and version
+    let mut corrupted = bytes.clone();
+    corrupted[0] ^= 1;
+    assert!(matches!(
+        QWT256::<u8>::load_from(&corrupted[..]),
+        Err(PersistError::InvalidMagic)
+    ));
+    let mut corrupted = bytes.clone();
+    corrupted[8] = 42;
+    assert!(matches!(
+        QWT256::<u8>::load_from(&corrupted[..]),
+        Err(PersistError::UnsupportedVersion(42))
+    ));
+
+    // Any corrupted byte of the payload or of the checksum is detected
+    for i in (100..bytes.len()).step_by(97) {
+        let mut corrupted = bytes.clone();
+        corrupted[i] ^= 0x10;
+        assert!(matches!(
+            QWT256::<u8>::load_from(&corrupted[..]),
+            Err(PersistError::ChecksumMismatch { .. })
+        ));
+    }
+
+    // Truncated file
+    assert!(matches!(
+        QWT256::<u8>::load_from(&bytes[..bytes.len() - 1]),
+        Err(PersistError::Io(_))
+    ));
+    assert!(matches!(
+        QWT256::<u8>::load_from(&bytes[..bytes.len() / 2]),
+        Err(PersistError::Io(_))
+    ));
+
+    // Wrong length in the header
+    let tag_len = QWT256::<u8>::type_tag().len();
+    let len_offset = 24 + tag_len;
+    let mut corrupted = bytes.clone();
+    corrupted[len_offset..len_offset + 8].copy_from_slice(&999u64.to_le_bytes());
+    fix_checksum(&mut corrupted);
+    assert!(matches!(
+        QWT256::<u8>::load_from(&corrupted[..]),
+        Err(PersistError::LengthMismatch {
+            expected: 999,
+            found: 1000
+        })
+    ));
+
+    // A payload with a valid checksum is checked anyway, here its length `n`
+    // after the magic number, the version, the type of the symbols and the prefetch flag
+    let n_offset = len_offset + 16 + 32;
+    let mut corrupted = bytes;
+    corrupted[n_offset..n_offset + 8].copy_from_slice(&(1u64 << 40).to_le_bytes());
+    fix_checksum(&mut corrupted);
+    assert!(matches!(
+        QWT256::<u8>::load_from(&corrupted[..]),
+        Err(PersistError::Layout(LayoutError::InvalidData(_)))
+    ));
+}
+
+#[test]
+fn test_checksum() {
+    let bytes: Vec<u8> = (0..1000).map(|i| (i * 7 % 251) as u8).collect();
+    let mut checksum = Checksum::new();
+    checksum.update(&bytes);
+    let expected = checksum.finish();
+
+    // The checksum does not depend on how the bytes are split
+    for split in [1, 3, 8, 13, 64] {
+        let mut checksum = Checksum::new();
+        for chunk in bytes.chunks(split) {
+            checksum.update(chunk);
+        }
+        assert_eq!(checksum.finish(), expected);
+    }
+
+    // Trailing zeros change the checksum
+    let mut checksum = Checksum::new();
+    checksum.update(&bytes);
+    checksum.update(&[0]);
+    assert_ne!(checksum.finish(), expected);
+}
@@ -0,0 +1,444 @@
+//! An append-only Quad Wavelet Tree, i.e., a wavelet tree that supports appending
+//! symbols at the end of the indexed sequence while staying queryable at all times.
+//!
+//! The levels of a wavelet matrix cannot be simply extended: each level is a stable
+//! partition of the previous one, so an appended symbol ends up in the middle of all
+//! the levels but the first one. Instead, [`AppendOnlyQWaveletTree`] is a quad wavelet
+//! tree with a node for each prefix of two bits per level of the symbols. A node stores
+//! the next two bits of the symbols with its prefix, in the order of the sequence, so an
+//! appended symbol is appended to the node of each level on its root-to-leaf path.
+//!
+//! Each node is an [`AppendOnlyRSQVector`], i.e., a growable quad vector whose full
+//! superblocks are sealed into the same counters of [`RSQVector`](crate::RSQVector).
+//! Thus, a `push` takes $$O(\log \sigma)$$ time and the queries visit a node per level
+//! as the ones of [`QWaveletTree`](crate::QWaveletTree).
+//! As each node has its own quad vector, the space usage is larger than the one of
+//! a [`QWaveletTree`](crate::QWaveletTree) when the alphabet is large.
+//!
+//! The number of levels grows as needed when a symbol larger than all the previous ones
+//! is appended. The new levels are prepended with a single node filled with zeros, as the
+//! higher bits of the previous symbols are zeros. This takes linear time but happens at most
+//! $$\log \sigma / 2$$ times, so a `push` takes amortized $$O(\log \sigma)$$ time.
+
+use crate::quadwt::WTIndexable;
+use crate::qvector::append_only_qvector::AppendOnlyRSQVector;
+use crate::utils::msb;
+use crate::{AccessQuad, RankQuad, SelectQuad};
+use crate::{AccessUnsigned, RankUnsigned, SelectUnsigned, SpaceUsage};
+
+use num_traits::AsPrimitive;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+/// Marks a missing child of a node.
+const NO_CHILD: usize = usize::MAX;
+
+/// A node of the tree with the two bits at its level of the symbols with its prefix.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Node<const B_SIZE: usize> {
+    qv: AppendOnlyRSQVector<B_SIZE>,
+    children: [usize; 4], // The ids of the children in the next level, or NO_CHILD
+}
+
+impl<const B_SIZE: usize> Node<B_SIZE> {
+    fn new() -> Self {
+        Self {
+            qv: AppendOnlyRSQVector::new(),
+            children: [NO_CHILD; 4],
+        }
+    }
+}
+
+/// A Quad Wavelet Tree that supports appending symbols.
+///
+/// The generic const `B_SIZE` is the block size of the quad vectors of the nodes,
+/// either 256 (default) or 512.
+///
+/// # Examples
+///
+/// ```
+/// use qwt::{AccessUnsigned, AppendOnlyQWT256, RankUnsigned, SelectUnsigned};
+///
+/// let mut aqwt = AppendOnlyQWT256::new();
+/// aqwt.extend([1u32, 0, 1, 0, 2, 4, 5, 3]);
+/// assert_eq!(aqwt.rank(1, aqwt.len()), Some(2));
+///
+/// aqwt.push(1000);
+/// assert_eq!(aqwt.get(8), Some(1000));
+/// assert_eq!(aqwt.select(1000, 0), Some(8));
+/// assert_eq!(aqwt.len(), 9);
+/// ```
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct AppendOnlyQWaveletTree<T, const B_SIZE: usize = 256> {
+    n: usize,                       // The length of the represented sequence
+    levels: Vec<Vec<Node<B_SIZE>>>, // The nodes of each level, the root is the first node of the first level
+    phantom_data: PhantomData<T>,
+}
+
+impl<T, const B_SIZE: usize> Default for AppendOnlyQWaveletTree<T, B_SIZE> {
+    fn default() -> Self {
+        Self {
+            n: 0,
+            levels: Vec::new(),
+            phantom_data: PhantomData,
+        }
+    }
+}
+
+impl<T, const B_SIZE: usize> AppendOnlyQWaveletTree<T, B_SIZE>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+{
+    /// Creates an empty append-only wavelet tree.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::AppendOnlyQWT256;
+    ///
+    /// let aqwt = AppendOnlyQWT256::<u8>::new();
+    ///
+    /// assert!(aqwt.is_empty());
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the length of the indexed sequence.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::AppendOnlyQWT256;
+    ///
+    /// let aqwt: AppendOnlyQWT256<u8> = [1, 0, 1, 0, 2, 4, 5, 3].into_iter().collect();
+    ///
+    /// assert_eq!(aqwt.len(), 8);
+    /// ```
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Checks if the indexed sequence is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::AppendOnlyQWT256;
+    ///
+    /// let mut aqwt = AppendOnlyQWT256::new();
+    /// aqwt.push(1u8);
+    ///
+    /// assert!(!aqwt.is_empty());
+    /// ```
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Returns the number of levels of the wavelet tree.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::AppendOnlyQWT256;
+    ///
+    /// let mut aqwt: AppendOnlyQWT256<u32> = [1, 0, 1, 0, 2, 4, 5, 3].into_iter().collect();
+    /// assert_eq!(aqwt.n_levels(), 2);
+    ///
+    /// aqwt.push(1 << 20);
+    /// assert_eq!(aqwt.n_levels(), 11);
+    /// ```
+    #[must_use]
+    pub fn n_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Appends `symbol` at the end of the indexed sequence.
+    ///
+    /// ## Panics
+    /// Panics if the sequence becomes longer than the largest possible length
+    /// of a [`QWaveletTree`](crate::QWaveletTree), i.e., 2^{43}-1 symbols.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::{AccessUnsigned, AppendOnlyQWT256};
+    ///
+    /// let mut aqwt = AppendOnlyQWT256::new();
+    /// for i in 0..10_000u32 {
+    ///     aqwt.push(i % 7);
+    /// }
+    ///
+    /// assert_eq!(aqwt.len(), 10_000);
+    /// assert_eq!(aqwt.get(9_999), Some(9_999 % 7));
+    /// ```
+    pub fn push(&mut self, symbol: T) {
+        let n_levels = ((msb(symbol) + 2) / 2) as usize;
+        if n_levels > self.levels.len() {
+            self.add_levels(n_levels - self.levels.len());
+        }
+
+        let mut node_id = 0;
+        let mut shift = 2 * self.levels.len();
+        for level in 0..self.levels.len() {
+            shift -= 2;
+            let two_bits: u8 = (symbol >> shift).as_() & 3;
+            self.levels[level][node_id].qv.push(two_bits);
+            if shift > 0 {
+                node_id = self.child_or_insert(level, node_id, two_bits);
+            }
+        }
+        self.n += 1;
+    }
+
+    /// Returns an iterator over the symbols of the indexed sequence.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::AppendOnlyQWT256;
+    ///
+    /// let data: Vec<u8> = (0..10u8).cycle().take(5000).collect();
+    /// let aqwt: AppendOnlyQWT256<u8> = data.iter().copied().collect();
+    ///
+    /// assert_eq!(aqwt.iter().collect::<Vec<_>>(), data);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        // SAFETY: all the positions are within the bounds
+        (0..self.n).map(|i| unsafe { self.get_unchecked(i) })
+    }
+
+    // Returns the id of the child of the node for `two_bits`, which is created
+    // in the next level if it does not exist yet.
+    fn child_or_insert(&mut self, level: usize, node_id: usize, two_bits: u8) -> usize {
+        let child_id = self.levels[level][node_id].children[two_bits as usize];
+        if child_id != NO_CHILD {
+            return child_id;
+        }
+
+        let child_id = self.levels[level + 1].len();
+        self.levels[level + 1].push(Node::new());
+        self.levels[level][node_id].children[two_bits as usize] = child_id;
+        child_id
+    }
+
+    // Prepends `n_new_levels` levels, so that larger symbols can be appended.
+    // Each new level has a single node of zeros whose only child is the previous root.
+    fn add_levels(&mut self, n_new_levels: usize) {
+        if self.n == 0 {
+            // The nodes below the root are created by `push`
+            self.levels = vec![Vec::new(); n_new_levels + self.levels.len()];
+            self.levels[0].push(Node::new());
+            return;
+        }
+
+        let mut levels: Vec<Vec<Node<B_SIZE>>> = (0..n_new_levels)
+            .map(|_| {
+                let mut node = Node::new();
+                node.qv.extend(std::iter::repeat_n(0u8, self.n));
+                node.children[0] = 0;
+                vec![node]
+            })
+            .collect();
+        levels.append(&mut self.levels);
+        self.levels = levels;
+    }
+}
+
+impl<T, const B_SIZE: usize> AccessUnsigned for AppendOnlyQWaveletTree<T, B_SIZE>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+{
+    type Item = T;
+
+    /// Returns the `i`-th symbol of the indexed sequence, `None` if `i` is out of bounds.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::{AccessUnsigned, AppendOnlyQWT256};
+    ///
+    /// let aqwt: AppendOnlyQWT256<u8> = [1, 0, 1, 0, 2, 4, 5, 3].into_iter().collect();
+    ///
+    /// assert_eq!(aqwt.get(5), Some(4));
+    /// assert_eq!(aqwt.get(8), None);
+    /// ```
+    #[inline]
+    fn get(&self, i: usize) -> Option<Self::Item> {
+        if i >= self.n {
+            return None;
+        }
+        // SAFETY: check before guarantees we are not out of bound
+        Some(unsafe { self.get_unchecked(i) })
+    }
+
+    /// Returns the `i`-th symbol of the indexed sequence.
+    ///
+    /// # Safety
+    /// Calling this method with an out-of-bounds index is undefined behavior.
+    #[inline]
+    unsafe fn get_unchecked(&self, i: usize) -> Self::Item {
+        let mut result = T::zero();
+        let mut cur_i = i;
+        let mut node_id = 0;
+        for level in self.levels.iter() {
+            let node = level.get_unchecked(node_id);
+            let two_bits = node.qv.get_unchecked(cur_i);
+            result = (result << 2) | two_bits.as_();
+            cur_i = node.qv.rank_unchecked(two_bits, cur_i);
+            node_id = node.children[two_bits as usize];
+        }
+        result
+    }
+}
+
+impl<T, const B_SIZE: usize> RankUnsigned for AppendOnlyQWaveletTree<T, B_SIZE>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+{
+    /// Returns the rank of `symbol` up to position `i` **excluded**, `None` if `i`
+    /// is out of bounds. The rank of a symbol that does not occur in the sequence is 0.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::{AppendOnlyQWT256, RankUnsigned};
+    ///
+    /// let aqwt: AppendOnlyQWT256<u8> = [1, 0, 1, 0, 2, 4, 5, 3].into_iter().collect();
+    ///
+    /// assert_eq!(aqwt.rank(1, 2), Some(1));
+    /// assert_eq!(aqwt.rank(100, 8), Some(0));
+    /// assert_eq!(aqwt.rank(1, 9), None);
+    /// ```
+    #[inline]
+    fn rank(&self, symbol: Self::Item, i: usize) -> Option<usize> {
+        if i > self.n {
+            return None;
+        }
+        if self.levels.is_empty() || (msb(symbol) as usize) >= 2 * self.levels.len() {
+            return Some(0);
+        }
+        // SAFETY: checks above guarantee correctness
+        Some(unsafe { self.rank_unchecked(symbol, i) })
+    }
+
+    /// Returns the rank of `symbol` up to position `i` **excluded**.
+    ///
+    /// # Safety
+    /// Calling this method with a position `i` larger than the length of the sequence
+    /// or with a symbol too large for the current levels is undefined behavior.
+    #[inline]
+    unsafe fn rank_unchecked(&self, symbol: Self::Item, i: usize) -> usize {
+        let mut cur_i = i;
+        let mut node_id = 0;
+        let mut shift = 2 * self.levels.len();
+        for level in self.levels.iter() {
+            // A missing child has no occurrences, so we stop before visiting it
+            if cur_i == 0 {
+                return 0;
+            }
+            shift -= 2;
+            let two_bits: u8 = (symbol >> shift).as_() & 3;
+            let node = level.get_unchecked(node_id);
+            cur_i = node.qv.rank_unchecked(two_bits, cur_i);
+            node_id = node.children[two_bits as usize];
+        }
+        cur_i
+    }
+}
+
+impl<T, const B_SIZE: usize> SelectUnsigned for AppendOnlyQWaveletTree<T, B_SIZE>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+{
+    /// Returns the position of the `i+1`-th occurrence of `symbol`, `None` if
+    /// there is no such occurrence.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::{AppendOnlyQWT256, SelectUnsigned};
+    ///
+    /// let aqwt: AppendOnlyQWT256<u8> = [1, 0, 1, 0, 2, 4, 5, 3].into_iter().collect();
+    ///
+    /// assert_eq!(aqwt.select(1, 1), Some(2));
+    /// assert_eq!(aqwt.select(0, 2), None);
+    /// assert_eq!(aqwt.select(100, 0), None);
+    /// ```
+    #[inline]
+    fn select(&self, symbol: Self::Item, i: usize) -> Option<usize> {
+        if self.levels.is_empty() || (msb(symbol) as usize) >= 2 * self.levels.len() {
+            return None;
+        }
+
+        // The ids of the nodes on the root-to-leaf path of the symbol
+        let mut path = Vec::with_capacity(self.levels.len());
+        let mut node_id = 0;
+        let mut shift = 2 * self.levels.len();
+        for level in self.levels.iter() {
+            if node_id == NO_CHILD {
+                return None;
+            }
+            path.push(node_id);
+            shift -= 2;
+            let two_bits: u8 = (symbol >> shift).as_() & 3;
+            node_id = level[node_id].children[two_bits as usize];
+        }
+
+        let mut result = i;
+        for (level, &node_id) in self.levels.iter().zip(path.iter()).rev() {
+            let two_bits: u8 = (symbol >> shift).as_() & 3;
+            result = level[node_id].qv.select(two_bits, result)?;
+            shift += 2;
+        }
+        Some(result)
+    }
+
+    /// Returns the position of the `i+1`-th occurrence of `symbol`.
+    ///
+    /// # Safety
+    /// Calling this method if the `i+1`-th occurrence of `symbol` does not exist
+    /// is undefined behavior.
+    #[inline]
+    unsafe fn select_unchecked(&self, symbol: Self::Item, i: usize) -> usize {
+        self.select(symbol, i).unwrap()
+    }
+}
+
+impl<T, const B_SIZE: usize> SpaceUsage for AppendOnlyQWaveletTree<T, B_SIZE> {
+    /// Returns the space usage of the data structure in bytes.
+    fn space_usage_byte(&self) -> usize {
+        self.n.space_usage_byte()
+            + self.levels.iter().flatten().fold(0, |acc, node| {
+                acc + node.qv.space_usage_byte() + 4 * node.children[0].space_usage_byte()
+            })
+    }
+}
+
+impl<T, const B_SIZE: usize> FromIterator<T> for AppendOnlyQWaveletTree<T, B_SIZE>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut aqwt = AppendOnlyQWaveletTree::new();
+        aqwt.extend(iter);
+        aqwt
+    }
+}
+
+impl<T, const B_SIZE: usize> Extend<T> for AppendOnlyQWaveletTree<T, B_SIZE>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        for symbol in iter {
+            self.push(symbol);
+        }
+    }
+}
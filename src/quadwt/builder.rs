@@ -0,0 +1,432 @@
+//! A builder for [`QWaveletTree`] with bounded construction memory.
+//!
+//! [`QWaveletTree::new`] partitions the sequence at every level through a full
+//! copy of it, so the construction needs as much extra memory as the input.
+//! The builder partitions the sequence in place with a scratch buffer of
+//! bounded size, at the cost of a slower construction when the buffer is much
+//! smaller than the sequence, and reports an estimate of the peak memory of the last build.
+//!
+//! The builder can also consume the symbols from an iterator or from a reader
+//! without keeping the sequence in memory. The sequence is spilled to temporary
+//! files, which are partitioned level by level, so that only the wavelet tree
+//! and a few I/O buffers are kept in memory.
+
+use crate::quadwt::wt_level::WTLevel;
+use crate::quadwt::{QWaveletTree, RSforWT, WTIndexable};
+use crate::utils::{max_pending_runs, stable_partition_of_4_with_scratch, PartitionRun};
+use crate::{Error, QVectorBuilder, SpaceUsage};
+
+use num_traits::AsPrimitive;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Builds a [`QWaveletTree`] by using a bounded amount of extra memory.
+///
+/// By default, the scratch buffer holds 1/16 of the symbols of the sequence
+/// and the temporary files are created in [`std::env::temp_dir`].
+///
+/// # Examples
+///
+/// ```
+/// use qwt::{QWaveletTreeBuilder, RankUnsigned, QWT256};
+///
+/// let mut data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+///
+/// let mut builder = QWaveletTreeBuilder::new().scratch_size(2);
+/// let qwt: QWT256<_> = builder.build(&mut data);
+///
+/// assert_eq!(qwt.len(), 8);
+/// assert_eq!(qwt.rank(1, 4), Some(2));
+/// assert!(builder.estimated_peak_memory() > 8);
+///
+/// // The same wavelet tree built from an iterator through temporary files
+/// let streamed: QWT256<_> = builder
+///     .build_from_iter([1u8, 0, 1, 0, 2, 4, 5, 3])
+///     .unwrap();
+///
+/// assert_eq!(streamed, qwt);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct QWaveletTreeBuilder {
+    scratch_size: Option<usize>, // The number of symbols in the scratch buffer
+    temp_dir: Option<PathBuf>,   // The directory of the temporary files
+    peak_memory: usize,          // The estimated peak memory in bytes of the last build
+}
+
+impl QWaveletTreeBuilder {
+    /// The scratch buffer holds `1/DEFAULT_SCRATCH_FRACTION` of the symbols by default.
+    const DEFAULT_SCRATCH_FRACTION: usize = 16;
+
+    /// The size in bytes of the buffer of each temporary file.
+    const IO_BUFFER_SIZE: usize = 1 << 16;
+
+    /// Creates a builder with the default scratch size.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of symbols of the scratch buffer used to partition
+    /// the sequence. Smaller buffers save memory but slow down the construction,
+    /// whose time grows with the logarithm of the ratio between the length of
+    /// the sequence and `scratch_size`.
+    ///
+    /// # Panics
+    /// Panics if `scratch_size` is zero.
+    #[must_use]
+    pub fn scratch_size(mut self, scratch_size: usize) -> Self {
+        assert!(scratch_size > 0, "The scratch size must be positive.");
+        self.scratch_size = Some(scratch_size);
+        self
+    }
+
+    /// Sets the directory where the streaming constructions create their
+    /// temporary files.
+    #[must_use]
+    pub fn temp_dir<P: Into<PathBuf>>(mut self, temp_dir: P) -> Self {
+        self.temp_dir = Some(temp_dir.into());
+        self
+    }
+
+    /// Returns an estimate of the peak memory in bytes used by the last build.
+    ///
+    /// The estimate counts the input sequence, the scratch buffer, the runs kept by
+    /// the partitioning, and the wavelet tree, whose levels take over the quad vectors
+    /// they are built from. For the streaming constructions, the input sequence, the
+    /// scratch buffer, and the runs are replaced by the buffers of the temporary files.
+    /// It does not count the transient allocations made while building the
+    /// rank and select support of a level, e.g., the unused capacity of its growing
+    /// vectors, nor the overhead of the allocator.
+    /// It is zero if nothing has been built yet.
+    #[must_use]
+    pub fn estimated_peak_memory(&self) -> usize {
+        self.peak_memory
+    }
+
+    /// Builds the wavelet tree of the `sequence` of unsigned integers.
+    /// The input `sequence` will be **destroyed**.
+    ///
+    /// The resulting wavelet tree is identical to the one built by [`QWaveletTree::new`].
+    ///
+    /// ## Panics
+    /// Panics if the sequence is longer than the largest possible length.
+    /// The largest possible length is 2^{43}-1 symbols.
+    /// Use [`QWaveletTreeBuilder::try_build`] to get an error instead.
+    pub fn build<T, RS, const WITH_PREFETCH_SUPPORT: bool>(
+        &mut self,
+        sequence: &mut [T],
+    ) -> QWaveletTree<T, RS, WITH_PREFETCH_SUPPORT>
+    where
+        T: WTIndexable,
+        u8: AsPrimitive<T>,
+        RS: RSforWT,
+    {
+        match self.try_build(sequence) {
+            Ok(qwt) => qwt,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Builds the wavelet tree of the `sequence` of unsigned integers as
+    /// [`QWaveletTreeBuilder::build`] does, but returns an error instead of panicking.
+    /// The input `sequence` will be **destroyed**.
+    ///
+    /// # Errors
+    /// Returns [`Error::TooLong`] if the sequence is longer than the largest
+    /// possible length, i.e., 2^{43}-1 symbols. The sequence is left untouched in this case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{QWaveletTreeBuilder, QWT256};
+    ///
+    /// let mut data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let mut builder = QWaveletTreeBuilder::new();
+    /// let qwt: QWT256<_> = builder.try_build(&mut data).unwrap();
+    ///
+    /// assert_eq!(qwt.len(), 8);
+    /// ```
+    pub fn try_build<T, RS, const WITH_PREFETCH_SUPPORT: bool>(
+        &mut self,
+        sequence: &mut [T],
+    ) -> Result<QWaveletTree<T, RS, WITH_PREFETCH_SUPPORT>, Error>
+    where
+        T: WTIndexable,
+        u8: AsPrimitive<T>,
+        RS: RSforWT,
+    {
+        if sequence.len() > RS::MAX_LEN {
+            return Err(Error::TooLong {
+                len: sequence.len(),
+                max_len: RS::MAX_LEN,
+            });
+        }
+
+        let scratch_size = self
+            .scratch_size
+            .unwrap_or(sequence.len().div_ceil(Self::DEFAULT_SCRATCH_FRACTION))
+            .clamp(1, sequence.len().max(1));
+        let mut scratch = vec![T::zero(); scratch_size];
+
+        let qwt = QWaveletTree::new_with_partition(sequence, |sequence, shift| {
+            stable_partition_of_4_with_scratch(sequence, shift, &mut scratch);
+        });
+
+        let runs_memory =
+            max_pending_runs(sequence.len(), scratch_size) * std::mem::size_of::<PartitionRun>();
+        self.set_peak_memory(
+            &qwt,
+            std::mem::size_of_val(sequence) + std::mem::size_of_val(&scratch[..]) + runs_memory,
+        );
+
+        Ok(qwt)
+    }
+
+    /// Builds the wavelet tree of the sequence of unsigned integers produced by
+    /// `iter` without keeping the sequence in memory.
+    ///
+    /// The sequence is written to a temporary file and each level is computed
+    /// with a sequential scan of the files of the previous level, which is
+    /// partitioned into four new files at the same time.
+    /// The resulting wavelet tree is identical to the one built by [`QWaveletTree::new`].
+    ///
+    /// # Errors
+    /// Returns [`Error::TooLong`] as soon as the sequence gets longer than the largest
+    /// possible length, i.e., 2^{43}-1 symbols, without consuming the rest of `iter`
+    /// and before building any level. In this case, the length in the error is the
+    /// number of symbols consumed so far.
+    /// Returns [`Error::Io`] if the temporary files cannot be created, written, or read.
+    pub fn build_from_iter<T, RS, const WITH_PREFETCH_SUPPORT: bool, I>(
+        &mut self,
+        iter: I,
+    ) -> Result<QWaveletTree<T, RS, WITH_PREFETCH_SUPPORT>, Error>
+    where
+        T: WTIndexable,
+        u8: AsPrimitive<T>,
+        RS: RSforWT,
+        I: IntoIterator<Item = T>,
+    {
+        self.build_from_results(iter.into_iter().map(Ok))
+    }
+
+    /// Builds the wavelet tree of the sequence of unsigned integers read from
+    /// `reader` without keeping the sequence in memory. Each symbol is encoded
+    /// in little-endian order with `size_of::<T>()` bytes, so a text is just a
+    /// sequence of bytes for `T = u8`.
+    ///
+    /// The construction is the same as the one of [`QWaveletTreeBuilder::build_from_iter`].
+    ///
+    /// # Errors
+    /// Returns [`Error::TooLong`] as [`QWaveletTreeBuilder::build_from_iter`] does.
+    /// Returns [`Error::Io`] if the reader fails, if its length is not a multiple of the
+    /// size of `T`, or if the temporary files cannot be created, written, or read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{QWaveletTreeBuilder, RankUnsigned, QWT256};
+    ///
+    /// let text = b"abracadabra";
+    ///
+    /// let mut builder = QWaveletTreeBuilder::new();
+    /// let qwt: QWT256<u8> = builder.build_from_reader(&text[..]).unwrap();
+    ///
+    /// assert_eq!(qwt.len(), 11);
+    /// assert_eq!(qwt.rank(b'a', 11), Some(5));
+    /// ```
+    pub fn build_from_reader<T, RS, const WITH_PREFETCH_SUPPORT: bool, R>(
+        &mut self,
+        reader: R,
+    ) -> Result<QWaveletTree<T, RS, WITH_PREFETCH_SUPPORT>, Error>
+    where
+        T: WTIndexable,
+        u8: AsPrimitive<T>,
+        RS: RSforWT,
+        R: Read,
+    {
+        let mut reader = BufReader::with_capacity(Self::IO_BUFFER_SIZE, reader);
+        self.build_from_results(std::iter::from_fn(|| read_symbol(&mut reader).transpose()))
+    }
+
+    /// Builds the wavelet tree of the sequence produced by `iter` by spilling it
+    /// to temporary files. Returns the first error produced by `iter`, if any.
+    fn build_from_results<T, RS, const WITH_PREFETCH_SUPPORT: bool, I>(
+        &mut self,
+        iter: I,
+    ) -> Result<QWaveletTree<T, RS, WITH_PREFETCH_SUPPORT>, Error>
+    where
+        T: WTIndexable,
+        u8: AsPrimitive<T>,
+        RS: RSforWT,
+        I: Iterator<Item = io::Result<T>>,
+    {
+        let temp_dir = self.temp_dir.clone().unwrap_or_else(std::env::temp_dir);
+
+        // Spill the sequence and compute its length and its largest symbol
+        let input = SpillFile::create(&temp_dir)?;
+        let mut writer = input.writer()?;
+        let mut n = 0;
+        let mut sigma = T::zero();
+        for symbol in iter {
+            // Stop before spilling the rest of a too long sequence
+            if n == RS::MAX_LEN {
+                return Err(Error::TooLong {
+                    len: n + 1,
+                    max_len: RS::MAX_LEN,
+                });
+            }
+            let symbol = symbol?;
+            write_symbol(&mut writer, symbol)?;
+            sigma = sigma.max(symbol);
+            n += 1;
+        }
+        writer.flush()?;
+        drop(writer);
+
+        // The current sequence is the concatenation of these files
+        let mut sequence = vec![input];
+
+        let qwt = QWaveletTree::from_levels(n, sigma, |_level, shift| {
+            // There is no need to partition the sequence after the last level
+            let partitions = if shift > 0 {
+                (0..4)
+                    .map(|_| SpillFile::create(&temp_dir))
+                    .collect::<io::Result<Vec<_>>>()?
+            } else {
+                Vec::new()
+            };
+            let mut writers = partitions
+                .iter()
+                .map(SpillFile::writer)
+                .collect::<io::Result<Vec<_>>>()?;
+
+            let mut qv = QVectorBuilder::with_capacity(n);
+            for file in &sequence {
+                let mut reader = file.reader()?;
+                while let Some(symbol) = read_symbol::<T, _>(&mut reader)? {
+                    let two_bits: u8 = (symbol >> shift).as_() & 3; // take the last 2 bits
+                    qv.push(two_bits);
+                    if let Some(writer) = writers.get_mut(two_bits as usize) {
+                        write_symbol(writer, symbol)?;
+                    }
+                }
+            }
+
+            for writer in &mut writers {
+                writer.flush()?;
+            }
+            drop(writers);
+
+            sequence = partitions; // the files of the previous level are removed
+            Ok::<_, Error>(qv.build())
+        })?;
+
+        // Besides the wavelet tree, at most four writers and one reader are alive
+        self.set_peak_memory(&qwt, 5 * Self::IO_BUFFER_SIZE);
+
+        Ok(qwt)
+    }
+
+    /// Sets the estimated peak memory of the construction of `qwt`, which used
+    /// `extra_memory` bytes besides the wavelet tree itself.
+    fn set_peak_memory<T, RS, const WITH_PREFETCH_SUPPORT: bool>(
+        &mut self,
+        qwt: &QWaveletTree<T, RS, WITH_PREFETCH_SUPPORT>,
+        extra_memory: usize,
+    ) where
+        RS: SpaceUsage,
+    {
+        // The quad vector of a level is moved into the level, so, besides the transient
+        // allocations of its rank and select support, the memory grows with the levels
+        // and the estimate is the memory at the end of the construction.
+        self.peak_memory = extra_memory + qwt.space_usage_byte();
+
+        // A binary first level is built from a quad vector of 64 bytes every 256 symbols,
+        // which is larger than the level itself. It matters only if it is the only level.
+        if qwt.n_levels == 1 && matches!(qwt.qvs[0], WTLevel::Binary { .. }) {
+            self.peak_memory += qwt.n.div_ceil(256) * 64;
+        }
+    }
+}
+
+/// A temporary file which is removed when dropped.
+struct SpillFile {
+    path: PathBuf,
+}
+
+impl SpillFile {
+    /// Creates a new empty temporary file in the directory `dir`.
+    fn create(dir: &Path) -> io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        loop {
+            let path = dir.join(format!(
+                "qwt-{}-{}.tmp",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Self { path }),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Returns a buffered writer that overwrites the file.
+    fn writer(&self) -> io::Result<BufWriter<File>> {
+        Ok(BufWriter::with_capacity(
+            QWaveletTreeBuilder::IO_BUFFER_SIZE,
+            File::create(&self.path)?,
+        ))
+    }
+
+    /// Returns a buffered reader from the beginning of the file.
+    fn reader(&self) -> io::Result<BufReader<File>> {
+        Ok(BufReader::with_capacity(
+            QWaveletTreeBuilder::IO_BUFFER_SIZE,
+            File::open(&self.path)?,
+        ))
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Writes `symbol` with `size_of::<T>()` bytes in little-endian order.
+fn write_symbol<T: WTIndexable, W: Write>(writer: &mut W, symbol: T) -> io::Result<()> {
+    let bytes = symbol.to_u128().unwrap().to_le_bytes();
+    writer.write_all(&bytes[..std::mem::size_of::<T>()])
+}
+
+/// Reads a symbol written by [`write_symbol`]. Returns `None` at the end of the
+/// input and an error if the input ends in the middle of a symbol.
+fn read_symbol<T: WTIndexable, R: Read>(reader: &mut R) -> io::Result<Option<T>> {
+    let mut bytes = [0u8; 16];
+    let size = std::mem::size_of::<T>();
+
+    let mut filled = 0;
+    while filled < size {
+        match reader.read(&mut bytes[filled..size]) {
+            Ok(0) => break,
+            Ok(k) => filled += k,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    match filled {
+        0 => Ok(None),
+        _ if filled < size => Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "The input ends in the middle of a symbol.",
+        )),
+        _ => Ok(num_traits::cast(u128::from_le_bytes(bytes))),
+    }
+}
@@ -0,0 +1,465 @@
+//! A dynamic Quad Wavelet Tree, i.e., a wavelet tree that supports insertions,
+//! deletions, and updates of symbols at arbitrary positions.
+//!
+//! [`DynamicQWaveletTree`] has the same shape as [`QWaveletTree`](crate::QWaveletTree),
+//! i.e., a wavelet matrix with two bits per level, but each level is a
+//! [`DynamicQVector`] instead of a static quad vector.
+//! The position of a symbol in the next level is computed with a `rank` query,
+//! so an update of a symbol is an update of each of the $$\lceil \log \sigma / 2 \rceil$$ levels.
+//! All the operations take $$O(\log \sigma \log n)$$ time.
+//!
+//! The number of levels grows as needed when a symbol larger than all the previous ones
+//! is inserted. The new levels are prepended and filled with zeros, as the higher bits
+//! of the previous symbols are zeros.
+
+use crate::quadwt::WTIndexable;
+use crate::qvector::dynamic_qvector::DynamicQVector;
+use crate::utils::msb;
+use crate::SpaceUsage;
+use crate::{AccessQuad, AccessUnsigned, RankQuad, RankUnsigned, SelectQuad, SelectUnsigned};
+
+use num_traits::AsPrimitive;
+use std::marker::PhantomData;
+
+/// A Quad Wavelet Tree that supports insertions, deletions, and updates of symbols.
+///
+/// # Examples
+///
+/// ```
+/// use qwt::{AccessUnsigned, DynamicQWaveletTree, RankUnsigned, SelectUnsigned};
+///
+/// let mut dqwt: DynamicQWaveletTree<u32> = [1, 0, 1, 0, 2, 4, 5, 3].into_iter().collect();
+///
+/// dqwt.insert(0, 1000);
+/// dqwt.push(5);
+/// assert_eq!(dqwt.get(0), Some(1000));
+/// assert_eq!(dqwt.rank(5, dqwt.len()), Some(2));
+///
+/// assert_eq!(dqwt.remove(1), 1);
+/// assert_eq!(dqwt.set(0, 3), 1000);
+/// assert_eq!(dqwt.select(3, 1), Some(7));
+/// assert_eq!(dqwt.len(), 9);
+/// ```
+#[derive(Clone, Debug)]
+pub struct DynamicQWaveletTree<T> {
+    n: usize,                 // The length of the represented sequence
+    qvs: Vec<DynamicQVector>, // A dynamic quad vector for each level
+    phantom_data: PhantomData<T>,
+}
+
+impl<T> Default for DynamicQWaveletTree<T> {
+    fn default() -> Self {
+        Self {
+            n: 0,
+            qvs: Vec::new(),
+            phantom_data: PhantomData,
+        }
+    }
+}
+
+impl<T> DynamicQWaveletTree<T>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+{
+    /// Creates an empty dynamic wavelet tree.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::DynamicQWaveletTree;
+    ///
+    /// let dqwt = DynamicQWaveletTree::<u8>::new();
+    ///
+    /// assert!(dqwt.is_empty());
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the length of the indexed sequence.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::DynamicQWaveletTree;
+    ///
+    /// let dqwt: DynamicQWaveletTree<u8> = [1, 0, 1, 0, 2, 4, 5, 3].into_iter().collect();
+    ///
+    /// assert_eq!(dqwt.len(), 8);
+    /// ```
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Checks if the indexed sequence is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::DynamicQWaveletTree;
+    ///
+    /// let mut dqwt = DynamicQWaveletTree::new();
+    /// dqwt.push(1u8);
+    ///
+    /// assert!(!dqwt.is_empty());
+    /// ```
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Returns the number of levels of the wavelet tree.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::DynamicQWaveletTree;
+    ///
+    /// let mut dqwt: DynamicQWaveletTree<u32> = [1, 0, 1, 0, 2, 4, 5, 3].into_iter().collect();
+    /// assert_eq!(dqwt.n_levels(), 2);
+    ///
+    /// dqwt.push(1 << 20);
+    /// assert_eq!(dqwt.n_levels(), 11);
+    /// ```
+    #[must_use]
+    pub fn n_levels(&self) -> usize {
+        self.qvs.len()
+    }
+
+    /// Inserts `symbol` at position `i`, shifting all the following symbols to the right.
+    ///
+    /// ## Panics
+    /// Panics if `i` is larger than the length of the sequence.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::{AccessUnsigned, DynamicQWaveletTree};
+    ///
+    /// let mut dqwt: DynamicQWaveletTree<u8> = [1, 0, 1, 0].into_iter().collect();
+    /// dqwt.insert(2, 7);
+    ///
+    /// assert_eq!(dqwt.get(2), Some(7));
+    /// assert_eq!(dqwt.get(3), Some(1));
+    /// ```
+    pub fn insert(&mut self, i: usize, symbol: T) {
+        assert!(i <= self.n, "The position {i} is out of bounds.");
+
+        let n_levels = ((msb(symbol) + 2) / 2) as usize;
+        if n_levels > self.qvs.len() {
+            self.add_levels(n_levels - self.qvs.len());
+        }
+
+        let mut cur_i = i;
+        let mut shift = 2 * self.qvs.len();
+        for qv in self.qvs.iter_mut() {
+            shift -= 2;
+            let two_bits: u8 = (symbol >> shift).as_() & 3;
+            qv.insert(cur_i, two_bits);
+
+            // SAFETY: two_bits is in [0, 3] and cur_i is not out of bounds
+            cur_i =
+                unsafe { qv.rank_unchecked(two_bits, cur_i) } + qv.occs_smaller(two_bits).unwrap();
+        }
+        self.n += 1;
+    }
+
+    /// Appends `symbol` at the end of the sequence.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::{DynamicQWaveletTree, RankUnsigned};
+    ///
+    /// let mut dqwt = DynamicQWaveletTree::new();
+    /// dqwt.push(3u16);
+    /// dqwt.push(3u16);
+    ///
+    /// assert_eq!(dqwt.rank(3, 2), Some(2));
+    /// ```
+    pub fn push(&mut self, symbol: T) {
+        self.insert(self.n, symbol);
+    }
+
+    /// Removes and returns the symbol at position `i`, shifting all the following
+    /// symbols to the left.
+    ///
+    /// ## Panics
+    /// Panics if `i` is out of bounds.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::{AccessUnsigned, DynamicQWaveletTree};
+    ///
+    /// let mut dqwt: DynamicQWaveletTree<u8> = [1, 0, 2, 0].into_iter().collect();
+    ///
+    /// assert_eq!(dqwt.remove(2), 2);
+    /// assert_eq!(dqwt.get(2), Some(0));
+    /// assert_eq!(dqwt.len(), 3);
+    /// ```
+    pub fn remove(&mut self, i: usize) -> T {
+        assert!(i < self.n, "The position {i} is out of bounds.");
+
+        let mut result = T::zero();
+        let mut cur_i = i;
+        for qv in self.qvs.iter_mut() {
+            let two_bits = qv.remove(cur_i);
+            result = (result << 2) | two_bits.as_();
+
+            // The rank of the removed symbol is the same before and after the removal
+            // SAFETY: two_bits is in [0, 3] and cur_i is not out of bounds
+            cur_i =
+                unsafe { qv.rank_unchecked(two_bits, cur_i) } + qv.occs_smaller(two_bits).unwrap();
+        }
+        self.n -= 1;
+        result
+    }
+
+    /// Replaces the symbol at position `i` with `symbol` and returns the old one.
+    ///
+    /// ## Panics
+    /// Panics if `i` is out of bounds.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::{DynamicQWaveletTree, RankUnsigned};
+    ///
+    /// let mut dqwt: DynamicQWaveletTree<u8> = [1, 0, 2, 0].into_iter().collect();
+    ///
+    /// assert_eq!(dqwt.set(1, 2), 0);
+    /// assert_eq!(dqwt.rank(2, 4), Some(2));
+    /// ```
+    pub fn set(&mut self, i: usize, symbol: T) -> T {
+        let old = self.remove(i);
+        self.insert(i, symbol);
+        old
+    }
+
+    /// Returns an iterator over the symbols of the indexed sequence.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::DynamicQWaveletTree;
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    /// let dqwt: DynamicQWaveletTree<u8> = data.iter().copied().collect();
+    ///
+    /// assert_eq!(dqwt.iter().collect::<Vec<_>>(), data);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        // SAFETY: all the positions are within the bounds
+        (0..self.n).map(|i| unsafe { self.get_unchecked(i) })
+    }
+
+    // Prepends `n_new_levels` levels of zeros, so that larger symbols can be inserted.
+    fn add_levels(&mut self, n_new_levels: usize) {
+        let mut qvs: Vec<DynamicQVector> = (0..n_new_levels)
+            .map(|_| std::iter::repeat_n(0u8, self.n).collect())
+            .collect();
+        qvs.append(&mut self.qvs);
+        self.qvs = qvs;
+    }
+}
+
+impl<T> AccessUnsigned for DynamicQWaveletTree<T>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+{
+    type Item = T;
+
+    /// Returns the `i`-th symbol of the indexed sequence, `None` if `i` is out of bounds.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::{AccessUnsigned, DynamicQWaveletTree};
+    ///
+    /// let dqwt: DynamicQWaveletTree<u8> = [1, 0, 1, 0, 2, 4, 5, 3].into_iter().collect();
+    ///
+    /// assert_eq!(dqwt.get(5), Some(4));
+    /// assert_eq!(dqwt.get(8), None);
+    /// ```
+    #[inline]
+    fn get(&self, i: usize) -> Option<Self::Item> {
+        if i >= self.n {
+            return None;
+        }
+        // SAFETY: check before guarantees we are not out of bound
+        Some(unsafe { self.get_unchecked(i) })
+    }
+
+    /// Returns the `i`-th symbol of the indexed sequence.
+    ///
+    /// # Safety
+    /// Calling this method with an out-of-bounds index is undefined behavior.
+    #[inline]
+    unsafe fn get_unchecked(&self, i: usize) -> Self::Item {
+        let mut result = T::zero();
+        let mut cur_i = i;
+        for qv in self.qvs.iter() {
+            let two_bits = qv.get_unchecked(cur_i);
+            result = (result << 2) | two_bits.as_();
+            cur_i = qv.rank_unchecked(two_bits, cur_i) + qv.occs_smaller(two_bits).unwrap();
+        }
+        result
+    }
+}
+
+impl<T> RankUnsigned for DynamicQWaveletTree<T>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+{
+    /// Returns the rank of `symbol` up to position `i` **excluded**, `None` if `i`
+    /// is out of bounds. The rank of a symbol too large for the current levels is 0.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::{DynamicQWaveletTree, RankUnsigned};
+    ///
+    /// let dqwt: DynamicQWaveletTree<u8> = [1, 0, 1, 0, 2, 4, 5, 3].into_iter().collect();
+    ///
+    /// assert_eq!(dqwt.rank(1, 2), Some(1));
+    /// assert_eq!(dqwt.rank(100, 8), Some(0));
+    /// assert_eq!(dqwt.rank(1, 9), None);
+    /// ```
+    #[inline]
+    fn rank(&self, symbol: Self::Item, i: usize) -> Option<usize> {
+        if i > self.n {
+            return None;
+        }
+        if self.qvs.is_empty() || (msb(symbol) as usize) >= 2 * self.qvs.len() {
+            return Some(0);
+        }
+        // SAFETY: checks above guarantee correctness
+        Some(unsafe { self.rank_unchecked(symbol, i) })
+    }
+
+    /// Returns the rank of `symbol` up to position `i` **excluded**.
+    ///
+    /// # Safety
+    /// Calling this method with a position `i` larger than the length of the sequence
+    /// or with a symbol too large for the current levels is undefined behavior.
+    #[inline]
+    unsafe fn rank_unchecked(&self, symbol: Self::Item, i: usize) -> usize {
+        let mut cur_i = i;
+        let mut cur_p = 0;
+        let mut shift = 2 * self.qvs.len();
+        for qv in self.qvs.iter() {
+            shift -= 2;
+            let two_bits: u8 = (symbol >> shift).as_() & 3;
+            let offset = qv.occs_smaller(two_bits).unwrap();
+            cur_p = qv.rank_unchecked(two_bits, cur_p) + offset;
+            cur_i = qv.rank_unchecked(two_bits, cur_i) + offset;
+        }
+        cur_i - cur_p
+    }
+}
+
+impl<T> SelectUnsigned for DynamicQWaveletTree<T>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+{
+    /// Returns the position of the `i+1`-th occurrence of `symbol`, `None` if
+    /// there is no such occurrence.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::{DynamicQWaveletTree, SelectUnsigned};
+    ///
+    /// let dqwt: DynamicQWaveletTree<u8> = [1, 0, 1, 0, 2, 4, 5, 3].into_iter().collect();
+    ///
+    /// assert_eq!(dqwt.select(1, 1), Some(2));
+    /// assert_eq!(dqwt.select(0, 2), None);
+    /// assert_eq!(dqwt.select(100, 0), None);
+    /// ```
+    #[inline]
+    fn select(&self, symbol: Self::Item, i: usize) -> Option<usize> {
+        if self.qvs.is_empty() || (msb(symbol) as usize) >= 2 * self.qvs.len() {
+            return None;
+        }
+
+        // Positions of the beginning of the range of the symbol in each level
+        let mut path_off = Vec::with_capacity(self.qvs.len());
+        let mut rank_path_off = Vec::with_capacity(self.qvs.len());
+
+        let mut b = 0;
+        let mut shift = 2 * self.qvs.len();
+        for qv in self.qvs.iter() {
+            shift -= 2;
+            path_off.push(b);
+            let two_bits: u8 = (symbol >> shift).as_() & 3;
+            let rank_b = qv.rank(two_bits, b)?;
+            b = rank_b + qv.occs_smaller(two_bits)?;
+            rank_path_off.push(rank_b);
+        }
+
+        let mut result = i;
+        for (level, qv) in self.qvs.iter().enumerate().rev() {
+            let two_bits: u8 = (symbol >> shift).as_() & 3;
+            result = qv.select(two_bits, rank_path_off[level] + result)? - path_off[level];
+            shift += 2;
+        }
+        Some(result)
+    }
+
+    /// Returns the position of the `i+1`-th occurrence of `symbol`.
+    ///
+    /// # Safety
+    /// Calling this method if the `i+1`-th occurrence of `symbol` does not exist
+    /// is undefined behavior.
+    #[inline]
+    unsafe fn select_unchecked(&self, symbol: Self::Item, i: usize) -> usize {
+        self.select(symbol, i).unwrap()
+    }
+}
+
+impl<T> SpaceUsage for DynamicQWaveletTree<T> {
+    /// Returns the space usage of the data structure in bytes.
+    fn space_usage_byte(&self) -> usize {
+        self.n.space_usage_byte()
+            + self
+                .qvs
+                .iter()
+                .fold(0, |acc, qv| acc + qv.space_usage_byte())
+    }
+}
+
+/// Two dynamic wavelet trees are equal if they index the same sequence.
+impl<T> PartialEq for DynamicQWaveletTree<T>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.n == other.n && self.iter().eq(other.iter())
+    }
+}
+
+impl<T> FromIterator<T> for DynamicQWaveletTree<T>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut dqwt = DynamicQWaveletTree::new();
+        dqwt.extend(iter);
+        dqwt
+    }
+}
+
+impl<T> Extend<T> for DynamicQWaveletTree<T>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        for symbol in iter {
+            self.push(symbol);
+        }
+    }
+}
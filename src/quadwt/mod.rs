@@ -6,6 +6,20 @@
 //! - `rank(s, i)`: Counts the number of occurrences of symbol `s` up to position `i`, excluding `i`.
 //! - `select(s, i)`: Returns the position of the `i+1`-th occurrence of symbol `s`.
 //!
+//! The queries `get_batch`, `rank_batch`, and `select_batch` answer many independent queries at once, overlapping their cache misses for a higher throughput.
+//!
+//! The query `backward_search_step(l..r, s)` computes both `rank(s, l)` and `rank(s, r)` with a single traversal, as needed by the backward search of an FM-index.
+//!
+//! The wavelet tree also counts the occurrences of the symbols smaller than, smaller than or equal to, or larger than a given symbol up to a position with `rank_lt(s, i)`, `rank_leq(s, i)`, and `rank_gt(s, i)`.
+//!
+//! It also supports queries on ranges of positions:
+//!
+//! - `quantile(l..r, k)`: Returns the `k`-th smallest symbol in `S[l..r)`. The method `range_median(l..r)` is a shorthand for the median.
+//! - `range_count(l..r, a..=b)`: Counts the number of positions in `[l, r)` holding a symbol in `[a, b]`.
+//! - `range_distinct(l..r)`: Iterates over the distinct symbols in `S[l..r)` with their frequencies, in increasing order of symbol.
+//! - `range_top_k(l..r, k)`: Returns the `k` most frequent symbols in `S[l..r)` with their frequencies.
+//! - `range_next_value(l..r, x)` and `range_prev_value(l..r, x)`: Return the smallest symbol larger than or equal to `x` and the largest symbol smaller than or equal to `x` in `S[l..r)`, respectively.
+//!
 //! We have four aliases types for a Quad Wavelet Tree: `QWT256<T>`, `QWT512<T>`, `QWT256Pfs<T>`, and `QWT512Pfs<T>`. The generic type `T` is the type of the indexed unsigned integer values.
 //! The values 256 and 512 are the employed block sizes in the internal representation.
 //! A block size of 256 has a faster query time at the cost of slightly larger space overhead.
@@ -13,10 +27,14 @@
 //!
 //! ## Performance
 //!
-//! All operations run in $$\Theta(\log \sigma)$$ time, where $$\sigma$$ is the alphabet size, i.e., one plus the largest symbol in the sequence. The space usage is $$n \log \sigma + o(n \log \sigma )$$ bits.
+//! All operations run in $$\Theta(\log \sigma)$$ time, where $$\sigma$$ is the alphabet size, i.e., one plus the largest symbol in the sequence. The space usage is $$n \lceil\log \sigma\rceil + o(n \log \sigma )$$ bits. Each level stores two bits per symbol, except the first one that stores a single bit per symbol whenever $$\lceil\log \sigma\rceil$$ is odd.
 //!
 //! To optimize query time and space usage, it's advisable to compact the alphabet and remove "holes," if any.
 //!
+//! The construction with [`QWaveletTree::new`] temporarily needs extra memory as large as the input sequence. Use a [`QWaveletTreeBuilder`] to bound this extra memory and to estimate the peak memory of the construction. The builder can also consume a sequence larger than the memory from an iterator or a reader by spilling it to temporary files.
+//!
+//! With the `parallel` feature, the quad vectors of the levels, their superblock counters, and the partitioning of the sequence are built by all the available cores. A binary first level and the prefetch support are still built by a single thread.
+//!
 //! ## Limitations
 //!
 //! This data structure can efficiently index vectors of lengths up to 2^{43} symbols.
@@ -28,7 +46,7 @@
 //!
 //! // Example usage of Quad Wavelet Tree
 //! // Constructing a Qwt256Pfs for u32 integers
-//! let qwt = QWT256Pfs::from(vec![1_u32, 2, 3, 4, 5, 6, 7, 8]);
+//! let qwt = QWT256Pfs::try_from(vec![1_u32, 2, 3, 4, 5, 6, 7, 8]).unwrap();
 //!
 //! // Querying operations
 //! assert_eq!(qwt.get(3), Some(4));  // Accesses the 3rd symbol (0-indexed), should return 4
@@ -36,20 +54,45 @@
 //! assert_eq!(qwt.select(3, 0), Some(2));  // Finds the position of the 1st occurrence of symbol 3, should return Some(2)
 //! ```
 
-use crate::utils::{msb, stable_partition_of_4};
+use crate::persist::Persist;
+use crate::utils::msb;
+#[cfg(not(feature = "parallel"))]
+use crate::utils::stable_partition_of_4;
+#[cfg(feature = "parallel")]
+use crate::utils::{n_threads, stable_partition_of_4_parallel};
+use crate::zero_copy::{LayoutError, LayoutReader, LayoutWriter, ZeroCopy};
+use crate::Error;
 use crate::{AccessUnsigned, RankUnsigned, SelectUnsigned, SpaceUsage, WTSupport};
 use crate::{QVector, QVectorBuilder}; // Traits
 
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{self, Write};
 use std::marker::PhantomData;
 
 // Traits bound
 use num_traits::{AsPrimitive, PrimInt, Unsigned};
-use std::ops::{Shl, Shr};
+use std::ops::{Range, RangeInclusive, Shl, Shr};
 
 mod prefetch_support;
 use crate::quadwt::prefetch_support::PrefetchSupport;
 
+mod wt_level;
+use crate::quadwt::wt_level::WTLevel;
+
+mod builder;
+pub use crate::quadwt::builder::QWaveletTreeBuilder;
+
+mod dynamic;
+pub use crate::quadwt::dynamic::DynamicQWaveletTree;
+
+mod append_only;
+pub use crate::quadwt::append_only::AppendOnlyQWaveletTree;
+
+/// The number of independent queries processed in lock-step by the batch queries.
+const BATCH_SIZE: usize = 32;
+
 /// Alias for the trait bounds to be satisfied by a data structure
 /// to support `rank` and `select` queries at each level of the wavelet tree.
 /// We need an alias to avoid repeating a lot of bounds here and there.
@@ -60,11 +103,13 @@ impl<T> RSforWT for T where T: From<QVector> + WTSupport + SpaceUsage + Default
 
 /// Alias for the trait bounds of the type T to be indexable in the
 /// wavelet tree.
+#[cfg(not(feature = "parallel"))]
 pub trait WTIndexable:
     Unsigned + PrimInt + Ord + Shr<usize> + Shl<usize> + AsPrimitive<u8>
 {
 }
 
+#[cfg(not(feature = "parallel"))]
 impl<T> WTIndexable for T
 where
     T: Unsigned + PrimInt + Ord + Shr<usize> + Shl<usize> + AsPrimitive<u8>,
@@ -72,6 +117,60 @@ where
 {
 }
 
+/// Alias for the trait bounds of the type T to be indexable in the
+/// wavelet tree. The parallel construction shares the symbols among
+/// threads, so they must be `Send` and `Sync`.
+#[cfg(feature = "parallel")]
+pub trait WTIndexable:
+    Unsigned + PrimInt + Ord + Shr<usize> + Shl<usize> + AsPrimitive<u8> + Send + Sync
+{
+}
+
+#[cfg(feature = "parallel")]
+impl<T> WTIndexable for T
+where
+    T: Unsigned + PrimInt + Ord + Shr<usize> + Shl<usize> + AsPrimitive<u8> + Send + Sync,
+    u8: AsPrimitive<T>,
+{
+}
+
+/// Builds the quad vector of a level with the two bits of each symbol in
+/// `sequence` obtained by shifting `shift` bits to the right.
+/// Each of the `n_threads` threads builds the data lines of a chunk of the
+/// sequence, so the result is identical to the one of the sequential construction.
+#[cfg(feature = "parallel")]
+fn build_level_parallel<T>(sequence: &[T], shift: usize, n_threads: usize) -> QVector
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+{
+    // Chunks are made of full data lines of 256 symbols
+    let chunk_size = sequence.len().div_ceil(n_threads).next_multiple_of(256);
+
+    let chunks: Vec<QVectorBuilder> = std::thread::scope(|s| {
+        let handles: Vec<_> = sequence
+            .chunks(chunk_size)
+            .map(|chunk| {
+                s.spawn(move || {
+                    let mut cur_qv = QVectorBuilder::with_capacity(chunk.len());
+                    for &symbol in chunk {
+                        let two_bits: u8 = (symbol >> shift).as_() & 3; // take the last 2 bits
+                        cur_qv.push(two_bits);
+                    }
+                    cur_qv
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    let mut qv = QVectorBuilder::with_capacity(sequence.len());
+    for chunk in chunks {
+        qv.append(chunk);
+    }
+    qv.build()
+}
+
 /// The generic RS is the data structure we use to index a quaternary
 /// sequence to support `access, `rank`, and `select` queries.
 ///
@@ -79,12 +178,16 @@ where
 /// is augmented with extra data to support a deeper level of prefetching.
 /// This extra informationa are needed only for sequences such that data
 /// about superblocks and blocks do not fit in L3 cache.
+///
+/// The `serde` representation of the levels changed in version 0.3.0 to allow
+/// a binary first level, so a wavelet tree serialized by an older version of the
+/// crate cannot be deserialized and has to be rebuilt.
 #[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct QWaveletTree<T, RS, const WITH_PREFETCH_SUPPORT: bool = false> {
-    n: usize,        // The length of the represented sequence
-    n_levels: usize, // The number of levels of the wavelet matrix
+    n: usize,              // The length of the represented sequence
+    n_levels: usize,       // The number of levels of the wavelet matrix
     sigma: T, // The largest symbol in the sequence. *NOTE*: It's not +1 because it may overflow
-    qvs: Vec<RS>, // A quad vector for each level
+    qvs: Vec<WTLevel<RS>>, // A quad vector for each level, the first one may be binary
     prefetch_support: Option<Vec<PrefetchSupport>>,
 }
 
@@ -107,7 +210,8 @@ where
     ///
     /// ## Panics
     /// Panics if the sequence is longer than the largest possible length.
-    /// The largest possible length is 2^{43} symbols.
+    /// The largest possible length is 2^{43}-1 symbols.
+    /// Use [`QWaveletTree::try_new`] to get an error instead.
     ///
     /// # Examples
     /// ```
@@ -121,51 +225,139 @@ where
     /// ```
     #[must_use]
     pub fn new(sequence: &mut [T]) -> Self {
-        if sequence.is_empty() {
-            return Self {
+        match Self::try_new(sequence) {
+            Ok(qwt) => qwt,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Builds the wavelet tree of the `sequence` of unsigned integers as
+    /// [`QWaveletTree::new`] does, but returns an error instead of panicking.
+    /// The input `sequence`` will be **destroyed**.
+    ///
+    /// # Errors
+    /// Returns [`Error::TooLong`] if the sequence is longer than the largest
+    /// possible length, i.e., 2^{43}-1 symbols. The sequence is left untouched in this case.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::QWT256;
+    ///
+    /// let mut data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let qwt = QWT256::try_new(&mut data).unwrap();
+    ///
+    /// assert_eq!(qwt.len(), 8);
+    /// ```
+    pub fn try_new(sequence: &mut [T]) -> Result<Self, Error> {
+        if sequence.len() > RS::MAX_LEN {
+            return Err(Error::TooLong {
+                len: sequence.len(),
+                max_len: RS::MAX_LEN,
+            });
+        }
+
+        #[cfg(feature = "parallel")]
+        let n_threads = n_threads();
+
+        Ok(Self::new_with_partition(sequence, |sequence, shift| {
+            #[cfg(not(feature = "parallel"))]
+            stable_partition_of_4(sequence, shift);
+
+            #[cfg(feature = "parallel")]
+            stable_partition_of_4_parallel(sequence, shift, n_threads);
+        }))
+    }
+
+    /// Builds the wavelet tree of the `sequence` as [`QWaveletTree::new`] does,
+    /// by using `partition` to stably partition the sequence by the two bits
+    /// obtained by shifting `shift` bits to the right after each level but the last one.
+    pub(crate) fn new_with_partition<F>(sequence: &mut [T], mut partition: F) -> Self
+    where
+        F: FnMut(&mut [T], usize),
+    {
+        let sigma = sequence.iter().copied().max().unwrap_or(T::zero());
+
+        #[cfg(feature = "parallel")]
+        let n_threads = n_threads();
+
+        let result = Self::from_levels(sequence.len(), sigma, |level, shift| {
+            if level > 0 {
+                // Partition by the two bits of the previous level
+                partition(sequence, shift + 2);
+            }
+
+            #[cfg(not(feature = "parallel"))]
+            let qv = {
+                let mut cur_qv = QVectorBuilder::with_capacity(sequence.len());
+                for &symbol in sequence.iter() {
+                    let two_bits: u8 = (symbol >> shift).as_() & 3; // take the last 2 bits
+                    cur_qv.push(two_bits);
+                }
+                cur_qv.build()
+            };
+
+            #[cfg(feature = "parallel")]
+            let qv = build_level_parallel(sequence, shift, n_threads);
+
+            Ok::<_, std::convert::Infallible>(qv)
+        });
+
+        match result {
+            Ok(qwt) => qwt,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Builds the wavelet tree of a sequence of length `n` whose largest symbol is `sigma`.
+    /// The quad vector of each level is computed by `next_level(level, shift)`,
+    /// where `shift` is the number of bits to shift the symbols to the right to obtain
+    /// the two bits of the level. Levels are computed in order, so the sequence can
+    /// be partitioned by the previous level when computing the next one.
+    ///
+    /// Returns the first error of `next_level`, if any.
+    pub(crate) fn from_levels<F, E>(n: usize, sigma: T, mut next_level: F) -> Result<Self, E>
+    where
+        F: FnMut(usize, usize) -> Result<QVector, E>,
+    {
+        if n == 0 {
+            return Ok(Self {
                 n: 0,
                 n_levels: 0,
                 sigma: T::zero(),
-                qvs: vec![RS::default()],
+                qvs: vec![WTLevel::Quad(RS::default())],
                 prefetch_support: None,
-            };
+            });
         }
-        let sigma = *sequence.iter().max().unwrap();
         let log_sigma = msb(sigma) + 1; // Note that sigma equals the largest symbol, so it's already "alphabet_size - 1"
-        let n_levels = ((log_sigma + 1) / 2) as usize; // TODO: if log_sigma is odd, the FIRST level should be a binary vector!
+        let n_levels = ((log_sigma + 1) / 2) as usize; // If log_sigma is odd, the first level is a binary vector
 
         let mut prefetch_support = Vec::with_capacity(n_levels); // used only if WITH_PREFETCH_SUPPORT
 
-        let mut qvs = Vec::<RS>::with_capacity(n_levels);
-
-        let mut shift = 2 * (n_levels - 1);
+        let mut qvs = Vec::<WTLevel<RS>>::with_capacity(n_levels);
 
-        for _level in 0..n_levels {
-            let mut cur_qv = QVectorBuilder::with_capacity(sequence.len());
-            for &symbol in sequence.iter() {
-                let two_bits: u8 = (symbol >> shift).as_() & 3; // take the last 2 bits
-                cur_qv.push(two_bits);
-            }
-
-            let qv = cur_qv.build();
-
-            if WITH_PREFETCH_SUPPORT {
-                let pfs = PrefetchSupport::new(&qv, 11); // 11 -> sample_rate = 2048
-                prefetch_support.push(pfs);
-            }
-            qvs.push(RS::from(qv));
+        for level in 0..n_levels {
+            let shift = 2 * (n_levels - 1 - level);
+            let qv = next_level(level, shift)?;
+            debug_assert_eq!(qv.len(), n);
 
-            stable_partition_of_4(sequence, shift);
-
-            if shift >= 2 {
-                shift -= 2;
+            if level == 0 && log_sigma % 2 == 1 {
+                // The highest of the two bits is always 0 at the first level.
+                // A binary level is never prefetched, so it has no prefetch support.
+                qvs.push(WTLevel::new_binary(qv.iter()));
+            } else {
+                if WITH_PREFETCH_SUPPORT {
+                    let pfs = PrefetchSupport::new(&qv, 11); // 11 -> sample_rate = 2048
+                    prefetch_support.push(pfs);
+                }
+                qvs.push(WTLevel::Quad(RS::from(qv)));
             }
         }
 
         qvs.shrink_to_fit();
 
-        Self {
-            n: sequence.len(),
+        Ok(Self {
+            n,
             n_levels,
             sigma,
             qvs,
@@ -174,7 +366,7 @@ where
             } else {
                 None
             },
-        }
+        })
     }
 
     /// Returns the length of the indexed sequence.
@@ -186,7 +378,7 @@ where
     ///
     /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
     ///
-    /// let qwt = QWT256::from(data);
+    /// let qwt = QWT256::try_from(data).unwrap();
     ///
     /// assert_eq!(qwt.len(), 8);
     /// ```
@@ -207,7 +399,7 @@ where
     ///
     /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
     ///
-    /// let qwt = QWT256::from(data);
+    /// let qwt = QWT256::try_from(data).unwrap();
     ///
     /// assert_eq!(qwt.sigma(), Some(5));
     /// ```
@@ -245,7 +437,7 @@ where
     ///
     /// let data = vec![1u8, 0, 1, 0, 255, 4, 5, 3];
     ///
-    /// let qwt = QWT256::from(data);
+    /// let qwt = QWT256::try_from(data).unwrap();
     ///
     /// assert_eq!(qwt.n_levels(), 4);
     /// ```
@@ -263,7 +455,7 @@ where
     ///
     /// let data: Vec<u8> = (0..10u8).into_iter().cycle().take(100).collect();
     ///
-    /// let qwt = QWT256::from(data.clone());
+    /// let qwt = QWT256::try_from(data.clone()).unwrap();
     ///
     /// assert_eq!(qwt.iter().collect::<Vec<_>>(), data);
     ///
@@ -282,19 +474,22 @@ where
     }
 
     #[inline]
-    unsafe fn rank_prefetch_superblocks_unchecked(&self, symbol: T, i: usize) -> usize {
+    unsafe fn rank_prefetch_superblocks_unchecked(&self, symbol: T, range: Range<usize>) -> usize {
         if !WITH_PREFETCH_SUPPORT {
             return 0;
         }
 
         if let Some(ref prefetch_support) = self.prefetch_support {
             let mut shift: i64 = (2 * (self.n_levels - 1)) as i64;
-            let mut range = 0..i;
+            let mut range = range;
 
             self.qvs[0].prefetch_data(range.end);
             self.qvs[0].prefetch_info(range.start);
             self.qvs[0].prefetch_info(range.end);
 
+            // A binary first level has no prefetch support
+            let n_binary_levels = self.n_levels - prefetch_support.len();
+
             #[allow(clippy::needless_range_loop)]
             for level in 0..self.n_levels - 1 {
                 let two_bits: u8 = (symbol >> shift as usize).as_() & 3;
@@ -302,9 +497,18 @@ where
                 // SAFETY: Here we are sure that two_bits is a symbol in [0..3]
                 let offset = self.qvs[level].occs_smaller_unchecked(two_bits);
 
-                let rank_start =
-                    prefetch_support[level].approx_rank_unchecked(two_bits, range.start);
-                let rank_end = prefetch_support[level].approx_rank_unchecked(two_bits, range.end);
+                let (rank_start, rank_end) = if level < n_binary_levels {
+                    (
+                        self.qvs[level].rank_unchecked(two_bits, range.start),
+                        self.qvs[level].rank_unchecked(two_bits, range.end),
+                    )
+                } else {
+                    let pfs = &prefetch_support[level - n_binary_levels];
+                    (
+                        pfs.approx_rank_unchecked(two_bits, range.start),
+                        pfs.approx_rank_unchecked(two_bits, range.end),
+                    )
+                };
 
                 range = (rank_start + offset)..(rank_end + offset);
                 self.qvs[level + 1].prefetch_info(range.start);
@@ -378,7 +582,7 @@ where
     ///
     /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
     ///
-    /// let qwt = QWT256::from(data);
+    /// let qwt = QWT256::try_from(data).unwrap();
     ///
     /// assert_eq!(qwt.rank_prefetch(1, 2), Some(1));
     /// assert_eq!(qwt.rank_prefetch(3, 8), Some(1));
@@ -414,7 +618,7 @@ where
     ///
     /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
     ///
-    /// let qwt = QWT256::from(data);
+    /// let qwt = QWT256::try_from(data).unwrap();
     ///
     /// unsafe {
     ///     assert_eq!(qwt.rank_prefetch_unchecked(1, 2), 1);
@@ -423,11 +627,24 @@ where
     #[must_use]
     #[inline(always)]
     pub unsafe fn rank_prefetch_unchecked(&self, symbol: T, i: usize) -> usize {
+        self.prefetch_rank_unchecked(symbol, 0..i);
+
+        self.rank_unchecked(symbol, i)
+    }
+
+    /// Estimates the positions accessed by the `rank` queries of `symbol` at
+    /// both ends of `range` and prefetches these data.
+    #[inline(always)]
+    unsafe fn prefetch_rank_unchecked(&self, symbol: T, range: Range<usize>) {
+        if self.n_levels == 0 {
+            return;
+        }
+
         if WITH_PREFETCH_SUPPORT {
-            let _ = self.rank_prefetch_superblocks_unchecked(symbol, i);
+            let _ = self.rank_prefetch_superblocks_unchecked(symbol, range.clone());
         }
 
-        let mut range = 0..i;
+        let mut range = range;
         let mut shift: i64 = (2 * (self.n_levels - 1)) as i64;
 
         const BLOCK_SIZE: usize = 256; // TODO: fix me!
@@ -492,217 +709,1236 @@ where
 
             shift -= 2;
         }
-        self.rank_unchecked(symbol, i)
     }
-}
 
-impl<T, RS, const WITH_PREFETCH_SUPPORT: bool> RankUnsigned
-    for QWaveletTree<T, RS, WITH_PREFETCH_SUPPORT>
-where
-    T: WTIndexable,
-    u8: AsPrimitive<T>,
-    RS: RSforWT,
-{
-    /// Returns the rank of `symbol` up to position `i` **excluded**.
+    /// Returns the range `rank(symbol, range.start)..rank(symbol, range.end)`.
     ///
-    /// `None` is returned if `i` is out of bound or if `symbol` is not valid
+    /// This is a step of the backward search of an FM-index. The two `rank` queries
+    /// are solved with a single traversal of the levels, which shares the offsets
+    /// of the levels and the position of the beginning of the path of `symbol`.
+    /// Thus, it performs three `rank` operations per level instead of the four of two
+    /// separate `rank` queries. The two ranks at the ends of `range` are still
+    /// computed, so the saving is about a quarter of the work, not a half.
+    ///
+    /// `None` is returned if `range` is out of bound or if `symbol` is not valid
     /// (i.e., it is greater than or equal to the alphabet size).
     ///
     /// # Examples
     ///
     /// ```
-    /// use qwt::{QWT256, RankUnsigned};
+    /// use qwt::QWT256;
     ///
     /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
     ///
-    /// let qwt = QWT256::from(data);
+    /// let qwt = QWT256::try_from(data).unwrap();
     ///
-    /// assert_eq!(qwt.rank(1, 2), Some(1));
-    /// assert_eq!(qwt.rank(3, 8), Some(1));
-    /// assert_eq!(qwt.rank(1, 0), Some(0));
-    /// assert_eq!(qwt.rank(1, 9), None);  // Too large position
-    /// assert_eq!(qwt.rank(6, 1), None);  // Too large symbol
+    /// assert_eq!(qwt.backward_search_step(1..8, 0), Some(0..2));
+    /// assert_eq!(qwt.backward_search_step(3..3, 1), Some(2..2));
+    /// assert_eq!(qwt.backward_search_step(0..9, 1), None);  // Too large position
+    /// assert_eq!(qwt.backward_search_step(0..8, 6), None);  // Too large symbol
     /// ```
-    #[must_use]
     #[inline(always)]
-    fn rank(&self, symbol: Self::Item, i: usize) -> Option<usize> {
-        if i > self.n || symbol > self.sigma {
+    #[must_use]
+    pub fn backward_search_step(&self, range: Range<usize>, symbol: T) -> Option<Range<usize>> {
+        if range.start > range.end || range.end > self.n || symbol > self.sigma {
             return None;
         }
 
-        // SAFETY: Check above guarantees we are not out of bound
-        Some(unsafe { self.rank_unchecked(symbol, i) })
+        // SAFETY: Check the above guarantees we are not out of bound
+        Some(unsafe { self.backward_search_step_unchecked(range, symbol) })
     }
 
-    /// Returns rank of `symbol` up to position `i` **excluded**.
+    /// Returns the range `rank(symbol, range.start)..rank(symbol, range.end)`.
     ///
     /// # Safety
-    ///
-    /// Calling this method with a position `i` larger than the size of the sequence
-    /// or with an invalid symbol is undefined behavior.
-    ///
-    /// Users must ensure that the position `i` is within the bounds of the sequence
-    /// and that the symbol is valid.
+    /// Calling this method with a `range` out of bound, with `range.start > range.end`,
+    /// or with invalid symbol is undefined behavior.
     ///
     /// # Examples
-    ///
     /// ```
-    /// use qwt::{QWT256, RankUnsigned};
+    /// use qwt::QWT256;
     ///
     /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
     ///
-    /// let qwt = QWT256::from(data);
+    /// let qwt = QWT256::try_from(data).unwrap();
     ///
     /// unsafe {
-    ///     assert_eq!(qwt.rank_unchecked(1, 2), 1);
+    ///     assert_eq!(qwt.backward_search_step_unchecked(1..8, 0), 0..2);
     /// }
     /// ```
     #[must_use]
     #[inline(always)]
-    unsafe fn rank_unchecked(&self, symbol: Self::Item, i: usize) -> usize {
+    pub unsafe fn backward_search_step_unchecked(
+        &self,
+        range: Range<usize>,
+        symbol: T,
+    ) -> Range<usize> {
+        if self.n_levels == 0 {
+            return 0..0;
+        }
+
         let mut shift: i64 = (2 * (self.n_levels - 1)) as i64;
-        let mut cur_i = i;
         let mut cur_p = 0;
+        let mut cur_l = range.start;
+        let mut cur_r = range.end;
 
         for level in 0..self.n_levels - 1 {
             let two_bits: u8 = (symbol >> shift as usize).as_() & 3;
 
-            // Safety: Here we are sure that two_bits is a symbol in [0..3]
-            let offset = unsafe { self.qvs[level].occs_smaller_unchecked(two_bits) };
-            cur_p = self.qvs[level].rank_unchecked(two_bits, cur_p) + offset;
-            cur_i = self.qvs[level].rank_unchecked(two_bits, cur_i) + offset;
+            // SAFETY: Here we are sure that two_bits is a symbol in [0..3]
+            let qv = self.qvs.get_unchecked(level);
+            let offset = qv.occs_smaller_unchecked(two_bits);
+            cur_p = qv.rank_unchecked(two_bits, cur_p) + offset;
+            cur_l = qv.rank_unchecked(two_bits, cur_l) + offset;
+            cur_r = qv.rank_unchecked(two_bits, cur_r) + offset;
 
             shift -= 2;
         }
 
         let two_bits: u8 = (symbol >> shift as usize).as_() & 3;
 
-        cur_i = self.qvs[self.n_levels - 1].rank_unchecked(two_bits, cur_i);
-        cur_p = self.qvs[self.n_levels - 1].rank_unchecked(two_bits, cur_p);
+        let qv = self.qvs.get_unchecked(self.n_levels - 1);
+        cur_p = qv.rank_unchecked(two_bits, cur_p);
+        cur_l = qv.rank_unchecked(two_bits, cur_l);
+        cur_r = qv.rank_unchecked(two_bits, cur_r);
 
-        cur_i - cur_p
+        (cur_l - cur_p)..(cur_r - cur_p)
     }
-}
-
-impl<T, RS, const WITH_PREFETCH_SUPPORT: bool> AccessUnsigned
-    for QWaveletTree<T, RS, WITH_PREFETCH_SUPPORT>
-where
-    T: WTIndexable,
-    u8: AsPrimitive<T>,
-    RS: RSforWT,
-{
-    type Item = T;
 
-    /// Returns the `i`-th symbol of the indexed sequence.
+    /// Returns the range `rank(symbol, range.start)..rank(symbol, range.end)`.
     ///
-    /// `None` is returned if `i` is out of bound.
+    /// Differently from `backward_search_step`, it first estimates the positions
+    /// accessed at both ends of `range` and prefetches these data, as done by `rank_prefetch`.
+    ///
+    /// `None` is returned if `range` is out of bound or if `symbol` is not valid
+    /// (i.e., it is greater than or equal to the alphabet size).
     ///
     /// # Examples
     ///
     /// ```
-    /// use qwt::{QWT256, AccessUnsigned};
+    /// use qwt::QWT256Pfs;
     ///
     /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
     ///
-    /// let qwt = QWT256::from(data);
+    /// let qwt = QWT256Pfs::try_from(data).unwrap();
     ///
-    /// assert_eq!(qwt.get(2), Some(1));
-    /// assert_eq!(qwt.get(3), Some(0));
-    /// assert_eq!(qwt.get(8), None);
+    /// assert_eq!(qwt.backward_search_step_prefetch(1..8, 0), Some(0..2));
+    /// assert_eq!(qwt.backward_search_step_prefetch(0..9, 1), None);  // Too large position
     /// ```
-    #[must_use]
     #[inline(always)]
-    fn get(&self, i: usize) -> Option<Self::Item> {
-        if i >= self.n {
+    #[must_use]
+    pub fn backward_search_step_prefetch(
+        &self,
+        range: Range<usize>,
+        symbol: T,
+    ) -> Option<Range<usize>> {
+        if range.start > range.end || range.end > self.n || symbol > self.sigma {
             return None;
         }
-        // SAFETY: check before guarantees we are not out of bound
-        Some(unsafe { self.get_unchecked(i) })
+
+        // SAFETY: Check the above guarantees we are not out of bound
+        unsafe {
+            self.prefetch_rank_unchecked(symbol, range.clone());
+            Some(self.backward_search_step_unchecked(range, symbol))
+        }
     }
 
-    /// Returns the `i`-th symbol of the indexed sequence.
+    /// Accesses the symbols at `positions` and writes them in `results`.
     ///
-    /// # Safety
+    /// The queries are processed in groups of `BATCH_SIZE` that traverse the levels
+    /// in lock-step. At each level, we first prefetch the data needed by all the
+    /// queries of the group, so that their cache misses overlap. This gives a much
+    /// higher throughput than answering the queries one by one.
     ///
-    /// Calling this method with an out-of-bounds index is undefined behavior.
+    /// Returns `false` if any position is out of bound. In this case, `results` is not modified.
     ///
-    /// Users must ensure that the index `i` is within the bounds of the sequence.
+    /// # Panics
+    /// Panics if `positions` and `results` have different lengths.
     ///
     /// # Examples
+    ///
     /// ```
-    /// use qwt::{QWT256, AccessUnsigned};
+    /// use qwt::QWT256;
     ///
     /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
     ///
-    /// let qwt = QWT256::from(data);
+    /// let qwt = QWT256::try_from(data).unwrap();
     ///
-    /// unsafe {
-    ///     assert_eq!(qwt.get_unchecked(2), 1);
-    ///     assert_eq!(qwt.get_unchecked(3), 0);
-    /// }
+    /// let mut results = vec![0; 3];
+    /// assert!(qwt.get_batch(&[7, 0, 5], &mut results));
+    /// assert_eq!(results, vec![3, 1, 4]);
+    ///
+    /// assert!(!qwt.get_batch(&[7, 8, 5], &mut results)); // Too large position
     /// ```
     #[must_use]
-    #[inline(always)]
-    unsafe fn get_unchecked(&self, i: usize) -> Self::Item {
-        let mut result = T::zero();
+    pub fn get_batch(&self, positions: &[usize], results: &mut [T]) -> bool {
+        assert_eq!(positions.len(), results.len(), "Lengths must be equal.");
 
-        let mut cur_i = i;
-        for level in 0..self.n_levels - 1 {
-            // The last rank can be saved. The improvement is just ~3%. Indeed, most of the cost is for the cache miss for data access that we pay anyway
+        if positions.iter().any(|&i| i >= self.n) {
+            return false;
+        }
 
-            self.qvs[level].prefetch_info(cur_i); // Compiler is not able to infer that later it is needed for the rank query. Access is roughly 33% slower for large files without this.
-            let symbol = self.qvs[level].get_unchecked(cur_i);
-            result = (result << 2) | symbol.as_();
+        // SAFETY: Check the above guarantees we are not out of bound
+        unsafe { self.get_batch_unchecked(positions, results) };
+        true
+    }
 
-            // SAFETY: Here we are sure that symbol is in [0..3]
-            let offset = unsafe { self.qvs[level].occs_smaller_unchecked(symbol) };
-            cur_i = self.qvs[level].rank_unchecked(symbol, cur_i) + offset;
-        }
+    /// Accesses the symbols at `positions` and writes them in `results`.
+    ///
+    /// If the slices have different lengths, only the first `min(positions.len(), results.len())`
+    /// positions are accessed and the remaining entries of `results` are not modified.
+    ///
+    /// # Safety
+    /// Calling this method with a position larger than or equal to the size of the
+    /// sequence is undefined behavior.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::QWT256;
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let qwt = QWT256::try_from(data).unwrap();
+    ///
+    /// let mut results = vec![0; 2];
+    /// unsafe {
+    ///     qwt.get_batch_unchecked(&[2, 3], &mut results);
+    /// }
+    /// assert_eq!(results, vec![1, 0]);
+    /// ```
+    pub unsafe fn get_batch_unchecked(&self, positions: &[usize], results: &mut [T]) {
+        let len = positions.len().min(results.len());
+        let (positions, results) = (&positions[..len], &mut results[..len]);
+
+        for (positions, results) in positions
+            .chunks(BATCH_SIZE)
+            .zip(results.chunks_mut(BATCH_SIZE))
+        {
+            let mut cur_i = [0; BATCH_SIZE];
+            let cur_i = &mut cur_i[..positions.len()];
+            cur_i.copy_from_slice(positions);
+            results.fill(T::zero());
+
+            for level in 0..self.n_levels {
+                let qv = self.qvs.get_unchecked(level);
+
+                for &i in cur_i.iter() {
+                    qv.prefetch_info(i);
+                    qv.prefetch_data(i);
+                }
 
-        let symbol = self.qvs[self.n_levels - 1].get_unchecked(cur_i);
-        (result << 2) | symbol.as_()
+                for (i, result) in cur_i.iter_mut().zip(results.iter_mut()) {
+                    let symbol = qv.get_unchecked(*i);
+                    *result = (*result << 2) | symbol.as_();
+
+                    if level + 1 < self.n_levels {
+                        // SAFETY: Here we are sure that symbol is in [0..3]
+                        let offset = qv.occs_smaller_unchecked(symbol);
+                        *i = qv.rank_unchecked(symbol, *i) + offset;
+                    }
+                }
+            }
+        }
     }
-}
 
-impl<T, RS, const WITH_PREFETCH_SUPPORT: bool> SelectUnsigned
-    for QWaveletTree<T, RS, WITH_PREFETCH_SUPPORT>
-where
-    T: WTIndexable,
-    u8: AsPrimitive<T>,
-    RS: RSforWT,
-{
-    /// Returns the position of the `i+1`-th occurrence of symbol `symbol`.
+    /// Computes the rank of each pair `(symbol, i)` in `queries` and writes them in `results`.
     ///
-    /// `None` is returned if the is no (i+1)th such occurrence for the symbol
-    /// or if `symbol` is not valid (i.e., it is greater than or equal to the alphabet size).
+    /// The queries are processed in groups of `BATCH_SIZE` that traverse the levels
+    /// in lock-step, prefetching the data of the whole group at each level.
+    ///
+    /// Returns `false` if any position is out of bound or any symbol is not valid.
+    /// In this case, `results` is not modified.
+    ///
+    /// # Panics
+    /// Panics if `queries` and `results` have different lengths.
     ///
     /// # Examples
+    ///
     /// ```
-    /// use qwt::{QWT256, SelectUnsigned};
+    /// use qwt::QWT256;
     ///
     /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
     ///
-    /// let qwt = QWT256::from(data);
+    /// let qwt = QWT256::try_from(data).unwrap();
     ///
-    /// assert_eq!(qwt.select(1, 1), Some(2));
-    /// assert_eq!(qwt.select(0, 1), Some(3));
-    /// assert_eq!(qwt.select(0, 2), None);
-    /// assert_eq!(qwt.select(1, 0), Some(0));
-    /// assert_eq!(qwt.select(5, 0), Some(6));
-    /// assert_eq!(qwt.select(6, 1), None);
-    /// ```    
+    /// let mut results = vec![0; 3];
+    /// assert!(qwt.rank_batch(&[(1, 2), (3, 8), (0, 8)], &mut results));
+    /// assert_eq!(results, vec![1, 1, 2]);
+    ///
+    /// assert!(!qwt.rank_batch(&[(1, 2), (6, 1), (0, 8)], &mut results)); // Too large symbol
+    /// ```
     #[must_use]
-    #[inline(always)]
-    fn select(&self, symbol: Self::Item, i: usize) -> Option<usize> {
-        if symbol > self.sigma {
-            return None;
-        }
+    pub fn rank_batch(&self, queries: &[(T, usize)], results: &mut [usize]) -> bool {
+        assert_eq!(queries.len(), results.len(), "Lengths must be equal.");
 
-        let mut path_off = Vec::with_capacity(self.n_levels);
-        let mut rank_path_off = Vec::with_capacity(self.n_levels);
+        if queries
+            .iter()
+            .any(|&(symbol, i)| i > self.n || symbol > self.sigma)
+        {
+            return false;
+        }
 
-        let mut b = 0;
-        let mut shift: i64 = 2 * (self.n_levels - 1) as i64;
+        // SAFETY: Check the above guarantees we are not out of bound
+        unsafe { self.rank_batch_unchecked(queries, results) };
+        true
+    }
+
+    /// Computes the rank of each pair `(symbol, i)` in `queries` and writes them in `results`.
+    ///
+    /// If the slices have different lengths, only the first `min(queries.len(), results.len())`
+    /// queries are answered and the remaining entries of `results` are not modified.
+    ///
+    /// # Safety
+    /// Calling this method with a position larger than the size of the sequence or
+    /// with an invalid symbol is undefined behavior.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::QWT256;
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let qwt = QWT256::try_from(data).unwrap();
+    ///
+    /// let mut results = vec![0; 2];
+    /// unsafe {
+    ///     qwt.rank_batch_unchecked(&[(1, 3), (0, 4)], &mut results);
+    /// }
+    /// assert_eq!(results, vec![2, 2]);
+    /// ```
+    pub unsafe fn rank_batch_unchecked(&self, queries: &[(T, usize)], results: &mut [usize]) {
+        let len = queries.len().min(results.len());
+        let (queries, results) = (&queries[..len], &mut results[..len]);
+
+        if self.n_levels == 0 {
+            results.fill(0);
+            return;
+        }
+
+        for (queries, results) in queries
+            .chunks(BATCH_SIZE)
+            .zip(results.chunks_mut(BATCH_SIZE))
+        {
+            let mut cur_p = [0; BATCH_SIZE];
+            let cur_p = &mut cur_p[..queries.len()];
+            for ((_, i), result) in queries.iter().zip(results.iter_mut()) {
+                *result = *i; // results hold the current position of each query
+            }
+
+            let mut shift = 2 * (self.n_levels - 1);
+            for level in 0..self.n_levels {
+                let qv = self.qvs.get_unchecked(level);
+
+                for (&p, &i) in cur_p.iter().zip(results.iter()) {
+                    qv.prefetch_info(p);
+                    qv.prefetch_info(i);
+                    qv.prefetch_data(i);
+                }
+
+                for ((&(symbol, _), p), i) in
+                    queries.iter().zip(cur_p.iter_mut()).zip(results.iter_mut())
+                {
+                    let two_bits: u8 = (symbol >> shift).as_() & 3;
+
+                    // SAFETY: Here we are sure that two_bits is a symbol in [0..3]
+                    let offset = if level + 1 < self.n_levels {
+                        qv.occs_smaller_unchecked(two_bits)
+                    } else {
+                        0
+                    };
+                    *p = qv.rank_unchecked(two_bits, *p) + offset;
+                    *i = qv.rank_unchecked(two_bits, *i) + offset;
+                }
+
+                shift = shift.saturating_sub(2);
+            }
+
+            for (i, p) in results.iter_mut().zip(cur_p.iter()) {
+                *i -= *p;
+            }
+        }
+    }
+
+    /// Computes the position of the `i+1`-th occurrence of `symbol` for each pair
+    /// `(symbol, i)` in `queries` and writes them in `results`.
+    ///
+    /// The queries are processed in groups of `BATCH_SIZE` that traverse the levels
+    /// in lock-step, first top-down and then bottom-up. At each level of the bottom-up
+    /// traversal, we first prefetch the counters scanned by the `select` of all the queries
+    /// of the group.
+    ///
+    /// The result of a query is `None` if there is no such occurrence or if the symbol is not valid.
+    ///
+    /// # Panics
+    /// Panics if `queries` and `results` have different lengths.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::QWT256;
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let qwt = QWT256::try_from(data).unwrap();
+    ///
+    /// let mut results = vec![None; 4];
+    /// qwt.select_batch(&[(1, 1), (0, 1), (0, 2), (6, 0)], &mut results);
+    /// assert_eq!(results, vec![Some(2), Some(3), None, None]);
+    /// ```
+    pub fn select_batch(&self, queries: &[(T, usize)], results: &mut [Option<usize>]) {
+        assert_eq!(queries.len(), results.len(), "Lengths must be equal.");
+
+        let n_levels = self.n_levels;
+        let mut path_off = vec![0; BATCH_SIZE * n_levels];
+        let mut rank_path_off = vec![0; BATCH_SIZE * n_levels];
+
+        for (queries, results) in queries
+            .chunks(BATCH_SIZE)
+            .zip(results.chunks_mut(BATCH_SIZE))
+        {
+            for (&(symbol, _), result) in queries.iter().zip(results.iter_mut()) {
+                *result = if symbol > self.sigma || self.is_empty() {
+                    None
+                } else {
+                    Some(0) // Valid query, results hold the current position of each query
+                };
+            }
+
+            // Top-down traversal to find the beginning of the path of each symbol
+            let mut b = [0; BATCH_SIZE];
+            for level in 0..n_levels {
+                // SAFETY: level is smaller than n_levels
+                let qv = unsafe { self.qvs.get_unchecked(level) };
+                let shift = 2 * (n_levels - 1 - level);
+
+                for (j, result) in results.iter().enumerate() {
+                    if result.is_some() {
+                        qv.prefetch_info(b[j]);
+                    }
+                }
+
+                for (j, (&(symbol, _), result)) in queries.iter().zip(results.iter()).enumerate() {
+                    if result.is_none() {
+                        continue;
+                    }
+                    let two_bits: u8 = (symbol >> shift).as_() & 3;
+
+                    path_off[level * BATCH_SIZE + j] = b[j];
+                    // SAFETY: b[j] is never out of bound and two_bits is in [0..3]
+                    let rank_b = unsafe { qv.rank_unchecked(two_bits, b[j]) };
+                    b[j] = rank_b + unsafe { qv.occs_smaller_unchecked(two_bits) };
+                    rank_path_off[level * BATCH_SIZE + j] = rank_b;
+                }
+            }
+
+            for ((_, i), result) in queries.iter().zip(results.iter_mut()) {
+                if result.is_some() {
+                    *result = Some(*i);
+                }
+            }
+
+            // Bottom-up traversal to find the positions of the occurrences
+            for level in (0..n_levels).rev() {
+                // SAFETY: level is smaller than n_levels
+                let qv = unsafe { self.qvs.get_unchecked(level) };
+                let shift = 2 * (n_levels - 1 - level);
+
+                for (j, (&(symbol, _), result)) in queries.iter().zip(results.iter()).enumerate() {
+                    if let Some(pos) = *result {
+                        let two_bits: u8 = (symbol >> shift).as_() & 3;
+                        qv.prefetch_select(two_bits, rank_path_off[level * BATCH_SIZE + j] + pos);
+                    }
+                }
+
+                for (j, (&(symbol, _), result)) in
+                    queries.iter().zip(results.iter_mut()).enumerate()
+                {
+                    let Some(pos) = *result else {
+                        continue;
+                    };
+                    let two_bits: u8 = (symbol >> shift).as_() & 3;
+
+                    let b = path_off[level * BATCH_SIZE + j];
+                    let rank_b = rank_path_off[level * BATCH_SIZE + j];
+                    *result = qv.select(two_bits, rank_b + pos).map(|p| p - b);
+                }
+            }
+        }
+    }
+
+    /// Returns the `k`-th smallest symbol (0-indexed) in the range of positions
+    /// `range`, i.e., the symbol that would be at position `k` if
+    /// `S[range.start..range.end]` were sorted.
+    ///
+    /// `None` is returned if `range` is empty or out of bound, or if `k` is not
+    /// smaller than the length of the range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::QWT256;
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let qwt = QWT256::try_from(data).unwrap();
+    ///
+    /// assert_eq!(qwt.quantile(0..8, 0), Some(0));
+    /// assert_eq!(qwt.quantile(0..8, 7), Some(5));
+    /// assert_eq!(qwt.quantile(2..6, 2), Some(2));
+    /// assert_eq!(qwt.quantile(2..6, 4), None); // Too large k
+    /// assert_eq!(qwt.quantile(2..9, 0), None); // Too large range
+    /// ```
+    #[must_use]
+    pub fn quantile(&self, range: Range<usize>, k: usize) -> Option<T> {
+        if range.start >= range.end || range.end > self.n || k >= range.end - range.start {
+            return None;
+        }
+
+        // SAFETY: checks above guarantee we are not out of bound
+        Some(unsafe { self.quantile_unchecked(range, k) })
+    }
+
+    /// Returns the `k`-th smallest symbol (0-indexed) in the range of positions
+    /// `range`.
+    ///
+    /// # Safety
+    /// Calling this method with an empty or out-of-bound `range` or with
+    /// `k` not smaller than the length of the range is undefined behavior.
+    #[must_use]
+    pub unsafe fn quantile_unchecked(&self, range: Range<usize>, k: usize) -> T {
+        self.quantile_from_level(0, range, k, T::zero())
+    }
+
+    /// Returns the `k`-th smallest symbol below the node at level `level` whose
+    /// range of positions is `range`. The symbols below this node share the
+    /// two-bit digits in `prefix`.
+    ///
+    /// # Safety
+    /// Calling this method with a `range` that is not valid for `level` or
+    /// with `k` not smaller than the length of the range is undefined behavior.
+    #[inline]
+    unsafe fn quantile_from_level(
+        &self,
+        level: usize,
+        range: Range<usize>,
+        k: usize,
+        prefix: T,
+    ) -> T {
+        let mut range = range;
+        let mut k = k;
+        let mut result = prefix;
+
+        for level in level..self.n_levels {
+            // Find the child of the current node that contains the k-th smallest symbol.
+            // Children are visited in increasing order of symbol.
+            let mut two_bits = 0;
+            let mut child_range = self.child_range(level, two_bits, &range);
+            while child_range.end - child_range.start <= k {
+                k -= child_range.end - child_range.start;
+                two_bits += 1;
+                child_range = self.child_range(level, two_bits, &range);
+            }
+
+            result = (result << 2) | two_bits.as_();
+            range = child_range;
+        }
+
+        result
+    }
+
+    /// Returns the median of the symbols in the range of positions `range`.
+    ///
+    /// If the range has an even length, the smaller of the two medians is returned.
+    /// `None` is returned if `range` is empty or out of bound.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::QWT256;
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let qwt = QWT256::try_from(data).unwrap();
+    ///
+    /// assert_eq!(qwt.range_median(0..8), Some(1));
+    /// assert_eq!(qwt.range_median(4..7), Some(4));
+    /// assert_eq!(qwt.range_median(3..3), None);
+    /// ```
+    #[must_use]
+    pub fn range_median(&self, range: Range<usize>) -> Option<T> {
+        let k = range.end.saturating_sub(range.start).saturating_sub(1) / 2;
+        self.quantile(range, k)
+    }
+
+    /// Returns the number of positions in the range `pos_range` whose symbol
+    /// is in the range of values `value_range`, i.e., the number of positions
+    /// `i` in `[l, r)` such that `a <= S[i] <= b`.
+    ///
+    /// `None` is returned if `pos_range` is out of bound.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::QWT256;
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let qwt = QWT256::try_from(data).unwrap();
+    ///
+    /// assert_eq!(qwt.range_count(0..8, 1..=3), Some(4));
+    /// assert_eq!(qwt.range_count(2..6, 0..=1), Some(2));
+    /// assert_eq!(qwt.range_count(0..8, 4..=255), Some(2));
+    /// assert_eq!(qwt.range_count(0..8, 6..=255), Some(0));
+    /// assert_eq!(qwt.range_count(0..9, 0..=5), None); // Too large range
+    /// ```
+    #[must_use]
+    pub fn range_count(
+        &self,
+        pos_range: Range<usize>,
+        value_range: RangeInclusive<T>,
+    ) -> Option<usize> {
+        if pos_range.start > pos_range.end || pos_range.end > self.n {
+            return None;
+        }
+
+        let (&a, &b) = (value_range.start(), value_range.end());
+        if pos_range.is_empty() || a > b || a > self.sigma {
+            return Some(0);
+        }
+        let b = b.min(self.sigma);
+
+        // SAFETY: checks above guarantee that the range is valid and both a and b are at most sigma
+        let (smaller_a, _) = unsafe { self.range_rank_smaller_equal(pos_range.clone(), a) };
+        let (smaller_b, equal_b) = unsafe { self.range_rank_smaller_equal(pos_range, b) };
+
+        Some(smaller_b + equal_b - smaller_a)
+    }
+
+    /// Returns an iterator over the distinct symbols in the range of positions
+    /// `range` together with their number of occurrences in the range.
+    /// Symbols are reported in increasing order.
+    ///
+    /// The iterator is lazy and only descends the non-empty children of each level,
+    /// so that the cost is proportional to the number of reported symbols times
+    /// the number of levels.
+    ///
+    /// `None` is returned if `range` is out of bound.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::QWT256;
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let qwt = QWT256::try_from(data).unwrap();
+    ///
+    /// let distinct: Vec<_> = qwt.range_distinct(0..5).unwrap().collect();
+    /// assert_eq!(distinct, vec![(0, 2), (1, 2), (2, 1)]);
+    ///
+    /// assert_eq!(qwt.range_distinct(3..3).unwrap().next(), None);
+    /// assert!(qwt.range_distinct(0..9).is_none()); // Too large range
+    /// ```
+    pub fn range_distinct(
+        &self,
+        range: Range<usize>,
+    ) -> Option<QWTRangeDistinctIterator<'_, T, RS, WITH_PREFETCH_SUPPORT>> {
+        if range.start > range.end || range.end > self.n {
+            return None;
+        }
+
+        let mut stack = Vec::with_capacity(3 * self.n_levels + 1);
+        if !range.is_empty() {
+            stack.push((0, range, T::zero()));
+        }
+
+        Some(QWTRangeDistinctIterator { qwt: self, stack })
+    }
+
+    /// Returns the `k` most frequent symbols in the range of positions `range`
+    /// together with their number of occurrences in the range.
+    ///
+    /// Symbols are reported by decreasing frequency. Ties are broken by reporting
+    /// smaller symbols first. Fewer than `k` symbols are reported if the range
+    /// contains less than `k` distinct symbols.
+    ///
+    /// The query performs a best-first traversal of the levels: the node with the
+    /// largest range is expanded first, as its size is an upper bound on the
+    /// frequency of any symbol below it.
+    ///
+    /// `None` is returned if `range` is out of bound.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::QWT256;
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3, 1];
+    ///
+    /// let qwt = QWT256::try_from(data).unwrap();
+    ///
+    /// assert_eq!(qwt.range_top_k(0..9, 2), Some(vec![(1, 3), (0, 2)]));
+    /// assert_eq!(qwt.range_top_k(4..7, 5), Some(vec![(2, 1), (4, 1), (5, 1)]));
+    /// assert_eq!(qwt.range_top_k(0..10, 1), None); // Too large range
+    /// ```
+    #[must_use]
+    pub fn range_top_k(&self, range: Range<usize>, k: usize) -> Option<Vec<(T, usize)>> {
+        if range.start > range.end || range.end > self.n {
+            return None;
+        }
+
+        let mut result = Vec::with_capacity(k.min(range.len()));
+        if range.is_empty() || k == 0 {
+            return Some(result);
+        }
+
+        // Each entry is (range length, smallest symbol in the node, level, range start, range end).
+        // The symbol is reversed to pop smaller symbols first on ties.
+        let mut heap = BinaryHeap::new();
+        heap.push((range.len(), Reverse(T::zero()), 0, range.start, range.end));
+
+        while let Some((len, Reverse(symbol), level, start, end)) = heap.pop() {
+            if level == self.n_levels {
+                result.push((symbol, len));
+                if result.len() == k {
+                    break;
+                }
+                continue;
+            }
+
+            let shift = 2 * (self.n_levels - level - 1);
+            for two_bits in 0..4u8 {
+                // SAFETY: ranges in the heap are always valid for their level
+                let child_range = unsafe { self.child_range(level, two_bits, &(start..end)) };
+                if !child_range.is_empty() {
+                    let child_symbol = symbol | (two_bits.as_() << shift);
+                    heap.push((
+                        child_range.len(),
+                        Reverse(child_symbol),
+                        level + 1,
+                        child_range.start,
+                        child_range.end,
+                    ));
+                }
+            }
+        }
+
+        Some(result)
+    }
+
+    /// Returns the smallest symbol larger than or equal to `symbol` in the range
+    /// of positions `range`.
+    ///
+    /// `None` is returned if there is no such symbol or if `range` is out of bound.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::QWT256;
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let qwt = QWT256::try_from(data).unwrap();
+    ///
+    /// assert_eq!(qwt.range_next_value(0..8, 3), Some(3));
+    /// assert_eq!(qwt.range_next_value(0..5, 3), None);
+    /// assert_eq!(qwt.range_next_value(4..7, 3), Some(4));
+    /// assert_eq!(qwt.range_next_value(0..9, 0), None); // Too large range
+    /// ```
+    #[must_use]
+    pub fn range_next_value(&self, range: Range<usize>, symbol: T) -> Option<T> {
+        if range.start >= range.end || range.end > self.n || symbol > self.sigma {
+            return None;
+        }
+
+        // SAFETY: checks above guarantee that the range is valid
+        unsafe { self.range_next_value_from_level(0, range, symbol, T::zero()) }
+    }
+
+    /// Returns the largest symbol smaller than or equal to `symbol` in the range
+    /// of positions `range`.
+    ///
+    /// `None` is returned if there is no such symbol or if `range` is out of bound.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::QWT256;
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let qwt = QWT256::try_from(data).unwrap();
+    ///
+    /// assert_eq!(qwt.range_prev_value(0..8, 3), Some(3));
+    /// assert_eq!(qwt.range_prev_value(4..7, 3), Some(2));
+    /// assert_eq!(qwt.range_prev_value(5..7, 3), None);
+    /// assert_eq!(qwt.range_prev_value(0..8, 255), Some(5));
+    /// assert_eq!(qwt.range_prev_value(0..9, 0), None); // Too large range
+    /// ```
+    #[must_use]
+    pub fn range_prev_value(&self, range: Range<usize>, symbol: T) -> Option<T> {
+        if range.start >= range.end || range.end > self.n {
+            return None;
+        }
+
+        // SAFETY: checks above guarantee that the range is valid and the symbol is at most sigma
+        unsafe { self.range_prev_value_from_level(0, range, symbol.min(self.sigma), T::zero()) }
+    }
+
+    /// Solves `range_next_value` below the node at level `level` whose symbols
+    /// share the same digits `prefix` of `symbol`.
+    ///
+    /// We first follow the path of `symbol`. If there is no valid symbol there,
+    /// the answer is the smallest symbol below the first non-empty larger child.
+    #[inline]
+    unsafe fn range_next_value_from_level(
+        &self,
+        level: usize,
+        range: Range<usize>,
+        symbol: T,
+        prefix: T,
+    ) -> Option<T> {
+        if range.is_empty() {
+            return None;
+        }
+        if level == self.n_levels {
+            return Some(prefix);
+        }
+
+        let shift = 2 * (self.n_levels - level - 1);
+        let two_bits: u8 = (symbol >> shift).as_() & 3;
+
+        let child_range = self.child_range(level, two_bits, &range);
+        let child_prefix = (prefix << 2) | two_bits.as_();
+        if let Some(result) =
+            self.range_next_value_from_level(level + 1, child_range, symbol, child_prefix)
+        {
+            return Some(result);
+        }
+
+        for c in two_bits + 1..4 {
+            let child_range = self.child_range(level, c, &range);
+            if !child_range.is_empty() {
+                return Some(self.quantile_from_level(
+                    level + 1,
+                    child_range,
+                    0,
+                    (prefix << 2) | c.as_(),
+                ));
+            }
+        }
+
+        None
+    }
+
+    /// Solves `range_prev_value` below the node at level `level` whose symbols
+    /// share the same digits `prefix` of `symbol`.
+    ///
+    /// We first follow the path of `symbol`. If there is no valid symbol there,
+    /// the answer is the largest symbol below the first non-empty smaller child.
+    #[inline]
+    unsafe fn range_prev_value_from_level(
+        &self,
+        level: usize,
+        range: Range<usize>,
+        symbol: T,
+        prefix: T,
+    ) -> Option<T> {
+        if range.is_empty() {
+            return None;
+        }
+        if level == self.n_levels {
+            return Some(prefix);
+        }
+
+        let shift = 2 * (self.n_levels - level - 1);
+        let two_bits: u8 = (symbol >> shift).as_() & 3;
+
+        let child_range = self.child_range(level, two_bits, &range);
+        let child_prefix = (prefix << 2) | two_bits.as_();
+        if let Some(result) =
+            self.range_prev_value_from_level(level + 1, child_range, symbol, child_prefix)
+        {
+            return Some(result);
+        }
+
+        for c in (0..two_bits).rev() {
+            let child_range = self.child_range(level, c, &range);
+            if !child_range.is_empty() {
+                let k = child_range.len() - 1;
+                return Some(self.quantile_from_level(
+                    level + 1,
+                    child_range,
+                    k,
+                    (prefix << 2) | c.as_(),
+                ));
+            }
+        }
+
+        None
+    }
+
+    /// Returns the number of positions up to position `i` **excluded** whose
+    /// symbol is strictly smaller than `symbol`.
+    ///
+    /// The query is solved with a single top-down traversal of the levels.
+    /// A `symbol` larger than sigma is valid, and in this case the result is `i`.
+    ///
+    /// `None` is returned if `i` is out of bound.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::QWT256;
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let qwt = QWT256::try_from(data).unwrap();
+    ///
+    /// assert_eq!(qwt.rank_lt(2, 8), Some(4));
+    /// assert_eq!(qwt.rank_lt(1, 3), Some(1));
+    /// assert_eq!(qwt.rank_lt(0, 8), Some(0));
+    /// assert_eq!(qwt.rank_lt(6, 8), Some(8));
+    /// assert_eq!(qwt.rank_lt(1, 9), None);  // Too large position
+    /// ```
+    #[must_use]
+    pub fn rank_lt(&self, symbol: T, i: usize) -> Option<usize> {
+        if i > self.n {
+            return None;
+        }
+        if symbol > self.sigma {
+            return Some(i);
+        }
+
+        // SAFETY: checks above guarantee we are not out of bound
+        Some(unsafe { self.range_rank_smaller_equal(0..i, symbol).0 })
+    }
+
+    /// Returns the number of positions up to position `i` **excluded** whose
+    /// symbol is smaller than or equal to `symbol`.
+    ///
+    /// The query is solved with a single top-down traversal of the levels.
+    /// A `symbol` larger than sigma is valid, and in this case the result is `i`.
+    ///
+    /// `None` is returned if `i` is out of bound.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::QWT256;
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let qwt = QWT256::try_from(data).unwrap();
+    ///
+    /// assert_eq!(qwt.rank_leq(2, 8), Some(5));
+    /// assert_eq!(qwt.rank_leq(1, 3), Some(3));
+    /// assert_eq!(qwt.rank_leq(0, 0), Some(0));
+    /// assert_eq!(qwt.rank_leq(1, 9), None);  // Too large position
+    /// ```
+    #[must_use]
+    pub fn rank_leq(&self, symbol: T, i: usize) -> Option<usize> {
+        if i > self.n {
+            return None;
+        }
+        if symbol > self.sigma {
+            return Some(i);
+        }
+
+        // SAFETY: checks above guarantee we are not out of bound
+        let (smaller, equal) = unsafe { self.range_rank_smaller_equal(0..i, symbol) };
+        Some(smaller + equal)
+    }
+
+    /// Returns the number of positions up to position `i` **excluded** whose
+    /// symbol is strictly larger than `symbol`.
+    ///
+    /// The query is solved with a single top-down traversal of the levels.
+    /// A `symbol` larger than sigma is valid, and in this case the result is 0.
+    ///
+    /// `None` is returned if `i` is out of bound.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::QWT256;
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let qwt = QWT256::try_from(data).unwrap();
+    ///
+    /// assert_eq!(qwt.rank_gt(2, 8), Some(3));
+    /// assert_eq!(qwt.rank_gt(0, 3), Some(2));
+    /// assert_eq!(qwt.rank_gt(5, 8), Some(0));
+    /// assert_eq!(qwt.rank_gt(1, 9), None);  // Too large position
+    /// ```
+    #[must_use]
+    pub fn rank_gt(&self, symbol: T, i: usize) -> Option<usize> {
+        Some(i - self.rank_leq(symbol, i)?)
+    }
+
+    /// Returns a pair with the number of symbols smaller than `symbol` and
+    /// the number of occurrences of `symbol` in the range of positions `range`.
+    ///
+    /// All the rank queries are solved with one top-down traversal of the levels.
+    /// At each level we account for all the children that precede the one of `symbol`.
+    ///
+    /// # Safety
+    /// Calling this method with an out-of-bound `range` or with a `symbol` larger
+    /// than sigma is undefined behavior.
+    #[inline]
+    unsafe fn range_rank_smaller_equal(&self, range: Range<usize>, symbol: T) -> (usize, usize) {
+        if self.n_levels == 0 || range.is_empty() {
+            return (0, 0);
+        }
+
+        let mut range = range;
+        let mut smaller = 0;
+        let mut shift: i64 = (2 * (self.n_levels - 1)) as i64;
+
+        for level in 0..self.n_levels {
+            if range.is_empty() {
+                break;
+            }
+
+            let two_bits: u8 = (symbol >> shift as usize).as_() & 3;
+
+            for c in 0..two_bits {
+                smaller += self.child_range(level, c, &range).len();
+            }
+            range = self.child_range(level, two_bits, &range);
+
+            shift -= 2;
+        }
+
+        (smaller, range.len())
+    }
+
+    /// Maps the range of positions `range` at level `level` to the range of the
+    /// positions at the next level of the symbols with `two_bits` at this level.
+    ///
+    /// The length of the returned range is the number of occurrences of `two_bits`
+    /// in `range` at level `level`.
+    #[inline(always)]
+    unsafe fn child_range(&self, level: usize, two_bits: u8, range: &Range<usize>) -> Range<usize> {
+        let qv = self.qvs.get_unchecked(level);
+        let offset = qv.occs_smaller_unchecked(two_bits);
+        let rank_start = qv.rank_unchecked(two_bits, range.start);
+        let rank_end = qv.rank_unchecked(two_bits, range.end);
+
+        (rank_start + offset)..(rank_end + offset)
+    }
+}
+
+impl<T, RS, const WITH_PREFETCH_SUPPORT: bool> RankUnsigned
+    for QWaveletTree<T, RS, WITH_PREFETCH_SUPPORT>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+    RS: RSforWT,
+{
+    /// Returns the rank of `symbol` up to position `i` **excluded**.
+    ///
+    /// `None` is returned if `i` is out of bound or if `symbol` is not valid
+    /// (i.e., it is greater than or equal to the alphabet size).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{QWT256, RankUnsigned};
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let qwt = QWT256::try_from(data).unwrap();
+    ///
+    /// assert_eq!(qwt.rank(1, 2), Some(1));
+    /// assert_eq!(qwt.rank(3, 8), Some(1));
+    /// assert_eq!(qwt.rank(1, 0), Some(0));
+    /// assert_eq!(qwt.rank(1, 9), None);  // Too large position
+    /// assert_eq!(qwt.rank(6, 1), None);  // Too large symbol
+    /// ```
+    #[must_use]
+    #[inline(always)]
+    fn rank(&self, symbol: Self::Item, i: usize) -> Option<usize> {
+        if i > self.n || symbol > self.sigma {
+            return None;
+        }
+
+        // SAFETY: Check above guarantees we are not out of bound
+        Some(unsafe { self.rank_unchecked(symbol, i) })
+    }
+
+    /// Returns rank of `symbol` up to position `i` **excluded**.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method with a position `i` larger than the size of the sequence
+    /// or with an invalid symbol is undefined behavior.
+    ///
+    /// Users must ensure that the position `i` is within the bounds of the sequence
+    /// and that the symbol is valid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{QWT256, RankUnsigned};
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let qwt = QWT256::try_from(data).unwrap();
+    ///
+    /// unsafe {
+    ///     assert_eq!(qwt.rank_unchecked(1, 2), 1);
+    /// }
+    /// ```
+    #[must_use]
+    #[inline(always)]
+    unsafe fn rank_unchecked(&self, symbol: Self::Item, i: usize) -> usize {
+        let mut shift: i64 = (2 * (self.n_levels - 1)) as i64;
+        let mut cur_i = i;
+        let mut cur_p = 0;
+
+        for level in 0..self.n_levels - 1 {
+            let two_bits: u8 = (symbol >> shift as usize).as_() & 3;
+
+            // Safety: Here we are sure that two_bits is a symbol in [0..3]
+            let offset = unsafe { self.qvs[level].occs_smaller_unchecked(two_bits) };
+            cur_p = self.qvs[level].rank_unchecked(two_bits, cur_p) + offset;
+            cur_i = self.qvs[level].rank_unchecked(two_bits, cur_i) + offset;
+
+            shift -= 2;
+        }
+
+        let two_bits: u8 = (symbol >> shift as usize).as_() & 3;
+
+        cur_i = self.qvs[self.n_levels - 1].rank_unchecked(two_bits, cur_i);
+        cur_p = self.qvs[self.n_levels - 1].rank_unchecked(two_bits, cur_p);
+
+        cur_i - cur_p
+    }
+}
+
+impl<T, RS, const WITH_PREFETCH_SUPPORT: bool> AccessUnsigned
+    for QWaveletTree<T, RS, WITH_PREFETCH_SUPPORT>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+    RS: RSforWT,
+{
+    type Item = T;
+
+    /// Returns the `i`-th symbol of the indexed sequence.
+    ///
+    /// `None` is returned if `i` is out of bound.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use qwt::{QWT256, AccessUnsigned};
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let qwt = QWT256::try_from(data).unwrap();
+    ///
+    /// assert_eq!(qwt.get(2), Some(1));
+    /// assert_eq!(qwt.get(3), Some(0));
+    /// assert_eq!(qwt.get(8), None);
+    /// ```
+    #[must_use]
+    #[inline(always)]
+    fn get(&self, i: usize) -> Option<Self::Item> {
+        if i >= self.n {
+            return None;
+        }
+        // SAFETY: check before guarantees we are not out of bound
+        Some(unsafe { self.get_unchecked(i) })
+    }
+
+    /// Returns the `i`-th symbol of the indexed sequence.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method with an out-of-bounds index is undefined behavior.
+    ///
+    /// Users must ensure that the index `i` is within the bounds of the sequence.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::{QWT256, AccessUnsigned};
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let qwt = QWT256::try_from(data).unwrap();
+    ///
+    /// unsafe {
+    ///     assert_eq!(qwt.get_unchecked(2), 1);
+    ///     assert_eq!(qwt.get_unchecked(3), 0);
+    /// }
+    /// ```
+    #[must_use]
+    #[inline(always)]
+    unsafe fn get_unchecked(&self, i: usize) -> Self::Item {
+        let mut result = T::zero();
+
+        let mut cur_i = i;
+        for level in 0..self.n_levels - 1 {
+            // The last rank can be saved. The improvement is just ~3%. Indeed, most of the cost is for the cache miss for data access that we pay anyway
+
+            self.qvs[level].prefetch_info(cur_i); // Compiler is not able to infer that later it is needed for the rank query. Access is roughly 33% slower for large files without this.
+            let symbol = self.qvs[level].get_unchecked(cur_i);
+            result = (result << 2) | symbol.as_();
+
+            // SAFETY: Here we are sure that symbol is in [0..3]
+            let offset = unsafe { self.qvs[level].occs_smaller_unchecked(symbol) };
+            cur_i = self.qvs[level].rank_unchecked(symbol, cur_i) + offset;
+        }
+
+        let symbol = self.qvs[self.n_levels - 1].get_unchecked(cur_i);
+        (result << 2) | symbol.as_()
+    }
+}
+
+impl<T, RS, const WITH_PREFETCH_SUPPORT: bool> SelectUnsigned
+    for QWaveletTree<T, RS, WITH_PREFETCH_SUPPORT>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+    RS: RSforWT,
+{
+    /// Returns the position of the `i+1`-th occurrence of symbol `symbol`.
+    ///
+    /// `None` is returned if the is no (i+1)th such occurrence for the symbol
+    /// or if `symbol` is not valid (i.e., it is greater than or equal to the alphabet size).
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::{QWT256, SelectUnsigned};
+    ///
+    /// let data = vec![1u8, 0, 1, 0, 2, 4, 5, 3];
+    ///
+    /// let qwt = QWT256::try_from(data).unwrap();
+    ///
+    /// assert_eq!(qwt.select(1, 1), Some(2));
+    /// assert_eq!(qwt.select(0, 1), Some(3));
+    /// assert_eq!(qwt.select(0, 2), None);
+    /// assert_eq!(qwt.select(1, 0), Some(0));
+    /// assert_eq!(qwt.select(5, 0), Some(6));
+    /// assert_eq!(qwt.select(6, 1), None);
+    /// ```    
+    #[must_use]
+    #[inline(always)]
+    fn select(&self, symbol: Self::Item, i: usize) -> Option<usize> {
+        if symbol > self.sigma {
+            return None;
+        }
+
+        let mut path_off = Vec::with_capacity(self.n_levels);
+        let mut rank_path_off = Vec::with_capacity(self.n_levels);
+
+        let mut b = 0;
+        let mut shift: i64 = 2 * (self.n_levels - 1) as i64;
 
         for level in 0..self.n_levels {
             path_off.push(b);
@@ -748,6 +1984,126 @@ where
     }
 }
 
+impl<T, RS, const WITH_PREFETCH_SUPPORT: bool> Persist
+    for QWaveletTree<T, RS, WITH_PREFETCH_SUPPORT>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+    RS: RSforWT + Persist,
+{
+    fn type_tag() -> String {
+        format!(
+            "QWaveletTree<u{}, {}, {WITH_PREFETCH_SUPPORT}>",
+            8 * std::mem::size_of::<T>(),
+            RS::type_tag()
+        )
+    }
+
+    fn persisted_len(&self) -> usize {
+        self.n
+    }
+}
+
+impl<T, RS, const WITH_PREFETCH_SUPPORT: bool> ZeroCopy
+    for QWaveletTree<T, RS, WITH_PREFETCH_SUPPORT>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+    RS: RSforWT + ZeroCopy,
+{
+    fn write_layout<W: Write>(&self, writer: &mut LayoutWriter<W>) -> io::Result<()> {
+        let sigma = self.sigma.to_u128().unwrap();
+
+        writer.write_u64(std::mem::size_of::<T>() as u64)?;
+        writer.write_u64(WITH_PREFETCH_SUPPORT as u64)?;
+        writer.write_u64(self.n as u64)?;
+        writer.write_u64(self.n_levels as u64)?;
+        writer.write_u64(sigma as u64)?;
+        writer.write_u64((sigma >> 64) as u64)?;
+
+        writer.write_u64(self.qvs.len() as u64)?;
+        for level in &self.qvs {
+            level.write_layout(writer)?;
+        }
+
+        let prefetch_support = self.prefetch_support.as_deref().unwrap_or_default();
+        writer.write_u64(prefetch_support.len() as u64)?;
+        for pfs in prefetch_support {
+            pfs.write_layout(writer)?;
+        }
+        Ok(())
+    }
+
+    unsafe fn read_layout(reader: &mut LayoutReader<'_>) -> Result<Self, LayoutError> {
+        if reader.read_u64()? != std::mem::size_of::<T>() as u64 {
+            return Err(LayoutError::InvalidData("type of the symbols"));
+        }
+        if reader.read_u64()? != WITH_PREFETCH_SUPPORT as u64 {
+            return Err(LayoutError::InvalidData(
+                "prefetch support of the wavelet tree",
+            ));
+        }
+        let n = reader.read_usize()?;
+        let n_levels = reader.read_usize()?;
+        let sigma = reader.read_u64()? as u128 | (reader.read_u64()? as u128) << 64;
+        let sigma = num_traits::cast(sigma).ok_or(LayoutError::InvalidData("largest symbol"))?;
+
+        // An empty wavelet tree has a single empty level
+        let n_qvs = reader.read_usize()?;
+        if n_qvs != n_levels.max(1) {
+            return Err(LayoutError::InvalidData(
+                "number of levels of the wavelet tree",
+            ));
+        }
+        let qvs: Vec<WTLevel<RS>> = (0..n_qvs)
+            .map(|_| WTLevel::read_layout(reader))
+            .collect::<Result<_, _>>()?;
+
+        // The levels must be the ones built by `from_levels`: each level has `n` symbols
+        // and only the first one is binary, if the number of bits of `sigma` is odd
+        let valid_levels = if n == 0 {
+            n_levels == 0 && sigma == T::zero() && qvs[0].len() == 0
+        } else {
+            let log_sigma = msb(sigma) as usize + 1;
+            n_levels == log_sigma.div_ceil(2)
+                && qvs.iter().enumerate().all(|(level, qv)| {
+                    let is_binary = matches!(qv, WTLevel::Binary { .. });
+                    qv.len() == n && is_binary == (level == 0 && log_sigma % 2 == 1)
+                })
+        };
+        if !valid_levels {
+            return Err(LayoutError::InvalidData("levels of the wavelet tree"));
+        }
+
+        // A prefetch support for each quad level
+        let n_prefetch_support = reader.read_usize()?;
+        let n_quad_levels = qvs
+            .iter()
+            .filter(|qv| matches!(qv, WTLevel::Quad(_)))
+            .count();
+        if n_prefetch_support != n_quad_levels * (WITH_PREFETCH_SUPPORT && n > 0) as usize {
+            return Err(LayoutError::InvalidData(
+                "prefetch support of the wavelet tree",
+            ));
+        }
+        let prefetch_support = (0..n_prefetch_support)
+            .map(|_| PrefetchSupport::read_layout(reader))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            n,
+            n_levels,
+            sigma,
+            qvs,
+            prefetch_support: if WITH_PREFETCH_SUPPORT && n > 0 {
+                Some(prefetch_support)
+            } else {
+                None
+            },
+        })
+    }
+}
+
 impl<T, RS: SpaceUsage, const WITH_PREFETCH_SUPPORT: bool> SpaceUsage
     for QWaveletTree<T, RS, WITH_PREFETCH_SUPPORT>
 {
@@ -777,6 +2133,46 @@ impl<T, RS, const WITH_PREFETCH_SUPPORT: bool> AsRef<QWaveletTree<T, RS, WITH_PR
     }
 }
 
+/// An iterator over the distinct symbols in a range of positions of a
+/// [`QWaveletTree`], together with their number of occurrences.
+///
+/// It is obtained with [`QWaveletTree::range_distinct`].
+#[derive(Debug)]
+pub struct QWTRangeDistinctIterator<'a, T, RS, const WITH_PREFETCH_SUPPORT: bool = false> {
+    qwt: &'a QWaveletTree<T, RS, WITH_PREFETCH_SUPPORT>,
+    stack: Vec<(usize, Range<usize>, T)>, // (level, range at that level, symbol prefix) of the nodes still to visit
+}
+
+impl<'a, T, RS, const WITH_PREFETCH_SUPPORT: bool> Iterator
+    for QWTRangeDistinctIterator<'a, T, RS, WITH_PREFETCH_SUPPORT>
+where
+    T: WTIndexable,
+    u8: AsPrimitive<T>,
+    RS: RSforWT,
+{
+    type Item = (T, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((level, range, symbol)) = self.stack.pop() {
+            if level == self.qwt.n_levels {
+                return Some((symbol, range.len()));
+            }
+
+            // Push children in reverse order so that smaller symbols are visited first
+            for two_bits in (0..4u8).rev() {
+                // SAFETY: ranges in the stack are always valid for their level
+                let child_range = unsafe { self.qwt.child_range(level, two_bits, &range) };
+                if !child_range.is_empty() {
+                    self.stack
+                        .push((level + 1, child_range, (symbol << 2) | two_bits.as_()));
+                }
+            }
+        }
+
+        None
+    }
+}
+
 // This is a naive implementation of an iterator for WT.
 // We could do better by storing more information and
 // avoid rank operations!
@@ -910,15 +2306,30 @@ where
     }
 }
 
-impl<T, RS, const WITH_PREFETCH_SUPPORT: bool> From<Vec<T>>
+/// Builds the wavelet tree of a vector of unsigned integers.
+///
+/// # Errors
+/// Returns [`Error::TooLong`] if the vector is longer than the largest possible length.
+///
+/// # Examples
+/// ```
+/// use qwt::{AccessUnsigned, QWT256};
+///
+/// let qwt = QWT256::try_from(vec![1u8, 0, 1, 0, 2, 4, 5, 3]).unwrap();
+///
+/// assert_eq!(qwt.get(5), Some(4));
+/// ```
+impl<T, RS, const WITH_PREFETCH_SUPPORT: bool> TryFrom<Vec<T>>
     for QWaveletTree<T, RS, WITH_PREFETCH_SUPPORT>
 where
     T: WTIndexable,
     u8: AsPrimitive<T>,
     RS: RSforWT,
 {
-    fn from(mut v: Vec<T>) -> Self {
-        QWaveletTree::new(&mut v[..])
+    type Error = Error;
+
+    fn try_from(mut v: Vec<T>) -> Result<Self, Error> {
+        QWaveletTree::try_new(&mut v[..])
     }
 }
 
@@ -1,5 +1,8 @@
+use crate::zero_copy::{LayoutError, LayoutReader, LayoutWriter, ZeroCopy};
 use crate::{BitVectorMut, QVector, RSNarrow, RankBin, SpaceUsage};
 
+use std::io::{self, Write};
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
@@ -63,6 +66,33 @@ impl PrefetchSupport {
     }
 }
 
+impl ZeroCopy for PrefetchSupport {
+    fn write_layout<W: Write>(&self, writer: &mut LayoutWriter<W>) -> io::Result<()> {
+        writer.write_u64(self.sample_rate_shift as u64)?;
+        writer.write_u64(self.samples.len() as u64)?;
+        for sample in &self.samples {
+            sample.write_layout(writer)?;
+        }
+        Ok(())
+    }
+
+    unsafe fn read_layout(reader: &mut LayoutReader<'_>) -> Result<Self, LayoutError> {
+        let sample_rate_shift = reader.read_usize()?;
+        let n_samples = reader.read_usize()?;
+        if n_samples != 4 || sample_rate_shift >= usize::BITS as usize {
+            return Err(LayoutError::InvalidData("prefetch support"));
+        }
+        let samples = (0..n_samples)
+            .map(|_| RSNarrow::read_layout(reader))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            samples,
+            sample_rate_shift,
+        })
+    }
+}
+
 impl SpaceUsage for PrefetchSupport {
     fn space_usage_byte(&self) -> usize {
         self.samples.iter().map(|bv| bv.space_usage_byte()).sum()
@@ -1,7 +1,8 @@
 use super::*;
-use crate::perf_and_test_utils::gen_sequence;
+use crate::perf_and_test_utils::{gen_sequence, SmallRSQVector};
 use crate::RSQVector512;
 use crate::QWT256;
+use crate::QWT512;
 
 #[test]
 fn test_small() {
@@ -116,3 +117,546 @@ fn test_serialize() {
 
     assert_eq!(des_qwt, qwt);
 }
+
+#[test]
+fn test_quantile() {
+    let n = 1025;
+    for sigma in [4, 5, 7, 8, 9, 15, 16, 17, 31, 32, 33, 255, 256] {
+        let sequence = gen_sequence(n, sigma);
+        let qwt = QWaveletTree::<_, RSQVector512>::new(&mut sequence.clone());
+
+        for (start, end) in [
+            (0, n),
+            (0, 1),
+            (13, 14),
+            (100, 600),
+            (511, 1024),
+            (n - 1, n),
+        ] {
+            let mut sorted = sequence[start..end].to_vec();
+            sorted.sort();
+
+            for (k, &symbol) in sorted.iter().enumerate() {
+                assert_eq!(qwt.quantile(start..end, k), Some(symbol));
+            }
+            assert_eq!(qwt.quantile(start..end, end - start), None);
+            assert_eq!(
+                qwt.range_median(start..end),
+                Some(sorted[(end - start - 1) / 2])
+            );
+        }
+    }
+
+    let qwt = QWaveletTree::<_, RSQVector512>::new(&mut [3_u8, 1, 2]);
+    assert_eq!(qwt.quantile(2..2, 0), None);
+    assert_eq!(qwt.quantile(2..4, 0), None);
+    assert_eq!(qwt.range_median(0..0), None);
+}
+
+#[test]
+fn test_range_count() {
+    let n = 1025;
+    for sigma in [4, 5, 8, 17, 33, 256] {
+        let sequence = gen_sequence(n, sigma);
+        let qwt = QWaveletTree::<_, RSQVector512>::new(&mut sequence.clone());
+
+        for (start, end) in [(0, n), (0, 1), (13, 14), (100, 600), (511, 1024), (7, 7)] {
+            for (a, b) in [
+                (0, 0),
+                (0, 255),
+                (1, 3),
+                (3, 1),
+                (2, 16),
+                (16, 255),
+                (255, 255),
+            ] {
+                let expected = sequence[start..end]
+                    .iter()
+                    .filter(|&&c| a <= c && c <= b)
+                    .count();
+                assert_eq!(qwt.range_count(start..end, a..=b), Some(expected));
+            }
+        }
+        assert_eq!(qwt.range_count(0..n + 1, 0..=255), None);
+    }
+}
+
+#[test]
+fn test_range_distinct() {
+    let n = 1025;
+    for sigma in [4, 5, 8, 17, 33, 256] {
+        let sequence = gen_sequence(n, sigma);
+        let qwt = QWaveletTree::<_, RSQVector512>::new(&mut sequence.clone());
+
+        for (start, end) in [(0, n), (0, 1), (13, 14), (100, 600), (511, 1024), (7, 7)] {
+            let mut expected = std::collections::BTreeMap::new();
+            for &c in &sequence[start..end] {
+                *expected.entry(c).or_insert(0) += 1;
+            }
+            let expected: Vec<_> = expected.into_iter().collect();
+
+            let distinct: Vec<_> = qwt.range_distinct(start..end).unwrap().collect();
+            assert_eq!(distinct, expected);
+        }
+        assert!(qwt.range_distinct(0..n + 1).is_none());
+    }
+}
+
+#[test]
+fn test_range_top_k() {
+    let n = 1025;
+    for sigma in [4, 5, 8, 17, 33, 256] {
+        let sequence = gen_sequence(n, sigma);
+        let qwt = QWaveletTree::<_, RSQVector512>::new(&mut sequence.clone());
+
+        for (start, end) in [(0, n), (0, 1), (13, 14), (100, 600), (511, 1024), (7, 7)] {
+            let mut freqs = std::collections::BTreeMap::new();
+            for &c in &sequence[start..end] {
+                *freqs.entry(c).or_insert(0) += 1;
+            }
+            let mut expected: Vec<_> = freqs.into_iter().collect();
+            expected.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+            for k in [0, 1, 3, 10, 300] {
+                let top_k = qwt.range_top_k(start..end, k).unwrap();
+                assert_eq!(top_k, expected[..k.min(expected.len())]);
+            }
+        }
+        assert_eq!(qwt.range_top_k(0..n + 1, 1), None);
+    }
+}
+
+#[test]
+fn test_range_next_prev_value() {
+    let n = 1025;
+    for sigma in [4, 5, 8, 17, 33, 256] {
+        let sequence = gen_sequence(n, sigma);
+        let qwt = QWaveletTree::<_, RSQVector512>::new(&mut sequence.clone());
+
+        for (start, end) in [(0, n), (0, 1), (13, 14), (100, 600), (511, 1024), (7, 7)] {
+            for x in 0..=255 {
+                let next = sequence[start..end].iter().filter(|&&c| c >= x).min();
+                assert_eq!(qwt.range_next_value(start..end, x), next.copied());
+
+                let prev = sequence[start..end].iter().filter(|&&c| c <= x).max();
+                assert_eq!(qwt.range_prev_value(start..end, x), prev.copied());
+            }
+        }
+        assert_eq!(qwt.range_next_value(0..n + 1, 0), None);
+        assert_eq!(qwt.range_prev_value(0..n + 1, 0), None);
+    }
+}
+
+#[test]
+fn test_rank_lt_leq_gt() {
+    let n = 1025;
+    for sigma in [4, 5, 8, 17, 33, 256] {
+        let sequence = gen_sequence(n, sigma);
+        let qwt = QWaveletTree::<_, RSQVector512>::new(&mut sequence.clone());
+
+        for i in [0, 1, 13, 255, 256, 600, 1024, n] {
+            for symbol in 0..=255 {
+                let prefix = &sequence[..i];
+                let lt = prefix.iter().filter(|&&c| c < symbol).count();
+                let leq = prefix.iter().filter(|&&c| c <= symbol).count();
+
+                assert_eq!(qwt.rank_lt(symbol, i), Some(lt));
+                assert_eq!(qwt.rank_leq(symbol, i), Some(leq));
+                assert_eq!(qwt.rank_gt(symbol, i), Some(i - leq));
+            }
+        }
+        assert_eq!(qwt.rank_lt(0, n + 1), None);
+        assert_eq!(qwt.rank_leq(0, n + 1), None);
+        assert_eq!(qwt.rank_gt(0, n + 1), None);
+    }
+
+    let qwt = QWT256::<u8>::new(&mut []);
+    for symbol in [0, 1, 255] {
+        assert_eq!(qwt.rank_lt(symbol, 0), Some(0));
+        assert_eq!(qwt.rank_leq(symbol, 0), Some(0));
+        assert_eq!(qwt.rank_gt(symbol, 0), Some(0));
+    }
+    assert_eq!(qwt.rank_lt(0, 1), None);
+}
+
+#[test]
+fn test_binary_first_level() {
+    let n = 10_000;
+    // sigma is one plus the largest symbol. Odd bit widths use a binary first level.
+    for sigma in [1, 2, 5, 7, 8, 9, 31, 32, 33, 128] {
+        let sequence = gen_sequence(n, sigma);
+        let qwt = QWaveletTree::<_, RSQVector512>::new(&mut sequence.clone());
+        let qwt_pfs = QWaveletTree::<_, RSQVector512, true>::new(&mut sequence.clone());
+
+        for (i, &symbol) in sequence.iter().enumerate() {
+            assert_eq!(qwt.get(i), Some(symbol));
+            assert_eq!(qwt_pfs.get(i), Some(symbol));
+        }
+
+        let mut ranks = vec![0; sigma];
+        for (i, &symbol) in sequence.iter().enumerate() {
+            let rank = ranks[symbol as usize];
+            assert_eq!(qwt.rank(symbol, i), Some(rank));
+            assert_eq!(qwt.rank_prefetch(symbol, i), Some(rank));
+            assert_eq!(qwt_pfs.rank_prefetch(symbol, i), Some(rank));
+            assert_eq!(qwt.select(symbol, rank), Some(i));
+            ranks[symbol as usize] += 1;
+        }
+
+        for symbol in 0..sigma as u8 {
+            assert_eq!(qwt.rank(symbol, n), Some(ranks[symbol as usize]));
+            assert_eq!(qwt.select(symbol, ranks[symbol as usize]), None);
+        }
+
+        let range = 17..n - 33;
+        let mut sorted = sequence[range.clone()].to_vec();
+        sorted.sort();
+        for k in [0, 1, sorted.len() / 2, sorted.len() - 1] {
+            assert_eq!(qwt.quantile(range.clone(), k), Some(sorted[k]));
+        }
+    }
+
+    // All zeros
+    let sequence = vec![0u8; n];
+    let qwt = QWaveletTree::<_, RSQVector512>::new(&mut sequence.clone());
+    assert_eq!(qwt.n_levels(), 1);
+    assert_eq!(qwt.rank(0, n), Some(n));
+    assert_eq!(qwt.select(0, n - 1), Some(n - 1));
+    assert_eq!(qwt.get(n - 1), Some(0));
+}
+
+#[test]
+fn test_binary_first_level_space() {
+    // The first level of 3-bit symbols takes one bit per symbol instead of two.
+    let n = 1 << 16;
+    let mut sequence = gen_sequence(n, 8);
+    let qwt = QWaveletTree::<_, RSQVector512>::new(&mut sequence);
+
+    assert_eq!(qwt.n_levels(), 2);
+    assert!(qwt.space_usage_byte() * 8 < 4 * n);
+}
+
+#[test]
+fn test_backward_search_step() {
+    let n = 1025;
+    for sigma in [4, 5, 8, 17, 33, 256] {
+        let sequence = gen_sequence(n, sigma);
+        let qwt = QWaveletTree::<_, RSQVector512>::new(&mut sequence.clone());
+        let qwt_pfs = QWaveletTree::<_, RSQVector512, true>::new(&mut sequence.clone());
+
+        for (l, r) in [(0, 0), (0, n), (3, 700), (256, 257), (511, 1024), (n, n)] {
+            for symbol in 0..sigma as u8 {
+                let expected = qwt.rank(symbol, l).unwrap()..qwt.rank(symbol, r).unwrap();
+                assert_eq!(
+                    qwt.backward_search_step(l..r, symbol),
+                    Some(expected.clone())
+                );
+                assert_eq!(
+                    qwt_pfs.backward_search_step_prefetch(l..r, symbol),
+                    Some(expected)
+                );
+            }
+        }
+
+        assert_eq!(qwt.backward_search_step(0..n + 1, 0), None);
+        let (l, r) = (5, 4);
+        assert_eq!(qwt.backward_search_step(l..r, 0), None); // Empty range with l > r
+    }
+}
+
+#[test]
+fn test_batch() {
+    let n = 1025;
+    for sigma in [1, 4, 5, 8, 17, 33, 256] {
+        let sequence = gen_sequence(n, sigma);
+        let qwt = QWaveletTree::<_, RSQVector512>::new(&mut sequence.clone());
+
+        // More queries than BATCH_SIZE, with a last incomplete group
+        let positions: Vec<usize> = (0..n).rev().step_by(3).collect();
+        let mut symbols = vec![0; positions.len()];
+        assert!(qwt.get_batch(&positions, &mut symbols));
+        for (&i, &symbol) in positions.iter().zip(symbols.iter()) {
+            assert_eq!(symbol, sequence[i]);
+        }
+
+        let queries: Vec<(u8, usize)> = (0..=n).step_by(5).map(|i| (sequence[i % n], i)).collect();
+        let mut ranks = vec![0; queries.len()];
+        assert!(qwt.rank_batch(&queries, &mut ranks));
+        for (&(symbol, i), &rank) in queries.iter().zip(ranks.iter()) {
+            assert_eq!(Some(rank), qwt.rank(symbol, i));
+        }
+
+        let queries: Vec<(u8, usize)> = (0..n)
+            .step_by(3)
+            .map(|i| (sequence[i], i / sigma))
+            .chain([(sigma as u8, 0), (0, n)])
+            .collect();
+        let mut selects = vec![None; queries.len()];
+        qwt.select_batch(&queries, &mut selects);
+        for (&(symbol, i), &pos) in queries.iter().zip(selects.iter()) {
+            assert_eq!(pos, qwt.select(symbol, i));
+        }
+
+        // Invalid queries leave the results untouched
+        let mut symbols = vec![42; 2];
+        assert!(!qwt.get_batch(&[0, n], &mut symbols));
+        assert_eq!(symbols, vec![42; 2]);
+        let mut ranks = vec![42; 2];
+        assert!(!qwt.rank_batch(&[(0, 0), (0, n + 1)], &mut ranks));
+        assert_eq!(ranks, vec![42; 2]);
+
+        // Unchecked queries answer only the queries with a corresponding result
+        let mut symbols = vec![42; 3];
+        unsafe { qwt.get_batch_unchecked(&[0, 1], &mut symbols) };
+        assert_eq!(symbols, vec![sequence[0], sequence[1], 42]);
+        let mut ranks = vec![42; 1];
+        unsafe { qwt.rank_batch_unchecked(&[(sequence[0], 1), (0, 0)], &mut ranks) };
+        assert_eq!(ranks, vec![1]);
+    }
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_build_level_parallel() {
+    let sequence = gen_sequence(100_000, 256);
+    for shift in [0, 2, 4, 6] {
+        let sequential = build_level_parallel(&sequence, shift, 1);
+        for n_threads in [2, 3, 8, 1000] {
+            assert_eq!(
+                build_level_parallel(&sequence, shift, n_threads),
+                sequential
+            );
+        }
+    }
+}
+
+#[test]
+fn test_builder() {
+    for sigma in [1, 2, 3, 4, 5, 16, 17, 256] {
+        let sequence = gen_sequence(10_000, sigma);
+        let qwt = QWT256::new(&mut sequence.clone());
+
+        for scratch_size in [1, 7, 1000, 10_000, 100_000] {
+            let mut builder = QWaveletTreeBuilder::new().scratch_size(scratch_size);
+            let built: QWT256<u8> = builder.build(&mut sequence.clone());
+            assert_eq!(built, qwt);
+
+            let scratch_size = scratch_size.min(sequence.len());
+            assert!(
+                builder.estimated_peak_memory()
+                    >= sequence.len() + scratch_size + qwt.space_usage_byte()
+            );
+        }
+
+        let mut builder = QWaveletTreeBuilder::new();
+        let built: QWT256<u8> = builder.build(&mut sequence.clone());
+        assert_eq!(built, qwt);
+        // The default scratch buffer is much smaller than the sequence
+        assert!(builder.estimated_peak_memory() < 2 * sequence.len() + qwt.space_usage_byte());
+    }
+
+    let mut builder = QWaveletTreeBuilder::new();
+    let qwt: QWT256<u8> = builder.build(&mut []);
+    assert!(qwt.is_empty());
+}
+
+#[test]
+fn test_builder_too_long() {
+    let temp_dir = std::env::temp_dir().join(format!("qwt-test-too-long-{}", std::process::id()));
+    std::fs::create_dir_all(&temp_dir).unwrap();
+    let mut builder = QWaveletTreeBuilder::new().temp_dir(&temp_dir);
+
+    let sequence = gen_sequence(100, 16);
+    let qwt = QWaveletTree::<_, SmallRSQVector>::new(&mut sequence.clone());
+    let built: QWaveletTree<_, SmallRSQVector> = builder.try_build(&mut sequence.clone()).unwrap();
+    assert_eq!(built, qwt);
+    let built: QWaveletTree<_, SmallRSQVector> = builder.build_from_iter(sequence).unwrap();
+    assert_eq!(built, qwt);
+
+    // A too long sequence is rejected before building any level and is left untouched
+    let sequence = gen_sequence(101, 16);
+    let mut input = sequence.clone();
+    let result: Result<QWaveletTree<_, SmallRSQVector>, _> = builder.try_build(&mut input);
+    assert!(matches!(
+        result,
+        Err(Error::TooLong {
+            len: 101,
+            max_len: 100
+        })
+    ));
+    assert_eq!(input, sequence);
+
+    // The streaming constructions stop reading after the first symbol that does not fit
+    let mut iter = (0..1000u32).map(|i| (i % 16) as u8);
+    let result: Result<QWaveletTree<_, SmallRSQVector>, _> = builder.build_from_iter(&mut iter);
+    assert!(matches!(
+        result,
+        Err(Error::TooLong {
+            len: 101,
+            max_len: 100
+        })
+    ));
+    assert_eq!(iter.len(), 899);
+
+    let result: Result<QWaveletTree<u8, SmallRSQVector>, _> =
+        builder.build_from_reader(&[0u8; 1000][..]);
+    assert!(matches!(result, Err(Error::TooLong { .. })));
+
+    // All the temporary files have been removed
+    assert_eq!(std::fs::read_dir(&temp_dir).unwrap().count(), 0);
+    std::fs::remove_dir(&temp_dir).unwrap();
+}
+
+#[test]
+fn test_builder_streaming() {
+    let temp_dir = std::env::temp_dir().join(format!("qwt-test-streaming-{}", std::process::id()));
+    std::fs::create_dir_all(&temp_dir).unwrap();
+    let mut builder = QWaveletTreeBuilder::new().temp_dir(&temp_dir);
+
+    for sigma in [1, 2, 3, 4, 5, 16, 17, 256] {
+        let sequence = gen_sequence(10_000, sigma);
+        let qwt = QWT256::new(&mut sequence.clone());
+
+        let built: QWT256<u8> = builder.build_from_iter(sequence.iter().copied()).unwrap();
+        assert_eq!(built, qwt);
+
+        let built: QWT256<u8> = builder.build_from_reader(&sequence[..]).unwrap();
+        assert_eq!(built, qwt);
+
+        // Wider symbols are read in little-endian order
+        let wide: Vec<u16> = sequence.iter().map(|&c| c as u16 * 257).collect();
+        let bytes: Vec<u8> = wide.iter().flat_map(|c| c.to_le_bytes()).collect();
+        let qwt = QWT512::new(&mut wide.clone());
+        let built: QWT512<u16> = builder.build_from_reader(&bytes[..]).unwrap();
+        assert_eq!(built, qwt);
+        assert!(builder.estimated_peak_memory() >= qwt.space_usage_byte());
+    }
+
+    let built: QWT256<u8> = builder.build_from_iter(std::iter::empty()).unwrap();
+    assert!(built.is_empty());
+
+    // The input ends in the middle of a symbol
+    let result: Result<QWT256<u16>, Error> = builder.build_from_reader(&[1u8, 0, 2][..]);
+    assert!(matches!(
+        result,
+        Err(Error::Io(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof
+    ));
+
+    // All the temporary files have been removed
+    assert_eq!(std::fs::read_dir(&temp_dir).unwrap().count(), 0);
+    std::fs::remove_dir(&temp_dir).unwrap();
+}
+
+// Checks all the queries of a dynamic wavelet tree against the static one built on `expected`.
+fn check_dynamic(dqwt: &DynamicQWaveletTree<u32>, expected: &[u32]) {
+    assert_eq!(dqwt.len(), expected.len());
+    assert!(dqwt.iter().eq(expected.iter().copied()));
+
+    let qwt = QWT256::try_from(expected.to_vec()).unwrap();
+    for (i, &symbol) in expected.iter().enumerate().step_by(7) {
+        assert_eq!(dqwt.get(i), Some(symbol));
+        assert_eq!(dqwt.rank(symbol, i), qwt.rank(symbol, i));
+        assert_eq!(
+            dqwt.rank(symbol + 1, i),
+            Some(qwt.rank(symbol + 1, i).unwrap_or(0))
+        );
+        let rank = dqwt.rank(symbol, i).unwrap();
+        assert_eq!(dqwt.select(symbol, rank), Some(i));
+    }
+    assert_eq!(dqwt.get(expected.len()), None);
+    assert_eq!(dqwt.rank(0, expected.len() + 1), None);
+}
+
+#[test]
+fn test_dynamic() {
+    use rand::Rng;
+
+    let mut rng = rand::thread_rng();
+    let mut dqwt = DynamicQWaveletTree::<u32>::new();
+    let mut expected = Vec::new();
+    assert_eq!(dqwt.rank(0, 0), Some(0));
+    assert_eq!(dqwt.select(0, 0), None);
+
+    // The alphabet grows with the insertions
+    for sigma in [2, 3, 16, 17, 1000, 1 << 20] {
+        for _ in 0..5000 {
+            let i = rng.gen_range(0..=expected.len());
+            let symbol = rng.gen_range(0..sigma);
+            dqwt.insert(i, symbol);
+            expected.insert(i, symbol);
+        }
+        check_dynamic(&dqwt, &expected);
+    }
+    assert_eq!(dqwt.n_levels(), 10);
+    assert_eq!(dqwt.rank(u32::MAX, expected.len()), Some(0));
+    assert_eq!(dqwt.select(u32::MAX, 0), None);
+
+    for _ in 0..5000 {
+        let i = rng.gen_range(0..expected.len());
+        let symbol = rng.gen_range(0..100);
+        assert_eq!(dqwt.set(i, symbol), expected[i]);
+        expected[i] = symbol;
+    }
+    check_dynamic(&dqwt, &expected);
+
+    while expected.len() > 100 {
+        let i = rng.gen_range(0..expected.len());
+        assert_eq!(dqwt.remove(i), expected.remove(i));
+    }
+    check_dynamic(&dqwt, &expected);
+
+    let other: DynamicQWaveletTree<u32> = expected.iter().copied().collect();
+    assert_eq!(other, dqwt);
+}
+
+#[test]
+fn test_append_only() {
+    use crate::AppendOnlyQWT256;
+    use rand::Rng;
+
+    let mut rng = rand::thread_rng();
+    let mut aqwt = AppendOnlyQWT256::<u32>::new();
+    let mut expected: Vec<u32> = Vec::new();
+    assert_eq!(aqwt.rank(0, 0), Some(0));
+    assert_eq!(aqwt.select(0, 0), None);
+
+    // Lengths around the superblocks of the nodes, and new levels for larger symbols
+    for n in [1, 2047, 2048, 2049, 4096, 6144, 10_000, 50_000] {
+        let sigma = if n < 4096 { 4 } else { 1 << 18 };
+        let batch: Vec<u32> = (expected.len()..n)
+            .map(|_| rng.gen_range(0..sigma))
+            .collect();
+        aqwt.extend(batch.iter().copied());
+        expected.extend(batch);
+
+        assert_eq!(aqwt.len(), expected.len());
+        assert!(aqwt.iter().eq(expected.iter().copied()));
+
+        let qwt = QWT256::try_from(expected.clone()).unwrap();
+        for (i, &symbol) in expected.iter().enumerate().step_by(13) {
+            assert_eq!(aqwt.get(i), Some(symbol));
+            assert_eq!(aqwt.rank(symbol, i), qwt.rank(symbol, i));
+            assert_eq!(
+                aqwt.rank(symbol + 1, i),
+                Some(qwt.rank(symbol + 1, i).unwrap_or(0))
+            );
+            let rank = aqwt.rank(symbol, i).unwrap();
+            assert_eq!(aqwt.select(symbol, rank), Some(i));
+        }
+        assert_eq!(aqwt.get(n), None);
+        assert_eq!(aqwt.rank(0, n + 1), None);
+        assert_eq!(aqwt.rank(u32::MAX, n), Some(0));
+        assert_eq!(aqwt.select(u32::MAX, 0), None);
+    }
+
+    let other: AppendOnlyQWT256<u32> = expected.iter().copied().collect();
+    assert_eq!(other, aqwt);
+
+    let aqwt: crate::AppendOnlyQWT512<u32> = expected.iter().copied().collect();
+    assert!(aqwt.iter().eq(expected.iter().copied()));
+    for (i, &symbol) in expected.iter().enumerate().step_by(13) {
+        let rank = aqwt.rank(symbol, i).unwrap();
+        assert_eq!(other.rank(symbol, i), Some(rank));
+        assert_eq!(aqwt.select(symbol, rank), Some(i));
+    }
+}
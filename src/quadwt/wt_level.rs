@@ -0,0 +1,210 @@
+//! A level of the Quad Wavelet Tree.
+//!
+//! All the levels store a quad vector except, possibly, the first one.
+//! When the number of bits needed to represent the largest symbol is odd,
+//! the first level has only one meaningful bit per symbol and it is
+//! represented with a binary vector, so that the wavelet tree uses exactly
+//! $$n \lceil\log \sigma\rceil$$ bits plus the rank/select overhead.
+//!
+//! A binary level answers the queries of a quad level with symbols in [0, 1].
+//! Queries with symbols 2 and 3 are still valid: these symbols never occur and
+//! all the symbols in the level are smaller than them. This way the traversal
+//! of the wavelet tree does not need to distinguish between the two kinds of level.
+
+use crate::quadwt::RSforWT;
+use crate::zero_copy::{LayoutError, LayoutReader, LayoutWriter, ZeroCopy};
+use crate::{AccessBin, RSNarrow, RankBin, SelectBin, SpaceUsage};
+
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub(crate) enum WTLevel<RS> {
+    Binary {
+        bv: RSNarrow,
+        n: usize,       // The length of the binary vector
+        n_zeros: usize, // The number of zeros, i.e., of symbols smaller than 1
+    },
+    Quad(RS),
+}
+
+impl<RS: RSforWT> WTLevel<RS> {
+    /// Builds a binary level from the sequence of its symbols in [0, 1].
+    /// The sequence must be non-empty.
+    pub(crate) fn new_binary<I>(symbols: I) -> Self
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        let bv = RSNarrow::new(symbols.into_iter().map(|symbol| symbol == 1).collect());
+        let n_zeros = bv.n_zeros();
+        let n = bv.n_ones() + n_zeros;
+
+        Self::Binary { bv, n, n_zeros }
+    }
+
+    /// Returns the number of symbols in the level.
+    #[inline(always)]
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Self::Binary { n, .. } => *n,
+            // SAFETY: 3 is a valid symbol
+            Self::Quad(qv) => unsafe { qv.occs_smaller_unchecked(3) + qv.occs_unchecked(3) },
+        }
+    }
+
+    /// Returns the symbol at position `i`.
+    ///
+    /// # Safety
+    /// Calling this method with an out-of-bounds index is undefined behavior.
+    #[inline(always)]
+    pub(crate) unsafe fn get_unchecked(&self, i: usize) -> u8 {
+        match self {
+            Self::Binary { bv, .. } => bv.get_unchecked(i) as u8,
+            Self::Quad(qv) => qv.get_unchecked(i),
+        }
+    }
+
+    /// Returns the number of occurrences of `symbol` up to position `i` excluded,
+    /// `None` if `i` is out of bound.
+    #[inline(always)]
+    pub(crate) fn rank(&self, symbol: u8, i: usize) -> Option<usize> {
+        match self {
+            Self::Binary { n, .. } if i > *n => None,
+            // SAFETY: `i` is not out of bound
+            Self::Binary { .. } => Some(unsafe { self.rank_unchecked(symbol, i) }),
+            Self::Quad(qv) => qv.rank(symbol, i),
+        }
+    }
+
+    /// Returns the number of occurrences of `symbol` up to position `i` excluded.
+    ///
+    /// # Safety
+    /// Calling this method if the `symbol` is larger than 3 or if the
+    /// position `i` is out of bound is undefined behavior.
+    #[inline(always)]
+    pub(crate) unsafe fn rank_unchecked(&self, symbol: u8, i: usize) -> usize {
+        match self {
+            Self::Binary { bv, .. } => match symbol {
+                0 => i - bv.rank1_unchecked(i),
+                1 => bv.rank1_unchecked(i),
+                _ => 0,
+            },
+            Self::Quad(qv) => qv.rank_unchecked(symbol, i),
+        }
+    }
+
+    /// Returns the rank of `symbol` up to the block that contains the position `i`.
+    /// A binary level has no blocks and returns the exact rank.
+    ///
+    /// # Safety
+    /// Calling this method if the `symbol` is larger than 3 or if the
+    /// position `i` is out of bound is undefined behavior.
+    #[inline(always)]
+    pub(crate) unsafe fn rank_block_unchecked(&self, symbol: u8, i: usize) -> usize {
+        match self {
+            Self::Binary { .. } => self.rank_unchecked(symbol, i),
+            Self::Quad(qv) => qv.rank_block_unchecked(symbol, i),
+        }
+    }
+
+    /// Returns the position of the `i+1`-th occurrence of `symbol`, `None` if
+    /// there is no such occurrence.
+    #[inline(always)]
+    pub(crate) fn select(&self, symbol: u8, i: usize) -> Option<usize> {
+        match self {
+            Self::Binary { bv, .. } => match symbol {
+                0 => bv.select0(i),
+                1 => bv.select1(i),
+                _ => None,
+            },
+            Self::Quad(qv) => qv.select(symbol, i),
+        }
+    }
+
+    /// Returns the number of occurrences of all the symbols smaller than `symbol`.
+    ///
+    /// # Safety
+    /// Calling this method if the `symbol` is larger than 3 is undefined behavior.
+    #[inline(always)]
+    pub(crate) unsafe fn occs_smaller_unchecked(&self, symbol: u8) -> usize {
+        match self {
+            Self::Binary { n, n_zeros, .. } => match symbol {
+                0 => 0,
+                1 => *n_zeros,
+                _ => *n,
+            },
+            Self::Quad(qv) => qv.occs_smaller_unchecked(symbol),
+        }
+    }
+
+    /// Prefetches counter of superblock and blocks containing the position `pos`.
+    /// Nothing is prefetched for a binary level.
+    #[inline(always)]
+    pub(crate) fn prefetch_info(&self, pos: usize) {
+        if let Self::Quad(qv) = self {
+            qv.prefetch_info(pos);
+        }
+    }
+
+    /// Prefetches data containing the position `pos`.
+    /// Nothing is prefetched for a binary level.
+    #[inline(always)]
+    pub(crate) fn prefetch_data(&self, pos: usize) {
+        if let Self::Quad(qv) = self {
+            qv.prefetch_data(pos);
+        }
+    }
+
+    /// Prefetches the counters accessed first by the query `select(symbol, i)`.
+    /// Nothing is prefetched for a binary level.
+    #[inline(always)]
+    pub(crate) fn prefetch_select(&self, symbol: u8, i: usize) {
+        if let Self::Quad(qv) = self {
+            qv.prefetch_select(symbol, i);
+        }
+    }
+}
+
+impl<RS: ZeroCopy> ZeroCopy for WTLevel<RS> {
+    fn write_layout<W: Write>(&self, writer: &mut LayoutWriter<W>) -> io::Result<()> {
+        match self {
+            Self::Binary { bv, n, n_zeros } => {
+                writer.write_u64(0)?;
+                writer.write_u64(*n as u64)?;
+                writer.write_u64(*n_zeros as u64)?;
+                bv.write_layout(writer)
+            }
+            Self::Quad(qv) => {
+                writer.write_u64(1)?;
+                qv.write_layout(writer)
+            }
+        }
+    }
+
+    unsafe fn read_layout(reader: &mut LayoutReader<'_>) -> Result<Self, LayoutError> {
+        match reader.read_u64()? {
+            0 => {
+                let n = reader.read_usize()?;
+                let n_zeros = reader.read_usize()?;
+                let bv = RSNarrow::read_layout(reader)?;
+                // The binary vector is checked, so its number of zeros is correct
+                if n == 0 || n != bv.bv_len() || n_zeros != bv.n_zeros() {
+                    return Err(LayoutError::InvalidData("binary level of the wavelet tree"));
+                }
+                Ok(Self::Binary { bv, n, n_zeros })
+            }
+            1 => Ok(Self::Quad(RS::read_layout(reader)?)),
+            _ => Err(LayoutError::InvalidData("kind of wavelet tree level")),
+        }
+    }
+}
+
+impl<RS: SpaceUsage> SpaceUsage for WTLevel<RS> {
+    /// Gives the space usage in bytes of the struct.
+    fn space_usage_byte(&self) -> usize {
+        match self {
+            Self::Binary { bv, .. } => 8 + 8 + bv.space_usage_byte(),
+            Self::Quad(qv) => qv.space_usage_byte(),
+        }
+    }
+}
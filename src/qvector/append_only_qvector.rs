@@ -0,0 +1,288 @@
+//! This module implements [`AppendOnlyRSQVector`], a quad vector that supports `access`,
+//! `rank`, and `select` queries together with appending symbols at the end.
+//!
+//! The symbols are stored in the same `DataLine`s of a [`QVector`](super::QVector) and
+//! the rank/select support has the same superblocks and blocks of an [`RSQVector`](crate::RSQVector).
+//! The counters of a superblock and of its blocks are written when they begin, so
+//! a `push` takes constant time and a full superblock is never modified again.
+//! The queries take the same time as the ones of an [`RSQVector`](crate::RSQVector).
+//!
+//! ## Examples
+//!
+//! ```
+//! use qwt::qvector::append_only_qvector::AppendOnlyRSQVector;
+//! use qwt::{AccessQuad, RankQuad, SelectQuad};
+//!
+//! let mut aqv: AppendOnlyRSQVector = [0u8, 1, 2, 3].into_iter().collect();
+//!
+//! aqv.push(3);
+//! assert_eq!(aqv.get(4), Some(3));
+//! assert_eq!(aqv.rank(3, 5), Some(2));
+//! assert_eq!(aqv.select(3, 1), Some(4));
+//! assert_eq!(aqv.len(), 5);
+//! ```
+
+use super::rs_qvector::AppendOnlyRSSupportPlain;
+use super::DataLine;
+
+use crate::utils::select_in_word_u128;
+use crate::{AccessQuad, RankQuad, SelectQuad, SpaceUsage};
+
+use num_traits::int::PrimInt;
+use num_traits::AsPrimitive;
+use serde::{Deserialize, Serialize};
+
+/// An append-only quad vector with support for `rank` and `select` queries.
+///
+/// The generic const `B_SIZE` specifies the number of symbols in each block,
+/// which is either 256 (default) or 512 as for [`RSQVector`](crate::RSQVector).
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AppendOnlyRSQVector<const B_SIZE: usize = 256> {
+    data: Vec<DataLine>,
+    len: usize,
+    rs_support: AppendOnlyRSSupportPlain<B_SIZE>,
+}
+
+impl<const B_SIZE: usize> AppendOnlyRSQVector<B_SIZE> {
+    /// Creates an empty append-only quad vector.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::qvector::append_only_qvector::AppendOnlyRSQVector;
+    ///
+    /// let aqv = AppendOnlyRSQVector::<512>::new();
+    /// assert!(aqv.is_empty());
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of symbols in the quad vector.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::qvector::append_only_qvector::AppendOnlyRSQVector;
+    ///
+    /// let aqv: AppendOnlyRSQVector = [0, 1, 2, 3].into_iter().cycle().take(10).collect();
+    /// assert_eq!(aqv.len(), 10);
+    /// ```
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the quad vector is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::qvector::append_only_qvector::AppendOnlyRSQVector;
+    ///
+    /// let aqv = AppendOnlyRSQVector::<256>::default();
+    /// assert!(aqv.is_empty());
+    /// ```
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Appends `symbol` at the end of the quad vector.
+    ///
+    /// ## Panics
+    /// Panics if `symbol` is larger than 3 or if the quad vector becomes longer
+    /// than 2^{43}-1 symbols.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::qvector::append_only_qvector::AppendOnlyRSQVector;
+    /// use qwt::RankQuad;
+    ///
+    /// let mut aqv = AppendOnlyRSQVector::<256>::new();
+    /// aqv.push(2);
+    /// aqv.push(2);
+    ///
+    /// assert_eq!(aqv.rank(2, 2), Some(2));
+    /// ```
+    pub fn push(&mut self, symbol: u8) {
+        assert!(symbol <= 3, "Only the four symbols in [0, 3] are possible.");
+        self.rs_support.push(self.len, symbol);
+
+        let pos_in_last_line = self.len & 255;
+        if pos_in_last_line == 0 {
+            self.data.push(DataLine::default());
+        }
+        self.data
+            .last_mut()
+            .unwrap()
+            .set_symbol(symbol, pos_in_last_line as u8);
+        self.len += 1;
+    }
+
+    /// Returns the number of occurrences of `symbol`, `None` if `symbol` is larger than 3.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::qvector::append_only_qvector::AppendOnlyRSQVector;
+    ///
+    /// let aqv: AppendOnlyRSQVector = [0u8, 1, 1, 3].into_iter().collect();
+    ///
+    /// assert_eq!(aqv.occs(1), Some(2));
+    /// assert_eq!(aqv.occs(2), Some(0));
+    /// assert_eq!(aqv.occs(4), None);
+    /// ```
+    #[must_use]
+    pub fn occs(&self, symbol: u8) -> Option<usize> {
+        if symbol > 3 {
+            return None;
+        }
+        Some(self.rs_support.occs(symbol))
+    }
+
+    /// Returns an iterator over the symbols of the quad vector.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::qvector::append_only_qvector::AppendOnlyRSQVector;
+    ///
+    /// let aqv: AppendOnlyRSQVector = [0u8, 1, 2, 3].into_iter().collect();
+    ///
+    /// assert_eq!(aqv.iter().collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        // SAFETY: all the positions are within the bounds
+        (0..self.len).map(|i| unsafe { self.get_unchecked(i) })
+    }
+}
+
+impl<const B_SIZE: usize> AccessQuad for AppendOnlyRSQVector<B_SIZE> {
+    /// Returns the symbol at position `i`, `None` if `i` is out of bounds.
+    #[inline]
+    fn get(&self, i: usize) -> Option<u8> {
+        if i >= self.len {
+            return None;
+        }
+        // SAFETY: bounds already checked
+        Some(unsafe { self.get_unchecked(i) })
+    }
+
+    /// Returns the symbol at position `i`.
+    ///
+    /// # Safety
+    /// Calling this method with an out-of-bounds index is undefined behavior.
+    #[inline]
+    unsafe fn get_unchecked(&self, i: usize) -> u8 {
+        self.data.get_unchecked(i >> 8).get_unchecked(i & 255)
+    }
+}
+
+impl<const B_SIZE: usize> RankQuad for AppendOnlyRSQVector<B_SIZE> {
+    /// Returns the number of occurrences of `symbol` up to position `i` excluded,
+    /// `None` if `i` is out of bounds or if `symbol` is larger than 3.
+    #[inline]
+    fn rank(&self, symbol: u8, i: usize) -> Option<usize> {
+        if symbol > 3 || i > self.len {
+            return None;
+        }
+        // SAFETY: checks above guarantee correctness
+        Some(unsafe { self.rank_unchecked(symbol, i) })
+    }
+
+    /// Returns the number of occurrences of `symbol` up to position `i` excluded.
+    ///
+    /// # Safety
+    /// Calling this method with an out-of-bounds index or with a symbol larger than
+    /// 3 is undefined behavior.
+    #[inline]
+    unsafe fn rank_unchecked(&self, symbol: u8, i: usize) -> usize {
+        // The block of position `len` may not begin yet
+        if i == self.len {
+            return self.rs_support.occs(symbol);
+        }
+
+        let line_id = i >> 8;
+        let first_line_id = (i / B_SIZE) * (B_SIZE / 256);
+        let mut rank = self.rs_support.rank_block(symbol, i);
+        for line in self.data.get_unchecked(first_line_id..line_id) {
+            rank += line.rank_unchecked(symbol, 256);
+        }
+        rank + self
+            .data
+            .get_unchecked(line_id)
+            .rank_unchecked(symbol, i & 255)
+    }
+}
+
+impl<const B_SIZE: usize> SelectQuad for AppendOnlyRSQVector<B_SIZE> {
+    /// Returns the position of the `i+1`-th occurrence of `symbol`, `None` if
+    /// there is no such occurrence or if `symbol` is larger than 3.
+    #[inline]
+    fn select(&self, symbol: u8, i: usize) -> Option<usize> {
+        if symbol > 3 || i >= self.rs_support.occs(symbol) {
+            return None;
+        }
+        // SAFETY: checks above guarantee correctness
+        Some(unsafe { self.select_unchecked(symbol, i) })
+    }
+
+    /// Returns the position of the `i+1`-th occurrence of `symbol`.
+    ///
+    /// # Safety
+    /// Calling this method if the `i+1`-th occurrence of `symbol` does not exist or with
+    /// a symbol larger than 3 is undefined behavior.
+    #[inline]
+    unsafe fn select_unchecked(&self, symbol: u8, i: usize) -> usize {
+        let (mut pos, rank) = self.rs_support.select_block(symbol, i + 1);
+        let mut i = i - rank;
+
+        // The occurrence is in the lines of the block, before the padding of the last line
+        for line in self.data.get_unchecked(pos >> 8..) {
+            let (word_0, word_1) = line.normalize(symbol);
+            for word in [word_0, word_1] {
+                let cnt = word.count_ones() as usize;
+                if cnt > i {
+                    return pos + select_in_word_u128(word, i as u64) as usize;
+                }
+                i -= cnt;
+                pos += 128;
+            }
+        }
+        unreachable!("The occurrence is within the quad vector.")
+    }
+}
+
+impl<const B_SIZE: usize> SpaceUsage for AppendOnlyRSQVector<B_SIZE> {
+    /// Returns the space usage of the data structure in bytes.
+    fn space_usage_byte(&self) -> usize {
+        self.data.space_usage_byte()
+            + self.len.space_usage_byte()
+            + self.rs_support.space_usage_byte()
+    }
+}
+
+impl<T, const B_SIZE: usize> FromIterator<T> for AppendOnlyRSQVector<B_SIZE>
+where
+    T: PrimInt + AsPrimitive<u8>,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut aqv = AppendOnlyRSQVector::new();
+        aqv.extend(iter);
+        aqv
+    }
+}
+
+impl<T, const B_SIZE: usize> Extend<T> for AppendOnlyRSQVector<B_SIZE>
+where
+    T: PrimInt + AsPrimitive<u8>,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        for symbol in iter {
+            self.push(symbol.as_());
+        }
+    }
+}
@@ -0,0 +1,519 @@
+//! This module implements [`DynamicQVector`], a quad vector that supports `access`,
+//! `rank`, and `select` queries together with insertions, deletions, and updates
+//! of symbols at arbitrary positions.
+//!
+//! The symbols are stored in the leaves of a balanced tree. Each leaf is a short
+//! sequence of `DataLine`s, i.e., the same lines of a [`QVector`](super::QVector),
+//! and each internal node stores the number of symbols and the occurrences of each
+//! of the four symbols in the subtree of each of its children.
+//! All the operations take $$O(\log n)$$ time.
+//!
+//! ## Examples
+//!
+//! ```
+//! use qwt::qvector::dynamic_qvector::DynamicQVector;
+//! use qwt::{AccessQuad, RankQuad, SelectQuad};
+//!
+//! let mut dqv: DynamicQVector = [0u8, 1, 2, 3].into_iter().collect();
+//!
+//! dqv.insert(1, 3);
+//! assert_eq!(dqv.get(1), Some(3));
+//! assert_eq!(dqv.rank(3, 5), Some(2));
+//!
+//! assert_eq!(dqv.remove(0), 0);
+//! dqv.set(0, 1);
+//! assert_eq!(dqv.select(1, 1), Some(1));
+//! assert_eq!(dqv.len(), 4);
+//! ```
+
+use super::DataLine;
+
+use crate::dynamic_tree::{DynamicLeaf, DynamicTree};
+use crate::utils::select_in_word_u128;
+use crate::{AccessQuad, RankQuad, SelectQuad, SpaceUsage};
+
+use num_traits::int::PrimInt;
+use num_traits::AsPrimitive;
+
+/// The number of `DataLine`s of a full leaf.
+const LEAF_LINES: usize = 8;
+
+// A leaf is a sequence of up to `LEAF_LINES` lines of 256 symbols each.
+// The symbols after the last one are always zeros.
+#[derive(Clone, Default, Debug)]
+struct QuadLeaf {
+    lines: Vec<DataLine>,
+    len: usize,
+}
+
+impl QuadLeaf {
+    // Returns the `w`-th 128-bit word of the plane of the higher bits (plane 0)
+    // or of the lower bits (plane 1) of the symbols.
+    #[inline(always)]
+    fn word(&self, plane: usize, w: usize) -> u128 {
+        self.lines[w >> 1].words[2 * plane + (w & 1)]
+    }
+
+    #[inline(always)]
+    fn word_mut(&mut self, plane: usize, w: usize) -> &mut u128 {
+        &mut self.lines[w >> 1].words[2 * plane + (w & 1)]
+    }
+
+    // Overwrites the symbol at position `i`.
+    #[inline]
+    fn write(&mut self, i: usize, symbol: u8) {
+        let shift = i & 127;
+        for plane in 0..2 {
+            let bit = ((symbol >> (1 - plane)) & 1) as u128;
+            let word = self.word_mut(plane, i >> 7);
+            *word = (*word & !(1 << shift)) | (bit << shift);
+        }
+    }
+}
+
+// Returns a mask with the `len` least significant bits set.
+#[inline(always)]
+fn low_mask(len: usize) -> u128 {
+    if len >= 128 {
+        u128::MAX
+    } else {
+        (1 << len) - 1
+    }
+}
+
+impl DynamicLeaf<4> for QuadLeaf {
+    const CAPACITY: usize = LEAF_LINES * 256;
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    fn get(&self, i: usize) -> u8 {
+        // SAFETY: the tree guarantees that `i` is smaller than the length
+        unsafe { self.lines[i >> 8].get_unchecked(i & 255) }
+    }
+
+    #[inline]
+    fn rank(&self, symbol: u8, i: usize) -> usize {
+        let full_lines = i >> 8;
+        // SAFETY: the tree guarantees that `symbol` is in [0, 3]
+        let mut rank = self.lines[..full_lines]
+            .iter()
+            .map(|line| unsafe { line.rank_unchecked(symbol, 256) })
+            .sum();
+        if i & 255 != 0 {
+            rank += unsafe { self.lines[full_lines].rank_unchecked(symbol, i & 255) };
+        }
+        rank
+    }
+
+    fn select(&self, symbol: u8, mut i: usize) -> usize {
+        for (line_id, line) in self.lines.iter().enumerate() {
+            // Symbols after the last one are zeros, so they must not be counted
+            let valid = self.len - line_id * 256;
+            let (word_0, word_1) = line.normalize(symbol);
+            let words = [
+                word_0 & low_mask(valid),
+                word_1 & low_mask(valid.saturating_sub(128)),
+            ];
+
+            for (j, word) in words.into_iter().enumerate() {
+                let cnt = word.count_ones() as usize;
+                if i < cnt {
+                    return line_id * 256 + j * 128 + select_in_word_u128(word, i as u64) as usize;
+                }
+                i -= cnt;
+            }
+        }
+        unreachable!("the tree guarantees that the occurrence exists")
+    }
+
+    fn insert(&mut self, i: usize, symbol: u8) {
+        if self.len == self.lines.len() * 256 {
+            self.lines.push(DataLine::default());
+        }
+
+        let first = i >> 7;
+        let last = self.len >> 7;
+        let mask = low_mask(i & 127);
+        for plane in 0..2 {
+            // Shifts by one position all the bits from `i` on, carrying the
+            // highest bit of each word to the next one
+            let word = self.word_mut(plane, first);
+            let mut carry = *word >> 127;
+            *word = (*word & mask) | ((*word & !mask) << 1);
+            for w in first + 1..=last {
+                let word = self.word_mut(plane, w);
+                let next_carry = *word >> 127;
+                *word = (*word << 1) | carry;
+                carry = next_carry;
+            }
+        }
+
+        self.len += 1;
+        self.write(i, symbol);
+    }
+
+    fn remove(&mut self, i: usize) -> u8 {
+        let symbol = self.get(i);
+
+        let first = i >> 7;
+        let last = (self.len - 1) >> 7;
+        let mask = low_mask(i & 127);
+        for plane in 0..2 {
+            for w in first..=last {
+                let next_bit = if w < last {
+                    self.word(plane, w + 1) & 1
+                } else {
+                    0
+                };
+                let word = self.word_mut(plane, w);
+                *word = if w == first {
+                    (*word & mask) | ((*word >> 1) & !mask)
+                } else {
+                    *word >> 1
+                };
+                *word |= next_bit << 127;
+            }
+        }
+
+        self.len -= 1;
+        if self.len.is_multiple_of(256) {
+            self.lines.pop();
+        }
+        symbol
+    }
+
+    #[inline]
+    fn set(&mut self, i: usize, symbol: u8) -> u8 {
+        let old = self.get(i);
+        self.write(i, symbol);
+        old
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.lines.truncate(len.div_ceil(256));
+        for plane in 0..2 {
+            for w in (len >> 7)..2 * self.lines.len() {
+                let mask = if w == len >> 7 {
+                    low_mask(len & 127)
+                } else {
+                    0
+                };
+                *self.word_mut(plane, w) &= mask;
+            }
+        }
+        self.len = len;
+    }
+}
+
+impl SpaceUsage for QuadLeaf {
+    fn space_usage_byte(&self) -> usize {
+        self.lines.capacity() * std::mem::size_of::<DataLine>() + self.len.space_usage_byte()
+    }
+}
+
+/// A quad vector with symbols in [0, 3] that supports insertions, deletions,
+/// and updates of symbols, besides `access`, `rank`, and `select` queries.
+#[derive(Clone, Default, Debug)]
+pub struct DynamicQVector {
+    tree: DynamicTree<QuadLeaf, 4>,
+}
+
+impl DynamicQVector {
+    /// Creates an empty dynamic quad vector.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::qvector::dynamic_qvector::DynamicQVector;
+    ///
+    /// let dqv = DynamicQVector::new();
+    /// assert!(dqv.is_empty());
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of symbols in the quad vector.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::qvector::dynamic_qvector::DynamicQVector;
+    ///
+    /// let dqv: DynamicQVector = [0, 1, 2, 3].into_iter().cycle().take(10).collect();
+    /// assert_eq!(dqv.len(), 10);
+    /// ```
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Checks if the quad vector is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::qvector::dynamic_qvector::DynamicQVector;
+    ///
+    /// let dqv = DynamicQVector::default();
+    /// assert!(dqv.is_empty());
+    /// ```
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.tree.len() == 0
+    }
+
+    /// Inserts `symbol` at position `i`, shifting all the following symbols to the right.
+    ///
+    /// ## Panics
+    /// Panics if `i` is larger than the length or if `symbol` is larger than 3.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::qvector::dynamic_qvector::DynamicQVector;
+    /// use qwt::AccessQuad;
+    ///
+    /// let mut dqv: DynamicQVector = [0u8, 1, 2].into_iter().collect();
+    /// dqv.insert(0, 3);
+    /// dqv.insert(4, 3);
+    ///
+    /// assert_eq!(dqv.get(0), Some(3));
+    /// assert_eq!(dqv.get(1), Some(0));
+    /// assert_eq!(dqv.get(4), Some(3));
+    /// ```
+    pub fn insert(&mut self, i: usize, symbol: u8) {
+        assert!(i <= self.len(), "The position {i} is out of bounds.");
+        assert!(symbol <= 3, "Only the four symbols in [0, 3] are possible.");
+        self.tree.insert(i, symbol);
+    }
+
+    /// Appends `symbol` at the end of the quad vector.
+    ///
+    /// ## Panics
+    /// Panics if `symbol` is larger than 3.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::qvector::dynamic_qvector::DynamicQVector;
+    /// use qwt::AccessQuad;
+    ///
+    /// let mut dqv = DynamicQVector::new();
+    /// dqv.push(2);
+    ///
+    /// assert_eq!(dqv.get(0), Some(2));
+    /// ```
+    pub fn push(&mut self, symbol: u8) {
+        assert!(symbol <= 3, "Only the four symbols in [0, 3] are possible.");
+        self.tree.push(symbol);
+    }
+
+    /// Removes and returns the symbol at position `i`, shifting all the following
+    /// symbols to the left.
+    ///
+    /// ## Panics
+    /// Panics if `i` is out of bounds.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::qvector::dynamic_qvector::DynamicQVector;
+    /// use qwt::AccessQuad;
+    ///
+    /// let mut dqv: DynamicQVector = [0u8, 1, 2].into_iter().collect();
+    ///
+    /// assert_eq!(dqv.remove(1), 1);
+    /// assert_eq!(dqv.get(1), Some(2));
+    /// assert_eq!(dqv.len(), 2);
+    /// ```
+    pub fn remove(&mut self, i: usize) -> u8 {
+        assert!(i < self.len(), "The position {i} is out of bounds.");
+        self.tree.remove(i)
+    }
+
+    /// Replaces the symbol at position `i` with `symbol` and returns the old one.
+    ///
+    /// ## Panics
+    /// Panics if `i` is out of bounds or if `symbol` is larger than 3.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::qvector::dynamic_qvector::DynamicQVector;
+    /// use qwt::RankQuad;
+    ///
+    /// let mut dqv: DynamicQVector = [0u8, 1, 2].into_iter().collect();
+    ///
+    /// assert_eq!(dqv.set(1, 2), 1);
+    /// assert_eq!(dqv.rank(2, 3), Some(2));
+    /// ```
+    pub fn set(&mut self, i: usize, symbol: u8) -> u8 {
+        assert!(i < self.len(), "The position {i} is out of bounds.");
+        assert!(symbol <= 3, "Only the four symbols in [0, 3] are possible.");
+        self.tree.set(i, symbol)
+    }
+
+    /// Returns the number of occurrences of `symbol`, `None` if `symbol` is larger than 3.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::qvector::dynamic_qvector::DynamicQVector;
+    ///
+    /// let dqv: DynamicQVector = [0u8, 1, 1, 3].into_iter().collect();
+    ///
+    /// assert_eq!(dqv.occs(1), Some(2));
+    /// assert_eq!(dqv.occs(2), Some(0));
+    /// assert_eq!(dqv.occs(4), None);
+    /// ```
+    #[must_use]
+    pub fn occs(&self, symbol: u8) -> Option<usize> {
+        (symbol <= 3).then(|| self.tree.occs(symbol))
+    }
+
+    /// Returns the number of occurrences of all the symbols smaller than `symbol`,
+    /// `None` if `symbol` is larger than 3.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::qvector::dynamic_qvector::DynamicQVector;
+    ///
+    /// let dqv: DynamicQVector = [0u8, 1, 1, 3].into_iter().collect();
+    ///
+    /// assert_eq!(dqv.occs_smaller(0), Some(0));
+    /// assert_eq!(dqv.occs_smaller(3), Some(3));
+    /// ```
+    #[must_use]
+    pub fn occs_smaller(&self, symbol: u8) -> Option<usize> {
+        (symbol <= 3).then(|| (0..symbol).map(|s| self.tree.occs(s)).sum())
+    }
+
+    /// Returns an iterator over the symbols of the quad vector.
+    ///
+    /// # Examples
+    /// ```
+    /// use qwt::qvector::dynamic_qvector::DynamicQVector;
+    ///
+    /// let dqv: DynamicQVector = [0u8, 1, 2, 3].into_iter().cycle().take(1000).collect();
+    ///
+    /// for (i, symbol) in dqv.iter().enumerate() {
+    ///     assert_eq!((i % 4) as u8, symbol);
+    /// }
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        self.tree
+            .leaves()
+            .into_iter()
+            .flat_map(|leaf| (0..leaf.len()).map(move |i| leaf.get(i)))
+    }
+}
+
+impl AccessQuad for DynamicQVector {
+    /// Returns the symbol at position `i`, `None` if `i` is out of bounds.
+    #[inline]
+    fn get(&self, i: usize) -> Option<u8> {
+        if i >= self.len() {
+            return None;
+        }
+        // SAFETY: bounds already checked
+        Some(unsafe { self.get_unchecked(i) })
+    }
+
+    /// Returns the symbol at position `i`.
+    ///
+    /// # Safety
+    /// Calling this method with an out-of-bounds index is undefined behavior.
+    #[inline]
+    unsafe fn get_unchecked(&self, i: usize) -> u8 {
+        self.tree.get(i)
+    }
+}
+
+impl RankQuad for DynamicQVector {
+    /// Returns the number of occurrences of `symbol` up to position `i` excluded,
+    /// `None` if `i` is out of bounds or if `symbol` is larger than 3.
+    #[inline]
+    fn rank(&self, symbol: u8, i: usize) -> Option<usize> {
+        if symbol > 3 || i > self.len() {
+            return None;
+        }
+        // SAFETY: checks above guarantee correctness
+        Some(unsafe { self.rank_unchecked(symbol, i) })
+    }
+
+    /// Returns the number of occurrences of `symbol` up to position `i` excluded.
+    ///
+    /// # Safety
+    /// Calling this method with an out-of-bounds index or with a symbol larger than
+    /// 3 is undefined behavior.
+    #[inline]
+    unsafe fn rank_unchecked(&self, symbol: u8, i: usize) -> usize {
+        self.tree.rank(symbol, i)
+    }
+}
+
+impl SelectQuad for DynamicQVector {
+    /// Returns the position of the `i+1`-th occurrence of `symbol`, `None` if
+    /// there is no such occurrence or if `symbol` is larger than 3.
+    #[inline]
+    fn select(&self, symbol: u8, i: usize) -> Option<usize> {
+        if symbol > 3 || i >= self.tree.occs(symbol) {
+            return None;
+        }
+        // SAFETY: checks above guarantee correctness
+        Some(unsafe { self.select_unchecked(symbol, i) })
+    }
+
+    /// Returns the position of the `i+1`-th occurrence of `symbol`.
+    ///
+    /// # Safety
+    /// Calling this method if the `i+1`-th occurrence of `symbol` does not exist or with
+    /// a symbol larger than 3 is undefined behavior.
+    #[inline]
+    unsafe fn select_unchecked(&self, symbol: u8, i: usize) -> usize {
+        self.tree.select(symbol, i)
+    }
+}
+
+impl SpaceUsage for DynamicQVector {
+    /// Returns the space usage of the data structure in bytes.
+    fn space_usage_byte(&self) -> usize {
+        self.tree.space_usage_byte()
+    }
+}
+
+/// Two dynamic quad vectors are equal if they store the same symbols,
+/// regardless of the shape of their trees.
+impl PartialEq for DynamicQVector {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for DynamicQVector {}
+
+impl<T> FromIterator<T> for DynamicQVector
+where
+    T: PrimInt + AsPrimitive<u8>,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut dqv = DynamicQVector::new();
+        dqv.extend(iter);
+        dqv
+    }
+}
+
+impl<T> Extend<T> for DynamicQVector
+where
+    T: PrimInt + AsPrimitive<u8>,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        for symbol in iter {
+            self.push(symbol.as_());
+        }
+    }
+}
@@ -6,8 +6,11 @@
 //! The use of `DataLine` in our setting is particulary conveninet because a vector of `DataLine` is aligned.
 //! This way, we load just one cache line everytime we access a `DataLine`.
 
+use crate::zero_copy::{LayoutError, LayoutReader, LayoutWriter, Pod, Storage, ZeroCopy};
 use crate::{AccessQuad, RankQuad, SpaceUsage}; // Traits
 
+use std::io::{self, Write};
+
 use num_traits::int::PrimInt;
 use num_traits::AsPrimitive;
 
@@ -136,7 +139,7 @@ impl SpaceUsage for DataLine {
 
 #[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
 pub struct QVector {
-    data: Box<[DataLine]>,
+    data: Storage<DataLine>,
     position: usize,
 }
 
@@ -240,6 +243,27 @@ impl SpaceUsage for QVector {
     }
 }
 
+// SAFETY: a data line is made of 64 bytes of plain words
+unsafe impl Pod for DataLine {}
+
+impl ZeroCopy for QVector {
+    fn write_layout<W: Write>(&self, writer: &mut LayoutWriter<W>) -> io::Result<()> {
+        writer.write_u64(self.position as u64)?;
+        writer.write_slice(&self.data)
+    }
+
+    unsafe fn read_layout(reader: &mut LayoutReader<'_>) -> Result<Self, LayoutError> {
+        let position = reader.read_usize()?;
+        let data = reader.read_storage()?;
+
+        if position > data.len() * 512 || !position.is_multiple_of(2) {
+            return Err(LayoutError::InvalidData("quad vector"));
+        }
+
+        Ok(Self { data, position })
+    }
+}
+
 impl AsRef<QVector> for QVector {
     fn as_ref(&self) -> &QVector {
         self
@@ -311,7 +335,7 @@ impl QVectorBuilder {
     /// Build the `qvector`.
     pub fn build(self) -> QVector {
         QVector {
-            data: self.data.into_boxed_slice(),
+            data: self.data.into(),
             position: self.position,
         }
     }
@@ -333,6 +357,18 @@ impl QVectorBuilder {
         }
     }
 
+    /// Appends all the symbols of `other` at the end of the quad vector.
+    ///
+    /// # Panics
+    /// Panics if the length of the quad vector is not a multiple of 256,
+    /// i.e., if its last data line is not full.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn append(&mut self, other: Self) {
+        assert_eq!((self.position / 2) & 255, 0, "The last line must be full.");
+        self.data.extend(other.data);
+        self.position += other.position;
+    }
+
     /// Appends the (last 2 bits of the) value `symbol` at the end
     /// of the quad vector.
     ///
@@ -386,6 +422,8 @@ where
     }
 }
 
+pub mod append_only_qvector;
+pub mod dynamic_qvector;
 pub mod rs_qvector;
 
 #[cfg(test)]
@@ -10,10 +10,15 @@ use num_traits::{AsPrimitive, Unsigned};
 use serde::{Deserialize, Serialize};
 
 // Traits
+use crate::persist::Persist;
+use crate::zero_copy::{LayoutError, LayoutReader, LayoutWriter, ZeroCopy};
 use crate::{AccessQuad, RankQuad, SelectQuad, SpaceUsage, WTSupport};
 
+use std::io::{self, Write};
+
 /// Alternative representations to support Rank/Select queries at the level of blocks
 mod rs_support_plain;
+pub(crate) use crate::qvector::rs_qvector::rs_support_plain::AppendOnlyRSSupportPlain;
 use crate::qvector::rs_qvector::rs_support_plain::RSSupportPlain;
 
 /// Possible specializations which provide different space/time trade-offs.
@@ -372,6 +377,8 @@ impl<S: RSSupport> SelectQuad for RSQVector<S> {
 }
 
 impl<S: RSSupport> WTSupport for RSQVector<S> {
+    const MAX_LEN: usize = S::MAX_LEN;
+
     /// Returns the number of occurrences of `symbol` in the indexed sequence,
     /// `None` if `symbol` is not in [0..3].  
     #[inline(always)]
@@ -443,6 +450,75 @@ impl<S: RSSupport> WTSupport for RSQVector<S> {
             prefetch_read_NTA(&self.qv.data, if line_id > 0 { line_id - 1 } else { 0 });
         }
     }
+
+    /// Prefetches the first superblock scanned by the query `select(symbol, i)`.
+    #[inline(always)]
+    fn prefetch_select(&self, symbol: u8, i: usize) {
+        if symbol <= 3 {
+            self.rs_support.prefetch_select(symbol, i + 1);
+        }
+    }
+}
+
+impl<S: RSSupport + ZeroCopy + PartialEq> Persist for RSQVector<S> {
+    fn type_tag() -> String {
+        format!("RSQVector<{}>", S::BLOCK_SIZE)
+    }
+
+    fn persisted_len(&self) -> usize {
+        self.qv.len()
+    }
+}
+
+impl<S: RSSupport + ZeroCopy + PartialEq> ZeroCopy for RSQVector<S> {
+    fn write_layout<W: Write>(&self, writer: &mut LayoutWriter<W>) -> io::Result<()> {
+        self.qv.write_layout(writer)?;
+        self.rs_support.write_layout(writer)?;
+        for &occs in &self.n_occs_smaller {
+            writer.write_u64(occs as u64)?;
+        }
+        Ok(())
+    }
+
+    unsafe fn read_layout(reader: &mut LayoutReader<'_>) -> Result<Self, LayoutError> {
+        let qv = QVector::read_layout(reader)?;
+        let rs_support = S::read_layout(reader)?;
+        let mut n_occs_smaller = [0; 5];
+        for occs in &mut n_occs_smaller {
+            *occs = reader.read_usize()?;
+        }
+
+        if qv.len() > S::MAX_LEN || !rs_support.has_len(qv.len()) {
+            return Err(LayoutError::InvalidData("rank/select support of RSQVector"));
+        }
+        if n_occs_smaller[0] != 0
+            || n_occs_smaller[4] != qv.len()
+            || n_occs_smaller.windows(2).any(|w| w[0] > w[1])
+        {
+            return Err(LayoutError::InvalidData("occurrences of RSQVector"));
+        }
+        if reader.is_trusted() {
+            return Ok(Self {
+                qv,
+                rs_support,
+                n_occs_smaller,
+            });
+        }
+
+        // The counters are checked by recomputing them
+        let expected = Self::from(qv);
+        if expected.rs_support != rs_support {
+            return Err(LayoutError::InvalidData("rank/select support of RSQVector"));
+        }
+        if expected.n_occs_smaller != n_occs_smaller {
+            return Err(LayoutError::InvalidData("occurrences of RSQVector"));
+        }
+        Ok(Self {
+            qv: expected.qv,
+            rs_support,
+            n_occs_smaller,
+        })
+    }
 }
 
 impl<S> AsRef<RSQVector<S>> for RSQVector<S> {
@@ -474,6 +550,10 @@ impl<'a, S> IntoIterator for &'a RSQVector<S> {
 pub trait RSSupport {
     const BLOCK_SIZE: usize;
 
+    /// The largest length of a quad vector that can be indexed.
+    /// The default is no limit.
+    const MAX_LEN: usize = usize::MAX;
+
     fn new(qv: &QVector) -> Self;
 
     /// Returns the number of occurrences of `SYMBOL` up to the beginning
@@ -488,6 +568,18 @@ pub trait RSSupport {
     fn select_block(&self, symbol: u8, i: usize) -> (usize, usize);
 
     fn prefetch(&self, pos: usize);
+
+    /// Prefetches the first superblock scanned by `select_block(symbol, i)`.
+    /// The default implementation prefetches nothing.
+    #[inline(always)]
+    fn prefetch_select(&self, _symbol: u8, _i: usize) {}
+
+    /// Checks if the arrays of the support have the lengths needed to index a
+    /// quad vector of `len` symbols, e.g., after reading the support from a layout.
+    /// The default implementation checks nothing.
+    fn has_len(&self, _len: usize) -> bool {
+        true
+    }
 }
 
 impl<T, S: RSSupport> FromIterator<T> for RSQVector<S>
@@ -4,19 +4,30 @@
 
 use crate::qvector::rs_qvector::RSSupport;
 use crate::utils::prefetch_read_NTA;
+use crate::zero_copy::{LayoutError, LayoutReader, LayoutWriter, Pod, Storage, ZeroCopy};
 use crate::QVector;
 use crate::{AccessQuad, SpaceUsage}; // Traits
 
+use std::io::{self, Write};
+
 use serde::{Deserialize, Serialize};
 
 /// The generic const `B_SIZE` specifies the number of symbols in each block.
 /// The possible values are 256 (default) and 512.
 /// The space overhead for 256 is 12.5% while 512 halves this
 /// space overhead (6.25%) at the cost of (slightly) increasing the query time.
-#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct RSSupportPlain<const B_SIZE: usize = 256> {
-    superblocks: Box<[SuperblockPlain]>,
-    select_samples: [Box<[u32]>; 4],
+    superblocks: Storage<SuperblockPlain>,
+    select_samples: [Storage<u32>; 4],
+}
+
+impl<const B_SIZE: usize> Default for RSSupportPlain<B_SIZE> {
+    /// Returns the support of an empty quad vector, which has a superblock
+    /// for position 0 as any other support.
+    fn default() -> Self {
+        Self::new(&QVector::default())
+    }
 }
 
 impl<const B_SIZE: usize> SpaceUsage for RSSupportPlain<B_SIZE> {
@@ -33,74 +44,27 @@ impl<const B_SIZE: usize> SpaceUsage for RSSupportPlain<B_SIZE> {
 impl<const B_SIZE: usize> RSSupport for RSSupportPlain<B_SIZE> {
     const BLOCK_SIZE: usize = B_SIZE;
 
+    // See `RSQVector::new` for the reasons of this limit
+    const MAX_LEN: usize = (1 << 43) - 1;
+
     fn new(qv: &QVector) -> Self {
-        assert!(qv.len() < (1 << 43));
+        assert!(
+            qv.len() <= Self::MAX_LEN,
+            "The quad vector is longer than 2^43-1 symbols."
+        );
 
         assert!(
             (Self::BLOCK_SIZE == 256) | (Self::BLOCK_SIZE == 512),
             "Block size is either 256 or 512 symbols."
         );
 
-        // A counter for each symbol
-        //    - from the beginning of the sequence
-        //    - from the beginning of the superblock
-        let mut superblock_counters = [0; 4];
-        let mut block_counters = [0; 4];
-        let mut occs = [0; 4];
-
-        // Sample superblock ids for each symbol at every SELECT_SAMPLES occurrence
-        let mut select_samples: [Vec<u32>; 4] = [Vec::new(), Vec::new(), Vec::new(), Vec::new()];
-
-        // Number of symbols in each superblock
-        let superblock_size: usize = Self::BLOCKS_IN_SUPERBLOCK * Self::BLOCK_SIZE;
-        let n_superblocks = (qv.len() + superblock_size) / superblock_size;
-        let mut superblocks = Vec::<SuperblockPlain>::with_capacity(n_superblocks);
-
-        for i in 0..qv.len() + 1 {
-            // Need position qv.len() to make last superblock if needed
-
-            if i % superblock_size == 0 {
-                superblocks.push(SuperblockPlain::new(&superblock_counters));
-                block_counters = [0; 4]; // reset block counters
-            }
-
-            if i % Self::BLOCK_SIZE == 0 {
-                // Start a new block and add occs in the block to its counter
-                let block_id = (i / Self::BLOCK_SIZE) % Self::BLOCKS_IN_SUPERBLOCK;
-
-                superblocks
-                    .last_mut()
-                    .unwrap()
-                    .set_block_counters(block_id, &block_counters);
-
-                for symbol in 0..4u8 {
-                    // just check if everything is ok
-                    debug_assert_eq!(
-                        block_counters[symbol as usize],
-                        superblocks
-                            .last()
-                            .unwrap()
-                            .get_block_counter(symbol, block_id)
-                    );
-                }
-            }
+        #[cfg(not(feature = "parallel"))]
+        let (mut superblocks, mut select_samples, block_counters) =
+            Self::build_superblocks(qv, 0, qv.len(), [0; 4]);
 
-            if i < qv.len() {
-                // Safety: We are sure to be not out of bound
-                let symbol = unsafe { qv.get_unchecked(i) as usize };
-
-                if occs[symbol] % Self::SELECT_NUM_SAMPLES == 0 {
-                    // we store a superblock id in a u32. Make sure it fits.
-                    debug_assert!(Self::superblock_index(i) <= u32::MAX as usize);
-                    debug_assert!(Self::superblock_index(i) < superblocks.len());
-                    select_samples[symbol].push(Self::superblock_index(i) as u32);
-                }
-
-                superblock_counters[symbol] += 1;
-                block_counters[symbol] += 1;
-                occs[symbol] += 1;
-            }
-        }
+        #[cfg(feature = "parallel")]
+        let (mut superblocks, mut select_samples, block_counters) =
+            Self::build_superblocks_parallel(qv, crate::utils::n_threads());
 
         // Fill the next blocks with max occurrences. This is a sentinel for `select` query algorithm.
         let next_block_id = (qv.len() / Self::BLOCK_SIZE) % Self::BLOCKS_IN_SUPERBLOCK + 1;
@@ -122,10 +86,10 @@ impl<const B_SIZE: usize> RSSupport for RSSupportPlain<B_SIZE> {
         }
 
         Self {
-            superblocks: superblocks.into_boxed_slice(),
+            superblocks: superblocks.into(),
             select_samples: select_samples
                 .into_iter()
-                .map(|sample| sample.into_boxed_slice())
+                .map(Storage::from)
                 .collect::<Vec<_>>()
                 .try_into()
                 .unwrap(), // all this just to convert Vecs into boxed slices (and because we cannot collect into an array directly)
@@ -199,6 +163,180 @@ impl<const B_SIZE: usize> RSSupport for RSSupportPlain<B_SIZE> {
 
         prefetch_read_NTA(&self.superblocks, superblock_index);
     }
+
+    #[inline(always)]
+    fn prefetch_select(&self, symbol: u8, i: usize) {
+        // The sample is missing if `i` is zero or too large, the query will fail anyway
+        let sampled_i = i.wrapping_sub(1) / Self::SELECT_NUM_SAMPLES;
+        if let Some(&first_sblock_id) = self.select_samples[symbol as usize].get(sampled_i) {
+            prefetch_read_NTA(&self.superblocks, first_sblock_id as usize);
+        }
+    }
+
+    fn has_len(&self, len: usize) -> bool {
+        // There is a superblock also for position `len`, see `build_superblocks`
+        self.superblocks.len() == Self::superblock_index(len) + 1
+            && self.select_samples.iter().all(|samples| samples.len() >= 2)
+    }
+}
+
+impl<const B_SIZE: usize> RSSupportPlain<B_SIZE> {
+    /// Builds the superblocks and the select samples of the positions in `[start, end)`
+    /// of `qv`, where `start` is the beginning of a superblock and `occs` are the
+    /// occurrences of each symbol before `start`.
+    /// If `end` is the length of `qv`, the position `end` is processed too to make
+    /// the last superblock if needed.
+    ///
+    /// Returns the superblocks, the select samples, and the block counters at the end.
+    #[allow(clippy::type_complexity)]
+    fn build_superblocks(
+        qv: &QVector,
+        start: usize,
+        end: usize,
+        occs: [usize; 4],
+    ) -> (Vec<SuperblockPlain>, [Vec<u32>; 4], [usize; 4]) {
+        // A counter for each symbol
+        //    - from the beginning of the sequence
+        //    - from the beginning of the superblock
+        let mut superblock_counters = occs;
+        let mut block_counters = [0; 4];
+        let mut occs = occs;
+
+        // Sample superblock ids for each symbol at every SELECT_SAMPLES occurrence
+        let mut select_samples: [Vec<u32>; 4] = [Vec::new(), Vec::new(), Vec::new(), Vec::new()];
+
+        // Number of symbols in each superblock
+        let superblock_size: usize = Self::BLOCKS_IN_SUPERBLOCK * Self::BLOCK_SIZE;
+        let n_superblocks = (end - start + superblock_size) / superblock_size;
+        let mut superblocks = Vec::<SuperblockPlain>::with_capacity(n_superblocks);
+
+        // Need position qv.len() to make last superblock if needed
+        let stop = if end == qv.len() { end + 1 } else { end };
+
+        for i in start..stop {
+            if i % superblock_size == 0 {
+                superblocks.push(SuperblockPlain::new(&superblock_counters));
+                block_counters = [0; 4]; // reset block counters
+            }
+
+            if i % Self::BLOCK_SIZE == 0 {
+                // Start a new block and add occs in the block to its counter
+                let block_id = (i / Self::BLOCK_SIZE) % Self::BLOCKS_IN_SUPERBLOCK;
+
+                superblocks
+                    .last_mut()
+                    .unwrap()
+                    .set_block_counters(block_id, &block_counters);
+
+                for symbol in 0..4u8 {
+                    // just check if everything is ok
+                    debug_assert_eq!(
+                        block_counters[symbol as usize],
+                        superblocks
+                            .last()
+                            .unwrap()
+                            .get_block_counter(symbol, block_id)
+                    );
+                }
+            }
+
+            if i < qv.len() {
+                // Safety: We are sure to be not out of bound
+                let symbol = unsafe { qv.get_unchecked(i) as usize };
+
+                if occs[symbol].is_multiple_of(Self::SELECT_NUM_SAMPLES) {
+                    // we store a superblock id in a u32. Make sure it fits.
+                    debug_assert!(Self::superblock_index(i) <= u32::MAX as usize);
+                    debug_assert!(
+                        Self::superblock_index(i) - Self::superblock_index(start)
+                            < superblocks.len()
+                    );
+                    select_samples[symbol].push(Self::superblock_index(i) as u32);
+                }
+
+                superblock_counters[symbol] += 1;
+                block_counters[symbol] += 1;
+                occs[symbol] += 1;
+            }
+        }
+
+        (superblocks, select_samples, block_counters)
+    }
+
+    /// Builds the superblocks and the select samples of `qv` with `n_threads` threads.
+    ///
+    /// The sequence is split into chunks of whole superblocks. A first pass counts
+    /// the occurrences of each symbol in every chunk, a second pass builds the
+    /// superblocks of each chunk starting from the counters of the previous ones.
+    /// The result is identical to the one of the sequential construction.
+    #[cfg(feature = "parallel")]
+    #[allow(clippy::type_complexity)]
+    fn build_superblocks_parallel(
+        qv: &QVector,
+        n_threads: usize,
+    ) -> (Vec<SuperblockPlain>, [Vec<u32>; 4], [usize; 4]) {
+        let superblock_size: usize = Self::BLOCKS_IN_SUPERBLOCK * Self::BLOCK_SIZE;
+        let n_superblocks = qv.len().div_ceil(superblock_size);
+
+        if n_threads <= 1 || n_superblocks <= 1 {
+            return Self::build_superblocks(qv, 0, qv.len(), [0; 4]);
+        }
+
+        let chunk_size = n_superblocks.div_ceil(n_threads) * superblock_size;
+        let chunks: Vec<(usize, usize)> = (0..qv.len())
+            .step_by(chunk_size)
+            .map(|start| (start, (start + chunk_size).min(qv.len())))
+            .collect();
+
+        // Occurrences of each symbol before the beginning of each chunk
+        let mut chunk_occs: Vec<[usize; 4]> = std::thread::scope(|s| {
+            let handles: Vec<_> = chunks
+                .iter()
+                .map(|&(start, end)| {
+                    s.spawn(move || {
+                        let mut occs = [0; 4];
+                        for i in start..end {
+                            // Safety: We are sure to be not out of bound
+                            occs[unsafe { qv.get_unchecked(i) } as usize] += 1;
+                        }
+                        occs
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let mut occs = [0; 4];
+        for chunk_occ in chunk_occs.iter_mut() {
+            for (chunk_count, count) in chunk_occ.iter_mut().zip(occs.iter_mut()) {
+                (*chunk_count, *count) = (*count, *count + *chunk_count);
+            }
+        }
+
+        let results: Vec<_> = std::thread::scope(|s| {
+            let handles: Vec<_> = chunks
+                .iter()
+                .zip(chunk_occs)
+                .map(|(&(start, end), occs)| {
+                    s.spawn(move || Self::build_superblocks(qv, start, end, occs))
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let mut superblocks = Vec::with_capacity(n_superblocks + 1);
+        let mut select_samples: [Vec<u32>; 4] = [Vec::new(), Vec::new(), Vec::new(), Vec::new()];
+        let mut block_counters = [0; 4];
+        for (chunk_superblocks, chunk_select_samples, chunk_block_counters) in results {
+            superblocks.extend(chunk_superblocks);
+            for (samples, chunk_samples) in select_samples.iter_mut().zip(chunk_select_samples) {
+                samples.extend(chunk_samples);
+            }
+            block_counters = chunk_block_counters;
+        }
+
+        (superblocks, select_samples, block_counters)
+    }
 }
 
 impl<const B_SIZE: usize> RSSupportPlain<B_SIZE> {
@@ -216,6 +354,124 @@ impl<const B_SIZE: usize> RSSupportPlain<B_SIZE> {
     }
 }
 
+/// The rank/select support of an [`AppendOnlyRSQVector`](crate::qvector::append_only_qvector::AppendOnlyRSQVector).
+///
+/// It has the same superblocks and select samples of [`RSSupportPlain`], which are built
+/// while the symbols are appended. The counters of a superblock and of each of its blocks
+/// are written when they begin, so a superblock is sealed, i.e., it does not change anymore,
+/// as soon as the next one begins.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub(crate) struct AppendOnlyRSSupportPlain<const B_SIZE: usize = 256> {
+    superblocks: Vec<SuperblockPlain>,
+    select_samples: [Vec<u32>; 4],
+    occs: [usize; 4],           // The occurrences of each symbol
+    block_counters: [usize; 4], // The occurrences of each symbol in the last superblock
+}
+
+impl<const B_SIZE: usize> AppendOnlyRSSupportPlain<B_SIZE> {
+    /// Updates the counters with `symbol` appended at position `i`,
+    /// which is the length of the indexed quad vector.
+    ///
+    /// # Panics
+    /// Panics if the quad vector becomes longer than 2^{43}-1 symbols.
+    #[inline]
+    pub(crate) fn push(&mut self, i: usize, symbol: u8) {
+        assert!(
+            (B_SIZE == 256) | (B_SIZE == 512),
+            "Block size is either 256 or 512 symbols."
+        );
+        assert!(
+            i < RSSupportPlain::<B_SIZE>::MAX_LEN,
+            "The quad vector is longer than 2^43-1 symbols."
+        );
+
+        let superblock_size = RSSupportPlain::<B_SIZE>::BLOCKS_IN_SUPERBLOCK * B_SIZE;
+        if i.is_multiple_of(superblock_size) {
+            self.superblocks
+                .push(SuperblockPlain::with_block_sentinels(&self.occs));
+            self.block_counters = [0; 4];
+        }
+
+        if i.is_multiple_of(B_SIZE) {
+            let block_id = (i / B_SIZE) % RSSupportPlain::<B_SIZE>::BLOCKS_IN_SUPERBLOCK;
+            self.superblocks
+                .last_mut()
+                .unwrap()
+                .set_block_counters(block_id, &self.block_counters);
+        }
+
+        let symbol = symbol as usize;
+        if self.occs[symbol].is_multiple_of(RSSupportPlain::<B_SIZE>::SELECT_NUM_SAMPLES) {
+            self.select_samples[symbol].push(RSSupportPlain::<B_SIZE>::superblock_index(i) as u32);
+        }
+        self.occs[symbol] += 1;
+        self.block_counters[symbol] += 1;
+    }
+
+    /// Returns the number of occurrences of `symbol`.
+    #[inline(always)]
+    pub(crate) fn occs(&self, symbol: u8) -> usize {
+        self.occs[symbol as usize]
+    }
+
+    /// Returns the number of occurrences of `symbol` up to the beginning
+    /// of the block that contains position `i`.
+    ///
+    /// The caller must guarantee that `i` is smaller than the length of the indexed sequence.
+    #[inline(always)]
+    pub(crate) fn rank_block(&self, symbol: u8, i: usize) -> usize {
+        let superblock_index = RSSupportPlain::<B_SIZE>::superblock_index(i);
+        let block_index = RSSupportPlain::<B_SIZE>::block_index(i);
+
+        self.superblocks[superblock_index].get_rank(symbol, block_index & 7)
+    }
+
+    /// Returns a pair `(position, rank)` where the position is the beginning of the block
+    /// that contains the `i`th occurrence of `symbol`, and `rank` is the number of
+    /// occurrences of `symbol` up to the beginning of this block.
+    ///
+    /// The caller must guarantee that `i` is not zero or greater than the number of
+    /// occurrences of `symbol`.
+    #[inline]
+    pub(crate) fn select_block(&self, symbol: u8, i: usize) -> (usize, usize) {
+        let samples = &self.select_samples[symbol as usize];
+        let sampled_i = (i - 1) / RSSupportPlain::<B_SIZE>::SELECT_NUM_SAMPLES;
+
+        // The superblock of the occurrence is between the two consecutive samples
+        let first_sblock_id = samples[sampled_i] as usize;
+        let last_sblock_id = samples
+            .get(sampled_i + 1)
+            .map_or(self.superblocks.len(), |&id| id as usize + 1);
+
+        let sblock_id = first_sblock_id
+            + self.superblocks[first_sblock_id..last_sblock_id]
+                .partition_point(|superblock| superblock.get_superblock_counter(symbol) < i)
+            - 1;
+
+        let superblock = &self.superblocks[sblock_id];
+        let rank = superblock.get_superblock_counter(symbol);
+
+        // The blocks that do not begin yet have sentinels
+        let (block_id, block_rank) = superblock.block_predecessor(symbol, i - rank);
+
+        (
+            (sblock_id * RSSupportPlain::<B_SIZE>::BLOCKS_IN_SUPERBLOCK + block_id) * B_SIZE,
+            rank + block_rank,
+        )
+    }
+}
+
+impl<const B_SIZE: usize> SpaceUsage for AppendOnlyRSSupportPlain<B_SIZE> {
+    /// Gives the space usage in bytes of the struct.
+    fn space_usage_byte(&self) -> usize {
+        let mut select_space = 0;
+        for c in 0..4 {
+            select_space += self.select_samples[c].space_usage_byte();
+        }
+        self.superblocks.space_usage_byte() + select_space + 2 * 4 * 8
+    }
+}
+
 /// Stores counters for a superblock and its blocks.
 /// We use a u128 for each of the 4 symbols.
 /// A u128 is subdivided as follows:
@@ -227,6 +483,38 @@ struct SuperblockPlain {
     counters: [u128; 4],
 }
 
+// SAFETY: a superblock is made of 64 bytes of plain words
+unsafe impl Pod for SuperblockPlain {}
+
+impl<const B_SIZE: usize> ZeroCopy for RSSupportPlain<B_SIZE> {
+    fn write_layout<W: Write>(&self, writer: &mut LayoutWriter<W>) -> io::Result<()> {
+        writer.write_u64(B_SIZE as u64)?;
+        writer.write_slice(&self.superblocks)?;
+        for samples in &self.select_samples {
+            writer.write_slice(samples)?;
+        }
+        Ok(())
+    }
+
+    unsafe fn read_layout(reader: &mut LayoutReader<'_>) -> Result<Self, LayoutError> {
+        if reader.read_u64()? != B_SIZE as u64 {
+            return Err(LayoutError::InvalidData(
+                "block size of the rank/select support",
+            ));
+        }
+
+        Ok(Self {
+            superblocks: reader.read_storage()?,
+            select_samples: [
+                reader.read_storage()?,
+                reader.read_storage()?,
+                reader.read_storage()?,
+                reader.read_storage()?,
+            ],
+        })
+    }
+}
+
 impl SpaceUsage for SuperblockPlain {
     /// Gives the space usage in bytes of the struct.
     fn space_usage_byte(&self) -> usize {
@@ -264,6 +552,17 @@ impl SuperblockPlain {
         (unsafe { *self.counters.get_unchecked(symbol as usize) } >> 84) as usize
     }
 
+    /// Creates a new superblock as `new` but with all the block counters set
+    /// to their maximum value. This is a sentinel for `block_predecessor` in
+    /// the blocks that do not begin yet.
+    fn with_block_sentinels(sbc: &[usize; 4]) -> Self {
+        let mut superblock = Self::new(sbc);
+        for counter in superblock.counters.iter_mut() {
+            *counter |= (1 << 84) - 1;
+        }
+        superblock
+    }
+
     fn set_block_counters(&mut self, block_id: usize, counters: &[usize; 4]) {
         assert!(block_id < 8);
         for &counter in counters.iter() {
@@ -274,8 +573,10 @@ impl SuperblockPlain {
             return;
         }
 
+        let shift = (block_id - 1) * 12;
         for (symbol, &counter) in counters.iter().enumerate() {
-            self.counters[symbol] |= (counter as u128) << ((block_id - 1) * 12);
+            self.counters[symbol] &= !(0b111111111111 << shift);
+            self.counters[symbol] |= (counter as u128) << shift;
         }
     }
 
@@ -312,3 +613,29 @@ impl SuperblockPlain {
         (Self::BLOCKS_IN_SUPERBLOCK - 1, prev_cnt)
     }
 }
+
+#[cfg(all(test, feature = "parallel"))]
+mod tests {
+    use super::*;
+    use crate::perf_and_test_utils::gen_sequence;
+
+    #[test]
+    fn test_build_superblocks_parallel() {
+        let superblock_size = 8 * 256;
+        for n in [
+            0,
+            1,
+            superblock_size - 1,
+            superblock_size,
+            3 * superblock_size,
+            (1 << 15) + 7,
+        ] {
+            let qv: QVector = gen_sequence(n, 4).into_iter().collect();
+            let sequential = RSSupportPlain::<256>::build_superblocks(&qv, 0, qv.len(), [0; 4]);
+            for n_threads in [1, 2, 3, 8] {
+                let parallel = RSSupportPlain::<256>::build_superblocks_parallel(&qv, n_threads);
+                assert_eq!(parallel, sequential);
+            }
+        }
+    }
+}
@@ -75,3 +75,133 @@ fn test_data_line() {
         }
     }
 }
+
+// Checks all the queries of a dynamic quad vector against the `expected` symbols.
+fn check_dynamic(dqv: &dynamic_qvector::DynamicQVector, expected: &[u8]) {
+    use crate::{AccessQuad, RankQuad, SelectQuad};
+
+    assert_eq!(dqv.len(), expected.len());
+    assert!(dqv.iter().eq(expected.iter().copied()));
+
+    let mut ranks = [0; 4];
+    for (i, &symbol) in expected.iter().enumerate() {
+        assert_eq!(dqv.get(i), Some(symbol));
+        for s in 0..4 {
+            assert_eq!(dqv.rank(s, i), Some(ranks[s as usize]));
+        }
+        assert_eq!(dqv.select(symbol, ranks[symbol as usize]), Some(i));
+        ranks[symbol as usize] += 1;
+    }
+    assert_eq!(dqv.get(expected.len()), None);
+    for s in 0..4 {
+        assert_eq!(dqv.rank(s, expected.len()), Some(ranks[s as usize]));
+        assert_eq!(dqv.occs(s), Some(ranks[s as usize]));
+        assert_eq!(dqv.select(s, ranks[s as usize]), None);
+    }
+}
+
+#[test]
+fn test_dynamic_qvector() {
+    use dynamic_qvector::DynamicQVector;
+    use rand::Rng;
+
+    let mut rng = rand::thread_rng();
+    let mut dqv = DynamicQVector::new();
+    let mut expected: Vec<u8> = Vec::new();
+    check_dynamic(&dqv, &expected);
+
+    // Random insertions make the tree grow by a few levels
+    for _ in 0..100_000 {
+        let i = rng.gen_range(0..=expected.len());
+        let symbol = rng.gen_range(0..4);
+        dqv.insert(i, symbol);
+        expected.insert(i, symbol);
+    }
+    check_dynamic(&dqv, &expected);
+
+    for _ in 0..10_000 {
+        let i = rng.gen_range(0..expected.len());
+        let symbol = rng.gen_range(0..4);
+        assert_eq!(dqv.set(i, symbol), expected[i]);
+        expected[i] = symbol;
+    }
+    check_dynamic(&dqv, &expected);
+
+    // Removals from a prefix make leaves and nodes underfull
+    while expected.len() > 1000 {
+        let i = rng.gen_range(0..expected.len().min(5000));
+        assert_eq!(dqv.remove(i), expected.remove(i));
+    }
+    check_dynamic(&dqv, &expected);
+
+    while !expected.is_empty() {
+        let i = rng.gen_range(0..expected.len());
+        assert_eq!(dqv.remove(i), expected.remove(i));
+    }
+    check_dynamic(&dqv, &expected);
+
+    let dqv: DynamicQVector = [0u8, 1, 2, 3].into_iter().cycle().take(10_000).collect();
+    let expected: Vec<u8> = [0u8, 1, 2, 3].into_iter().cycle().take(10_000).collect();
+    check_dynamic(&dqv, &expected);
+    assert_eq!(dqv.occs_smaller(3), Some(7500));
+}
+
+// Checks all the queries of an append-only quad vector against the `expected` symbols.
+fn check_append_only<const B_SIZE: usize>(
+    aqv: &append_only_qvector::AppendOnlyRSQVector<B_SIZE>,
+    expected: &[u8],
+) {
+    use crate::{AccessQuad, RankQuad, SelectQuad};
+
+    assert_eq!(aqv.len(), expected.len());
+    assert!(aqv.iter().eq(expected.iter().copied()));
+
+    let mut ranks = [0; 4];
+    for (i, &symbol) in expected.iter().enumerate() {
+        assert_eq!(aqv.get(i), Some(symbol));
+        for s in 0..4 {
+            assert_eq!(aqv.rank(s, i), Some(ranks[s as usize]));
+        }
+        assert_eq!(aqv.select(symbol, ranks[symbol as usize]), Some(i));
+        ranks[symbol as usize] += 1;
+    }
+    assert_eq!(aqv.get(expected.len()), None);
+    assert_eq!(aqv.rank(0, expected.len() + 1), None);
+    for s in 0..4 {
+        assert_eq!(aqv.rank(s, expected.len()), Some(ranks[s as usize]));
+        assert_eq!(aqv.occs(s), Some(ranks[s as usize]));
+        assert_eq!(aqv.select(s, ranks[s as usize]), None);
+    }
+}
+
+#[test]
+fn test_append_only_qvector() {
+    use append_only_qvector::AppendOnlyRSQVector;
+    use rand::Rng;
+
+    let mut rng = rand::thread_rng();
+    let mut aqv256 = AppendOnlyRSQVector::<256>::new();
+    let mut aqv512 = AppendOnlyRSQVector::<512>::new();
+    let mut expected: Vec<u8> = Vec::new();
+    check_append_only(&aqv256, &expected);
+    check_append_only(&aqv512, &expected);
+
+    // Lengths around the boundaries of the lines, blocks, and superblocks
+    for n in [1, 255, 256, 257, 511, 512, 2047, 2048, 2049, 4096, 10_000] {
+        while expected.len() < n {
+            let symbol = rng.gen_range(0..4);
+            aqv256.push(symbol);
+            aqv512.push(symbol);
+            expected.push(symbol);
+        }
+        check_append_only(&aqv256, &expected);
+        check_append_only(&aqv512, &expected);
+    }
+
+    // Full blocks of the same symbol and more than one select sample
+    let expected = vec![2u8; 20_000];
+    let aqv256: AppendOnlyRSQVector<256> = expected.iter().copied().collect();
+    let aqv512: AppendOnlyRSQVector<512> = expected.iter().copied().collect();
+    check_append_only(&aqv256, &expected);
+    check_append_only(&aqv512, &expected);
+}
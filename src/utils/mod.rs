@@ -273,5 +273,200 @@ where
     }
 }
 
+/// In-place version of [`stable_partition_of_4`] whose extra memory is bounded
+/// by the size of the `scratch` buffer.
+///
+/// The sequence is split into blocks as large as the scratch buffer. Each block
+/// is stably partitioned through the buffer, then adjacent partitioned runs of
+/// the same size are merged in place by rotating their parts, as in a binary counter.
+/// Thus, at most $$O(\log (n/b))$$ runs are pending at any time and the time is
+/// $$O(n \log (n/b))$$, where $$b$$ is the size of the buffer.
+/// The result is identical to the one of [`stable_partition_of_4`].
+///
+/// # Panics
+/// Panics if the scratch buffer is empty and the sequence is not.
+pub fn stable_partition_of_4_with_scratch<T>(sequence: &mut [T], shift: usize, scratch: &mut [T])
+where
+    T: Unsigned + PrimInt + Ord + Shr<usize> + AsPrimitive<u8>,
+    u8: AsPrimitive<T>,
+{
+    if sequence.is_empty() {
+        return;
+    }
+    assert!(!scratch.is_empty(), "The scratch buffer cannot be empty.");
+
+    // The pending runs, each one is a tuple (length, occurrences of each pair of bits, level).
+    // A run of level l is made of 2^l blocks, levels are decreasing from the bottom of the stack.
+    let mut runs: Vec<PartitionRun> =
+        Vec::with_capacity(max_pending_runs(sequence.len(), scratch.len()));
+    let max_runs = runs.capacity();
+
+    for start in (0..sequence.len()).step_by(scratch.len()) {
+        let end = (start + scratch.len()).min(sequence.len());
+        let block = &mut sequence[start..end];
+        let buffer = &mut scratch[..block.len()];
+        buffer.copy_from_slice(block);
+
+        let mut counts = [0; 4];
+        for &a in buffer.iter() {
+            counts[((a >> shift).as_() & 3) as usize] += 1;
+        }
+
+        let mut pos = [0, counts[0], counts[0] + counts[1], block.len() - counts[3]];
+        for &a in buffer.iter() {
+            let two_bits = ((a >> shift).as_() & 3) as usize;
+            block[pos[two_bits]] = a;
+            pos[two_bits] += 1;
+        }
+
+        runs.push((block.len(), counts, 0));
+        debug_assert!(runs.len() <= max_runs);
+
+        while runs.len() > 1 && runs[runs.len() - 1].2 == runs[runs.len() - 2].2 {
+            merge_last_runs(sequence, &mut runs, end);
+        }
+    }
+
+    while runs.len() > 1 {
+        merge_last_runs(sequence, &mut runs, sequence.len());
+    }
+}
+
+/// A run of [`stable_partition_of_4_with_scratch`]: its length, the occurrences of each
+/// pair of bits, and its level.
+pub(crate) type PartitionRun = (usize, [usize; 4], u32);
+
+/// Returns the largest number of runs kept by [`stable_partition_of_4_with_scratch`]
+/// for a sequence of length `n` and a scratch buffer of size `scratch_size`.
+pub(crate) fn max_pending_runs(n: usize, scratch_size: usize) -> usize {
+    // Pending runs have distinct levels, plus the last block before it is merged
+    n.div_ceil(scratch_size.max(1)).max(1).ilog2() as usize + 2
+}
+
+/// Merges in place the last two runs in `runs`, which end at position `end` of `sequence`.
+fn merge_last_runs<T>(sequence: &mut [T], runs: &mut Vec<PartitionRun>, end: usize) {
+    let (right_len, right, level) = runs.pop().unwrap();
+    let (left_len, left, _) = runs.pop().unwrap();
+
+    // The run is L0 L1 L2 L3 R0 R1 R2 R3 and it becomes L0 R0 L1 R1 L2 R2 L3 R3
+    let run = &mut sequence[end - left_len - right_len..end];
+    let mut pos = 0;
+    let mut left_rest = left_len;
+    for two_bits in 0..4 {
+        // run[pos..] is L_{two_bits} .. L3 R_{two_bits} .. R3
+        pos += left[two_bits];
+        left_rest -= left[two_bits];
+        run[pos..pos + left_rest + right[two_bits]].rotate_right(right[two_bits]);
+        pos += right[two_bits];
+    }
+
+    let mut counts = left;
+    for (count, right_count) in counts.iter_mut().zip(right) {
+        *count += right_count;
+    }
+    runs.push((left_len + right_len, counts, level + 1));
+}
+
+/// Returns the number of threads used by the parallel construction.
+#[cfg(feature = "parallel")]
+pub(crate) fn n_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Parallel version of [`stable_partition_of_4`] which uses `n_threads` threads.
+///
+/// The sequence is split into chunks. A first pass counts the occurrences of
+/// each pair of bits in every chunk, so that each chunk knows where to write
+/// its values in the partitioned sequence. A second pass scatters the values
+/// of the chunks in parallel. The result is identical to the one of
+/// [`stable_partition_of_4`].
+#[cfg(feature = "parallel")]
+pub fn stable_partition_of_4_parallel<T>(sequence: &mut [T], shift: usize, n_threads: usize)
+where
+    T: Unsigned + PrimInt + Ord + Shr<usize> + AsPrimitive<u8> + Send + Sync,
+    u8: AsPrimitive<T>,
+{
+    if n_threads <= 1 || sequence.len() < 2 {
+        stable_partition_of_4(sequence, shift);
+        return;
+    }
+
+    let chunk_size = sequence.len().div_ceil(n_threads);
+
+    // Occurrences of each pair of bits in each chunk
+    let counts: Vec<[usize; 4]> = std::thread::scope(|s| {
+        let handles: Vec<_> = sequence
+            .chunks(chunk_size)
+            .map(|chunk| {
+                s.spawn(move || {
+                    let mut counts = [0; 4];
+                    for &a in chunk {
+                        counts[((a >> shift).as_() & 3) as usize] += 1;
+                    }
+                    counts
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    let mut partitioned = sequence.to_vec();
+
+    // Split the output into the regions written by each chunk
+    let mut regions: Vec<Vec<&mut [T]>> = (0..counts.len()).map(|_| Vec::new()).collect();
+    let mut rest = &mut partitioned[..];
+    for two_bits in 0..4 {
+        for (chunk_counts, chunk_regions) in counts.iter().zip(regions.iter_mut()) {
+            let (region, tail) = std::mem::take(&mut rest).split_at_mut(chunk_counts[two_bits]);
+            chunk_regions.push(region);
+            rest = tail;
+        }
+    }
+
+    std::thread::scope(|s| {
+        for (chunk, mut chunk_regions) in sequence.chunks(chunk_size).zip(regions) {
+            s.spawn(move || {
+                let mut pos = [0; 4];
+                for &a in chunk {
+                    let two_bits = ((a >> shift).as_() & 3) as usize;
+                    chunk_regions[two_bits][pos[two_bits]] = a;
+                    pos[two_bits] += 1;
+                }
+            });
+        }
+    });
+
+    std::thread::scope(|s| {
+        for (dst, src) in sequence
+            .chunks_mut(chunk_size)
+            .zip(partitioned.chunks(chunk_size))
+        {
+            s.spawn(move || dst.copy_from_slice(src));
+        }
+    });
+}
+
+/// Utility function to partition values in `sequence` by the bit
+/// that we obtain by shifting `shift` bits to the right.
+/// This is used by the construction of BinaryWaveletMatrix.
+pub fn stable_partition_of_2<T>(sequence: &mut [T], shift: usize)
+where
+    T: Unsigned + PrimInt + Ord + Shr<usize> + AsPrimitive<u8>,
+    u8: AsPrimitive<T>,
+{
+    let mut vecs = [Vec::new(), Vec::new()];
+
+    for &a in sequence.iter() {
+        let bit = (a >> shift).as_() & 1;
+        vecs[bit as usize].push(a);
+    }
+
+    let mut pos = 0;
+    for i in 0..2 {
+        sequence[pos..pos + vecs[i].len()].copy_from_slice(&(vecs[i][..]));
+        pos += vecs[i].len()
+    }
+}
+
 #[cfg(test)]
 mod tests;
@@ -46,3 +46,63 @@ fn test_stable_partition_of_4() {
 
     assert_eq!(vv, v);
 }
+
+#[test]
+fn test_stable_partition_of_4_with_scratch() {
+    let v: Vec<u16> = crate::perf_and_test_utils::gen_sequence(10_000, 256)
+        .into_iter()
+        .map(|c| c as u16 * 3)
+        .collect();
+
+    for shift in [0, 2, 4, 6, 8] {
+        let mut seq = v.clone();
+        stable_partition_of_4(&mut seq, shift);
+        for scratch_size in [1, 2, 3, 100, 1024, 5000, 10_000, 20_000] {
+            let mut scratch = vec![0; scratch_size];
+            let mut in_place = v.clone();
+            stable_partition_of_4_with_scratch(&mut in_place, shift, &mut scratch);
+            assert_eq!(in_place, seq);
+        }
+    }
+
+    let mut empty: [u8; 0] = [];
+    stable_partition_of_4_with_scratch(&mut empty, 0, &mut []);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_stable_partition_of_4_parallel() {
+    let v: Vec<u16> = crate::perf_and_test_utils::gen_sequence(10_000, 256)
+        .into_iter()
+        .map(|c| c as u16 * 3)
+        .collect();
+
+    for shift in [0, 2, 4, 6, 8] {
+        let mut seq = v.clone();
+        stable_partition_of_4(&mut seq, shift);
+        for n_threads in [1, 2, 3, 8, 64] {
+            let mut par = v.clone();
+            stable_partition_of_4_parallel(&mut par, shift, n_threads);
+            assert_eq!(par, seq);
+        }
+    }
+}
+
+#[test]
+fn test_stable_partition_of_2() {
+    let mut v: Vec<u8> = vec![1, 2, 3, 0, 2, 2, 2, 3, 3, 0, 0, 0, 1, 3, 2, 1];
+
+    for shift in 0..2 {
+        let mut vv = v.clone();
+        stable_partition_of_2(&mut vv, shift);
+
+        v.sort_by(|a, b| {
+            // stable sorting by current bit
+            let a_bit: u8 = AsPrimitive::<u8>::as_(*a >> shift) & 1;
+            let b_bit: u8 = AsPrimitive::<u8>::as_(*b >> shift) & 1;
+            a_bit.cmp(&b_bit)
+        });
+
+        assert_eq!(vv, v);
+    }
+}
@@ -0,0 +1,627 @@
+//! This module defines a stable, aligned binary layout for the data structures
+//! of the crate, and [`View`]s that query them directly over a buffer, without
+//! copying or deserializing their data.
+//!
+//! A data structure implementing [`ZeroCopy`] is written with [`ZeroCopy::save_layout`].
+//! The buffer holding the written bytes, e.g., a memory-mapped file, is turned into a
+//! view with [`ZeroCopy::view`]. The view dereferences to the data structure, so all its
+//! queries are available, but all its arrays borrow the buffer.
+//!
+//! Opening a view checks that the buffer describes a valid data structure, so that
+//! no query can read out of bounds, whatever the content of the buffer. Besides the
+//! lengths of the arrays, this requires to recompute the counters of the rank and
+//! select supports from the data, which takes time linear in the size of the buffer,
+//! although the data is not copied. If the buffer is trusted, e.g., because it has been
+//! written by the same application, [`ZeroCopy::view_unchecked`] checks only the
+//! lengths and costs time proportional to the number of arrays, not to their size.
+//!
+//! ## Layout
+//!
+//! The layout starts with a magic number and the version of the layout. Then, each
+//! data structure writes its fields in order. Scalars are stored as little-endian
+//! 64-bit words. Each array is stored as its length followed by its elements, which
+//! start at an offset multiple of 64 bytes from the beginning of the layout.
+//! For this reason, the buffer of a view must be aligned to 64 bytes, which is always
+//! the case for a memory-mapped file.
+//!
+//! The layout can be read only on little-endian machines with 64-bit words, which are
+//! the ones where the arrays can be used as they are.
+//!
+//! ## Examples
+//!
+//! ```
+//! use qwt::{QWT256, RankUnsigned, ZeroCopy};
+//! use qwt::zero_copy::{AlignedBytes, QWaveletTreeRef};
+//!
+//! let qwt = QWT256::try_from(vec![1u8, 0, 1, 0, 2, 4, 5, 3]).unwrap();
+//!
+//! let mut bytes = Vec::new();
+//! qwt.save_layout(&mut bytes).unwrap();
+//!
+//! // Copy the bytes into a buffer aligned to 64 bytes, as a memory-mapped file is
+//! let buffer = AlignedBytes::from(&bytes[..]);
+//!
+//! let view: QWaveletTreeRef<u8, _> = QWT256::view(&buffer).unwrap();
+//! assert_eq!(view.rank(1, 4), Some(2));
+//! assert_eq!(*view, qwt);
+//! ```
+
+use crate::{QWaveletTree, SpaceUsage};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+/// The magic number at the beginning of a layout, i.e., "QWTLAYOU" in little-endian.
+const MAGIC: u64 = u64::from_le_bytes(*b"QWTLAYOU");
+
+/// The version of the layout. It changes whenever the layout of any data structure changes.
+pub const LAYOUT_VERSION: u64 = 2;
+
+/// The alignment in bytes of the arrays in a layout.
+const ALIGNMENT: usize = 64;
+
+/// Type alias for a view of a [`QWaveletTree`].
+pub type QWaveletTreeRef<'a, T, RS, const WITH_PREFETCH_SUPPORT: bool = false> =
+    View<'a, QWaveletTree<T, RS, WITH_PREFETCH_SUPPORT>>;
+
+/// The errors that may occur when opening a view.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutError {
+    /// The buffer does not start with the magic number of a layout.
+    InvalidMagic,
+    /// The layout has been written with a different version of the layout.
+    UnsupportedVersion(u64),
+    /// The buffer is not aligned to 64 bytes.
+    Misaligned,
+    /// The buffer ends before the end of the layout.
+    Truncated,
+    /// The layout does not describe a valid data structure of the requested type.
+    InvalidData(&'static str),
+    /// The layout cannot be used on this machine, which is not little-endian with 64-bit words.
+    UnsupportedPlatform,
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidMagic => write!(f, "the buffer does not contain a qwt layout"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "the layout has version {version} but version {LAYOUT_VERSION} is supported"
+            ),
+            Self::Misaligned => write!(f, "the buffer is not aligned to {ALIGNMENT} bytes"),
+            Self::Truncated => write!(f, "the buffer ends before the end of the layout"),
+            Self::InvalidData(what) => write!(f, "invalid layout: {what}"),
+            Self::UnsupportedPlatform => write!(
+                f,
+                "the layout requires a little-endian machine with 64-bit words"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+/// Marker trait for the types whose values can be read directly from the bytes of a layout.
+///
+/// # Safety
+/// The type must have no padding and no invalid bit patterns, and its alignment
+/// must be at most 64 bytes.
+pub unsafe trait Pod: Copy + 'static {}
+
+unsafe impl Pod for u8 {}
+unsafe impl Pod for u16 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for u64 {}
+unsafe impl Pod for u128 {}
+unsafe impl Pod for i64 {}
+unsafe impl Pod for usize {}
+
+/// A trait for the data structures with an aligned binary layout that can be
+/// queried without copying it.
+pub trait ZeroCopy: Sized {
+    /// Writes the fields of the data structure to the `writer`.
+    ///
+    /// # Errors
+    /// Returns an error if the writer fails.
+    fn write_layout<W: Write>(&self, writer: &mut LayoutWriter<W>) -> io::Result<()>;
+
+    /// Reads a data structure written by [`ZeroCopy::write_layout`] from the `reader`.
+    /// The arrays of the data structure borrow the buffer of the reader.
+    ///
+    /// Unless the reader is trusted (see [`LayoutReader::is_trusted`]), the data structure
+    /// must check all the invariants its queries rely on, so that no query on the returned
+    /// data structure reads out of bounds.
+    ///
+    /// # Errors
+    /// Returns an error if the layout is not valid.
+    ///
+    /// # Safety
+    /// The returned data structure must not outlive the buffer of the reader.
+    unsafe fn read_layout(reader: &mut LayoutReader<'_>) -> Result<Self, LayoutError>;
+
+    /// Writes the layout of the data structure, magic number and version included.
+    ///
+    /// # Errors
+    /// Returns an error if the writer fails.
+    fn save_layout<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut writer = LayoutWriter::new(writer);
+        writer.write_u64(MAGIC)?;
+        writer.write_u64(LAYOUT_VERSION)?;
+        self.write_layout(&mut writer)?;
+        writer.finish()
+    }
+
+    /// Returns the length in bytes of the layout written by [`ZeroCopy::save_layout`].
+    ///
+    /// The layout is written to a sink, which discards the arrays without reading
+    /// them, so this takes time proportional to the number of arrays, not to their size.
+    fn layout_len(&self) -> usize {
+        let mut writer = LayoutWriter::new(io::sink());
+        writer.write_u64(MAGIC).unwrap();
+        writer.write_u64(LAYOUT_VERSION).unwrap();
+        self.write_layout(&mut writer).unwrap(); // Writing to a sink never fails
+        writer.offset.next_multiple_of(ALIGNMENT)
+    }
+
+    /// Returns a view of the data structure whose layout is in `buffer`.
+    /// The buffer must be aligned to 64 bytes.
+    ///
+    /// Besides the lengths of the arrays, the counters of the rank and select supports
+    /// are checked against the data, which takes time linear in the size of the buffer.
+    /// The supports of [`RSNarrow`](crate::RSNarrow) and [`RSWide`](crate::RSWide) are
+    /// checked with a scan of their bit vector, while the other ones, e.g., the ones of
+    /// the quad vectors, are rebuilt and compared, which also allocates as much memory
+    /// as the rebuilt support. For large buffers written by [`ZeroCopy::save_layout`]
+    /// and trusted by the application, [`ZeroCopy::view_unchecked`] avoids this cost.
+    ///
+    /// # Errors
+    /// Returns an error if the buffer is misaligned or does not contain a valid layout.
+    fn view(buffer: &[u8]) -> Result<View<'_, Self>, LayoutError> {
+        let mut reader = LayoutReader::new(buffer);
+        // SAFETY: the view does not outlive the buffer
+        let inner = unsafe { read_checked(&mut reader)? };
+        Ok(View {
+            inner,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Returns a view of the data structure whose layout is in `buffer`, like
+    /// [`ZeroCopy::view`], but checks only the lengths of the arrays and not
+    /// their content. The buffer must be aligned to 64 bytes.
+    ///
+    /// # Errors
+    /// Returns an error if the buffer is misaligned or does not contain a layout
+    /// of this data structure.
+    ///
+    /// # Safety
+    /// The buffer must contain a layout written by [`ZeroCopy::save_layout`] for the
+    /// same type, and not modified afterwards. Otherwise, queries may read out of bounds.
+    unsafe fn view_unchecked(buffer: &[u8]) -> Result<View<'_, Self>, LayoutError> {
+        let mut reader = LayoutReader::new(buffer);
+        reader.trusted = true;
+        let inner = read_checked(&mut reader)?;
+        Ok(View {
+            inner,
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// Reads a data structure from its layout, magic number and version included.
+///
+/// # Safety
+/// The returned data structure must not outlive the buffer of the reader.
+unsafe fn read_checked<S: ZeroCopy>(reader: &mut LayoutReader<'_>) -> Result<S, LayoutError> {
+    if cfg!(target_endian = "big") || std::mem::size_of::<usize>() != 8 {
+        return Err(LayoutError::UnsupportedPlatform);
+    }
+    if !(reader.buffer.as_ptr() as usize).is_multiple_of(ALIGNMENT) {
+        return Err(LayoutError::Misaligned);
+    }
+
+    if reader.read_u64()? != MAGIC {
+        return Err(LayoutError::InvalidMagic);
+    }
+    let version = reader.read_u64()?;
+    if version != LAYOUT_VERSION {
+        return Err(LayoutError::UnsupportedVersion(version));
+    }
+    S::read_layout(reader)
+}
+
+/// Reads a data structure from the layout in `buffer`. Its arrays share the
+/// ownership of the buffer, so the data structure is not tied to a lifetime.
+pub(crate) fn load_layout<S: ZeroCopy>(buffer: Arc<AlignedBytes>) -> Result<S, LayoutError> {
+    let mut reader = LayoutReader {
+        buffer: &buffer,
+        offset: 0,
+        owner: Some(Arc::clone(&buffer)),
+        trusted: false,
+    };
+    // SAFETY: the arrays of the data structure keep the buffer alive
+    unsafe { read_checked(&mut reader) }
+}
+
+/// A data structure whose arrays borrow a buffer with lifetime `'a`.
+///
+/// The view dereferences to the data structure, so it supports all its queries.
+/// Cloning the data structure, e.g., with `view.clone()`, gives an owned copy.
+pub struct View<'a, S> {
+    inner: S,
+    _marker: PhantomData<&'a [u8]>,
+}
+
+impl<S> Deref for View<'_, S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for View<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+/// Writes a layout to a [`Write`], keeping track of the offset to align the arrays.
+pub struct LayoutWriter<W> {
+    writer: W,
+    offset: usize,
+}
+
+impl<W: Write> LayoutWriter<W> {
+    /// Creates a writer of a layout starting at the current position of `writer`.
+    pub fn new(writer: W) -> Self {
+        Self { writer, offset: 0 }
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bytes)?;
+        self.offset += bytes.len();
+        Ok(())
+    }
+
+    fn pad(&mut self) -> io::Result<()> {
+        let padding = self.offset.next_multiple_of(ALIGNMENT) - self.offset;
+        self.write_bytes(&[0; ALIGNMENT][..padding])
+    }
+
+    /// Writes a scalar as a little-endian 64-bit word.
+    ///
+    /// # Errors
+    /// Returns an error if the writer fails.
+    pub fn write_u64(&mut self, value: u64) -> io::Result<()> {
+        self.write_bytes(&value.to_le_bytes())
+    }
+
+    /// Writes an array, i.e., its length and its elements aligned to 64 bytes.
+    ///
+    /// # Errors
+    /// Returns an error if the writer fails.
+    pub fn write_slice<T: Pod>(&mut self, slice: &[T]) -> io::Result<()> {
+        self.write_u64(slice.len() as u64)?;
+        self.pad()?;
+        // SAFETY: Pod types have no padding, so all their bytes are initialized
+        let bytes = unsafe {
+            std::slice::from_raw_parts(slice.as_ptr() as *const u8, std::mem::size_of_val(slice))
+        };
+        self.write_bytes(bytes)
+    }
+
+    /// Pads the layout to a multiple of 64 bytes and flushes the writer.
+    ///
+    /// # Errors
+    /// Returns an error if the writer fails.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.pad()?;
+        self.writer.flush()
+    }
+}
+
+/// Reads a layout from a buffer aligned to 64 bytes.
+pub struct LayoutReader<'a> {
+    buffer: &'a [u8],
+    offset: usize,
+    owner: Option<Arc<AlignedBytes>>,
+    trusted: bool,
+}
+
+impl<'a> LayoutReader<'a> {
+    /// Creates a reader of the layout in `buffer`.
+    pub fn new(buffer: &'a [u8]) -> Self {
+        Self {
+            buffer,
+            offset: 0,
+            owner: None,
+            trusted: false,
+        }
+    }
+
+    /// Checks if the layout is trusted to be written by [`ZeroCopy::save_layout`].
+    /// If so, the data structures check only the lengths of their arrays and skip
+    /// the checks that take time linear in their size.
+    pub fn is_trusted(&self) -> bool {
+        self.trusted
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], LayoutError> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|&end| end <= self.buffer.len())
+            .ok_or(LayoutError::Truncated)?;
+        let bytes = &self.buffer[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    /// Reads a scalar written by [`LayoutWriter::write_u64`].
+    ///
+    /// # Errors
+    /// Returns an error if the buffer ends.
+    pub fn read_u64(&mut self) -> Result<u64, LayoutError> {
+        let bytes = self.read_bytes(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// Reads a scalar written by [`LayoutWriter::write_u64`] as a `usize`.
+    ///
+    /// # Errors
+    /// Returns an error if the buffer ends or if the value does not fit a `usize`.
+    pub fn read_usize(&mut self) -> Result<usize, LayoutError> {
+        usize::try_from(self.read_u64()?).map_err(|_| LayoutError::InvalidData("too large value"))
+    }
+
+    /// Reads an array written by [`LayoutWriter::write_slice`] without copying it.
+    ///
+    /// # Errors
+    /// Returns an error if the buffer ends or if the array is misaligned.
+    pub fn read_slice<T: Pod>(&mut self) -> Result<&'a [T], LayoutError> {
+        let len = self.read_usize()?;
+        self.read_bytes(self.offset.next_multiple_of(ALIGNMENT) - self.offset)?;
+        let size = len
+            .checked_mul(std::mem::size_of::<T>())
+            .ok_or(LayoutError::Truncated)?;
+        let bytes = self.read_bytes(size)?;
+
+        if !bytes.as_ptr().cast::<T>().is_aligned() {
+            return Err(LayoutError::Misaligned);
+        }
+        // SAFETY: the bytes are aligned and any bit pattern is a valid Pod value
+        Ok(unsafe { std::slice::from_raw_parts(bytes.as_ptr() as *const T, len) })
+    }
+
+    /// Reads an array written by [`LayoutWriter::write_slice`] as the storage of
+    /// a data structure.
+    ///
+    /// # Safety
+    /// The storage must not outlive the buffer of the reader.
+    pub(crate) unsafe fn read_storage<T: Pod>(&mut self) -> Result<Storage<T>, LayoutError> {
+        let slice = self.read_slice()?;
+        Ok(Storage::from_slice(slice, self.owner.clone()))
+    }
+}
+
+/// A buffer of bytes aligned to 64 bytes, which can hold a layout read
+/// from a source that is not memory-mapped.
+pub struct AlignedBytes {
+    lines: Box<[AlignedLine]>,
+    len: usize,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C, align(64))]
+struct AlignedLine([u8; ALIGNMENT]);
+
+impl From<&[u8]> for AlignedBytes {
+    fn from(bytes: &[u8]) -> Self {
+        let mut lines = vec![AlignedLine([0; ALIGNMENT]); bytes.len().div_ceil(ALIGNMENT)];
+        for (line, chunk) in lines.iter_mut().zip(bytes.chunks(ALIGNMENT)) {
+            line.0[..chunk.len()].copy_from_slice(chunk);
+        }
+        Self {
+            lines: lines.into_boxed_slice(),
+            len: bytes.len(),
+        }
+    }
+}
+
+impl AlignedBytes {
+    /// Reads `len` bytes from the `reader` into an aligned buffer.
+    /// The buffer grows while reading, so a wrong `len` does not allocate
+    /// more memory than the bytes actually available.
+    pub(crate) fn read_from<R: Read>(reader: &mut R, len: usize) -> io::Result<Self> {
+        const LINES_PER_READ: usize = 1 << 14;
+
+        let n_lines = len.div_ceil(ALIGNMENT);
+        let mut lines = Vec::new();
+        while lines.len() < n_lines {
+            let start = lines.len();
+            let end = n_lines.min(start + LINES_PER_READ);
+            lines.resize(end, AlignedLine([0; ALIGNMENT]));
+            let n_bytes = len.min(end * ALIGNMENT) - start * ALIGNMENT;
+            // SAFETY: the lines are contiguous bytes
+            let bytes = unsafe {
+                std::slice::from_raw_parts_mut(lines[start..].as_mut_ptr() as *mut u8, n_bytes)
+            };
+            reader.read_exact(bytes)?;
+        }
+
+        Ok(Self {
+            lines: lines.into_boxed_slice(),
+            len,
+        })
+    }
+}
+
+impl Deref for AlignedBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: the lines are contiguous bytes and there are at least `len` of them
+        unsafe { std::slice::from_raw_parts(self.lines.as_ptr() as *const u8, self.len) }
+    }
+}
+
+/// The storage of an immutable array of a data structure, which is either owned,
+/// borrowed from the buffer of a [`View`], or shared with the other arrays of a
+/// data structure loaded from a file.
+///
+/// The elements are always accessed through a pointer and a length, so that
+/// dereferencing the storage does not depend on who owns them.
+pub(crate) struct Storage<T> {
+    ptr: NonNull<T>,
+    len: usize,
+    owner: Owner,
+    _marker: PhantomData<T>,
+}
+
+/// The owner of the elements of a [`Storage`].
+enum Owner {
+    /// The elements are in a `Box<[T]>` released when the storage is dropped.
+    Boxed,
+    /// The elements are in the buffer of a [`View`].
+    Borrowed,
+    /// The elements are in a buffer shared with the other arrays of a data structure,
+    /// which is kept alive by the storage.
+    #[allow(dead_code)]
+    Shared(Arc<AlignedBytes>),
+}
+
+// SAFETY: the storage is immutable, so it is shared exactly as a `Box<[T]>`
+unsafe impl<T: Sync> Send for Storage<T> {}
+unsafe impl<T: Sync> Sync for Storage<T> {}
+
+impl<T> Storage<T> {
+    /// Creates a storage borrowing `slice`, whose buffer is kept alive by `owner`.
+    ///
+    /// # Safety
+    /// The storage must not outlive `slice`, unless `owner` holds its buffer.
+    unsafe fn from_slice(slice: &[T], owner: Option<Arc<AlignedBytes>>) -> Self {
+        Self {
+            ptr: NonNull::from(slice).cast(),
+            len: slice.len(),
+            owner: owner.map_or(Owner::Borrowed, Owner::Shared),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Converts the storage into a vector, copying the elements if they are borrowed.
+    pub(crate) fn into_vec(self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        match self.owner {
+            Owner::Boxed => {
+                let this = std::mem::ManuallyDrop::new(self);
+                let slice = std::ptr::slice_from_raw_parts_mut(this.ptr.as_ptr(), this.len);
+                // SAFETY: the elements come from a `Box<[T]>`, which is not dropped twice
+                unsafe { Box::from_raw(slice) }.into_vec()
+            }
+            Owner::Borrowed | Owner::Shared(_) => self.to_vec(),
+        }
+    }
+}
+
+impl<T> Drop for Storage<T> {
+    fn drop(&mut self) {
+        if let Owner::Boxed = self.owner {
+            let slice = std::ptr::slice_from_raw_parts_mut(self.ptr.as_ptr(), self.len);
+            // SAFETY: the elements come from a `Box<[T]>`
+            drop(unsafe { Box::from_raw(slice) });
+        }
+    }
+}
+
+impl<T> Deref for Storage<T> {
+    type Target = [T];
+
+    #[inline(always)]
+    fn deref(&self) -> &[T] {
+        // SAFETY: the elements are owned by the storage or by a buffer that outlives it
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> Default for Storage<T> {
+    fn default() -> Self {
+        Self::from(Box::<[T]>::default())
+    }
+}
+
+impl<T: Clone> Clone for Storage<T> {
+    fn clone(&self) -> Self {
+        Self::from(self.to_vec())
+    }
+}
+
+impl<T: PartialEq> PartialEq for Storage<T> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: Eq> Eq for Storage<T> {}
+
+impl<T: fmt::Debug> fmt::Debug for Storage<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T> From<Box<[T]>> for Storage<T> {
+    fn from(data: Box<[T]>) -> Self {
+        let len = data.len();
+        let ptr = Box::into_raw(data).cast::<T>();
+        Self {
+            // SAFETY: the pointer of a box is never null
+            ptr: unsafe { NonNull::new_unchecked(ptr) },
+            len,
+            owner: Owner::Boxed,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> From<Vec<T>> for Storage<T> {
+    fn from(data: Vec<T>) -> Self {
+        Self::from(data.into_boxed_slice())
+    }
+}
+
+impl<T: SpaceUsage> SpaceUsage for Storage<T> {
+    /// Gives the space usage in bytes of the struct.
+    /// Borrowed elements are counted as well.
+    fn space_usage_byte(&self) -> usize {
+        std::mem::size_of::<Box<[T]>>()
+            + self
+                .iter()
+                .map(|elem| elem.space_usage_byte())
+                .sum::<usize>()
+    }
+}
+
+/// The storage is serialized exactly as a `Box<[T]>`.
+impl<T: Serialize> Serialize for Storage<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Storage<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Box::<[T]>::deserialize(deserializer).map(Self::from)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,217 @@
+use super::*;
+use crate::perf_and_test_utils::gen_sequence;
+use crate::{
+    AccessUnsigned, BitVector, DArray, QWT256Pfs, RSNarrow, RSWide, RankBin, RankUnsigned,
+    SelectBin, SelectUnsigned, QWT256, QWT512,
+};
+
+fn layout<S: ZeroCopy>(ds: &S) -> AlignedBytes {
+    let mut bytes = Vec::new();
+    ds.save_layout(&mut bytes).unwrap();
+    assert_eq!(bytes.len() % ALIGNMENT, 0);
+    assert_eq!(bytes.len(), ds.layout_len());
+    AlignedBytes::from(&bytes[..])
+}
+
+#[test]
+fn test_qwt_view() {
+    for sigma in [1, 2, 3, 4, 17, 256] {
+        let sequence = gen_sequence(10_000, sigma);
+
+        let qwt = QWT256::try_from(sequence.clone()).unwrap();
+        let buffer = layout(&qwt);
+        let view = QWT256::<u8>::view(&buffer).unwrap();
+        assert_eq!(*view, qwt);
+        for (i, &symbol) in sequence.iter().enumerate().step_by(7) {
+            assert_eq!(view.get(i), Some(symbol));
+            assert_eq!(view.rank(symbol, i), qwt.rank(symbol, i));
+            assert_eq!(view.select(symbol, 3), qwt.select(symbol, 3));
+        }
+        // A clone of the view owns its data
+        assert_eq!(view.clone(), qwt);
+
+        let sequence: Vec<u32> = sequence.iter().map(|&c| c as u32 * 1000).collect();
+        let qwt = QWT256Pfs::try_from(sequence.clone()).unwrap();
+        let buffer = layout(&qwt);
+        let view = QWT256Pfs::<u32>::view(&buffer).unwrap();
+        assert_eq!(*view, qwt);
+        for (i, &symbol) in sequence.iter().enumerate().step_by(7) {
+            assert_eq!(view.rank_prefetch(symbol, i), qwt.rank(symbol, i));
+        }
+    }
+
+    let qwt = QWT512::<u16>::try_from(Vec::new()).unwrap();
+    let buffer = layout(&qwt);
+    assert_eq!(*QWT512::<u16>::view(&buffer).unwrap(), qwt);
+}
+
+#[test]
+fn test_bit_vectors_view() {
+    let bv: BitVector = gen_sequence(100_000, 2)
+        .into_iter()
+        .map(|c| c == 1)
+        .collect();
+
+    let buffer = layout(&bv);
+    assert_eq!(*BitVector::view(&buffer).unwrap(), bv);
+
+    let rs = RSNarrow::new(bv.clone());
+    let buffer = layout(&rs);
+    let view = RSNarrow::view(&buffer).unwrap();
+    assert_eq!(*view, rs);
+    assert_eq!(view.rank1(50_000), rs.rank1(50_000));
+    assert_eq!(view.select0(1000), rs.select0(1000));
+
+    let rs = RSWide::new(bv.clone());
+    let buffer = layout(&rs);
+    let view = RSWide::view(&buffer).unwrap();
+    assert_eq!(*view, rs);
+    assert_eq!(view.select1(1000), rs.select1(1000));
+
+    let da = DArray::<true>::new(bv.clone());
+    let buffer = layout(&da);
+    let view = DArray::<true>::view(&buffer).unwrap();
+    assert_eq!(*view, da);
+    assert_eq!(view.select0(1000), da.select0(1000));
+
+    // The support for select0 must match
+    assert_eq!(
+        DArray::<false>::view(&buffer).unwrap_err(),
+        LayoutError::InvalidData("select0 support of DArray")
+    );
+    let da = DArray::<false>::new(bv.clone());
+    let buffer = layout(&da);
+    assert_eq!(*DArray::<false>::view(&buffer).unwrap(), da);
+
+    // The support for rank must match as well
+    assert_eq!(
+        DArray::<false, true>::view(&buffer).unwrap_err(),
+        LayoutError::InvalidData("rank support of DArray")
+    );
+    let da = DArray::<true, true>::new(bv);
+    let buffer = layout(&da);
+    let view = DArray::<true, true>::view(&buffer).unwrap();
+    assert_eq!(*view, da);
+    assert_eq!(view.rank1(77_777), da.rank1(77_777));
+}
+
+#[test]
+fn test_view_errors() {
+    let qwt = QWT256::try_from(gen_sequence(1000, 16)).unwrap();
+    let mut bytes = Vec::new();
+    qwt.save_layout(&mut bytes).unwrap();
+
+    // Wrong types
+    let buffer = AlignedBytes::from(&bytes[..]);
+    assert!(matches!(
+        QWT512::<u8>::view(&buffer),
+        Err(LayoutError::InvalidData(_))
+    ));
+    assert!(matches!(
+        QWT256::<u16>::view(&buffer),
+        Err(LayoutError::InvalidData(_))
+    ));
+    assert!(matches!(
+        QWT256Pfs::<u8>::view(&buffer),
+        Err(LayoutError::InvalidData(_))
+    ));
+
+    // Misaligned buffer
+    let mut shifted = vec![0];
+    shifted.extend_from_slice(&bytes);
+    let buffer = AlignedBytes::from(&shifted[..]);
+    assert_eq!(
+        QWT256::<u8>::view(&buffer[1..]).unwrap_err(),
+        LayoutError::Misaligned
+    );
+
+    // Truncated buffer
+    let buffer = AlignedBytes::from(&bytes[..bytes.len() - 100]);
+    assert_eq!(
+        QWT256::<u8>::view(&buffer).unwrap_err(),
+        LayoutError::Truncated
+    );
+
+    // Wrong magic number and version
+    let mut corrupted = bytes.clone();
+    corrupted[0] ^= 1;
+    let buffer = AlignedBytes::from(&corrupted[..]);
+    assert_eq!(
+        QWT256::<u8>::view(&buffer).unwrap_err(),
+        LayoutError::InvalidMagic
+    );
+
+    let mut corrupted = bytes;
+    corrupted[8] = 42;
+    let buffer = AlignedBytes::from(&corrupted[..]);
+    assert_eq!(
+        QWT256::<u8>::view(&buffer).unwrap_err(),
+        LayoutError::UnsupportedVersion(42)
+    );
+}
+
+#[test]
+fn test_view_invalid_data() {
+    let qwt = QWT256::try_from(vec![1u8, 0, 1, 0, 2, 3, 3, 3]).unwrap();
+    let mut bytes = Vec::new();
+    qwt.save_layout(&mut bytes).unwrap();
+
+    let buffer = AlignedBytes::from(&bytes[..]);
+    assert_eq!(
+        *unsafe { QWT256::<u8>::view_unchecked(&buffer) }.unwrap(),
+        qwt
+    );
+
+    // A length that does not match the levels, even if the view is not checked
+    let mut corrupted = bytes.clone();
+    corrupted[32..40].copy_from_slice(&(1u64 << 40).to_le_bytes());
+    let buffer = AlignedBytes::from(&corrupted[..]);
+    assert_eq!(
+        QWT256::<u8>::view(&buffer).unwrap_err(),
+        LayoutError::InvalidData("levels of the wavelet tree")
+    );
+    assert_eq!(
+        unsafe { QWT256::<u8>::view_unchecked(&buffer) }.unwrap_err(),
+        LayoutError::InvalidData("levels of the wavelet tree")
+    );
+
+    // Symbols that do not match the counters of the level
+    let qwt = QWT256::try_from(gen_sequence(1000, 16)).unwrap();
+    let mut bytes = Vec::new();
+    qwt.save_layout(&mut bytes).unwrap();
+    let mut corrupted = bytes;
+    corrupted[128] ^= 1; // The first data line of the first level
+    let buffer = AlignedBytes::from(&corrupted[..]);
+    assert!(matches!(
+        QWT256::<u8>::view(&buffer),
+        Err(LayoutError::InvalidData(_))
+    ));
+
+    // A bit vector whose number of ones does not match its bits
+    let bv: BitVector = gen_sequence(10_000, 2)
+        .into_iter()
+        .map(|c| c == 1)
+        .collect();
+    let mut bytes = Vec::new();
+    RSNarrow::new(bv).save_layout(&mut bytes).unwrap();
+    bytes[64] ^= 1; // The first data line
+    let buffer = AlignedBytes::from(&bytes[..]);
+    assert_eq!(
+        RSNarrow::view(&buffer).unwrap_err(),
+        LayoutError::InvalidData("number of ones of the bit vector")
+    );
+}
+
+#[test]
+fn test_storage_serde() {
+    // The storage is serialized as a boxed slice, so that bincode files are unchanged
+    let data: Box<[u32]> = vec![1, 2, 3, 4].into_boxed_slice();
+    let storage = Storage::from(data.clone());
+
+    let bytes = bincode::serialize(&storage).unwrap();
+    assert_eq!(bytes, bincode::serialize(&data).unwrap());
+    assert_eq!(
+        bincode::deserialize::<Storage<u32>>(&bytes).unwrap(),
+        storage
+    );
+}